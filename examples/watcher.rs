@@ -0,0 +1,14 @@
+//! Watch a wallet and print its new transactions and balance changes.
+//!
+//! ```text
+//! cargo run --example watcher --features watcher -- <base url> <descriptor> \
+//!     [--state <path>] [--interval <seconds>] [--once]
+//! ```
+
+fn main() {
+    let args = std::env::args().skip(1);
+    if let Err(e) = waterfalls_client::watcher::run(args, std::io::stdout()) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
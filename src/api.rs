@@ -1,15 +1,166 @@
+//! Types of the Waterfalls API.
+//!
+//! This module doesn't depend on any HTTP backend, so payloads delivered out-of-band can be
+//! parsed with `default-features = false`.
+
+use bitcoin::bip32::Xpub;
 pub use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::constants::ChainHash;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 pub use bitcoin::hex::FromHex;
-use bitcoin::Weight;
 pub use bitcoin::{
     transaction, Amount, BlockHash, OutPoint, ScriptBuf, Transaction, TxIn, TxOut, Txid, Witness,
 };
+use bitcoin::{Address, FeeRate, Network, Weight};
 
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A block height.
+///
+/// Serialized as a plain number, like the server sends it.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+#[serde(transparent)]
+pub struct Height(pub u32);
+
+impl Height {
+    /// The height of the genesis block
+    pub const ZERO: Height = Height(0);
+
+    /// The height as a number
+    pub fn to_u32(self) -> u32 {
+        self.0
+    }
+
+    /// The height `blocks` above this one, `None` on overflow
+    pub fn checked_add(self, blocks: u32) -> Option<Height> {
+        self.0.checked_add(blocks).map(Height)
+    }
+
+    /// The height `blocks` below this one, `None` below the genesis block
+    pub fn checked_sub(self, blocks: u32) -> Option<Height> {
+        self.0.checked_sub(blocks).map(Height)
+    }
+
+    /// The number of blocks from `earlier` to this height, `None` if `earlier` is higher
+    pub fn blocks_since(self, earlier: Height) -> Option<u32> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    /// The confirmations of a block at this height when the tip is at `tip`, zero if the
+    /// block is above the tip
+    pub fn confirmations(self, tip: Height) -> Confirmations {
+        Confirmations(tip.blocks_since(self).map_or(0, |blocks| blocks + 1))
+    }
+}
+
+impl From<u32> for Height {
+    fn from(height: u32) -> Self {
+        Height(height)
+    }
+}
+
+impl From<Height> for u32 {
+    fn from(height: Height) -> Self {
+        height.0
+    }
+}
+
+impl std::fmt::Display for Height {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A unix timestamp in seconds, such as a block time.
+///
+/// Serialized as a plain number, like the server sends it.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+#[serde(transparent)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    /// The timestamp as a number of seconds since the unix epoch
+    pub fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// The seconds from `earlier` to this timestamp, `None` if `earlier` is later
+    pub fn secs_since(self, earlier: Timestamp) -> Option<u64> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    /// The timestamp `secs` seconds later, saturating on overflow
+    pub fn saturating_add(self, secs: u64) -> Timestamp {
+        Timestamp(self.0.saturating_add(secs))
+    }
+}
+
+impl From<u32> for Timestamp {
+    fn from(timestamp: u32) -> Self {
+        Timestamp(u64::from(timestamp))
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(timestamp: u64) -> Self {
+        Timestamp(timestamp)
+    }
+}
+
+impl From<Timestamp> for u64 {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The number of confirmations of a transaction, zero if unconfirmed.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+#[serde(transparent)]
+pub struct Confirmations(pub u32);
+
+impl Confirmations {
+    /// The confirmations as a number
+    pub fn to_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if there is at least one confirmation
+    pub fn is_confirmed(self) -> bool {
+        self.0 > 0
+    }
+}
+
+impl From<Confirmations> for u32 {
+    fn from(confirmations: Confirmations) -> Self {
+        confirmations.0
+    }
+}
+
+impl std::fmt::Display for Confirmations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The key of the address histories in the responses, as returned by the Waterfalls server
+pub const ADDRESSES_KEY: &str = "addresses";
 
 /// Response from the waterfalls endpoint
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
 pub struct WaterfallResponse {
     pub txs_seen: BTreeMap<String, Vec<Vec<TxSeen>>>,
     pub page: u16,
@@ -20,33 +171,73 @@ pub struct WaterfallResponse {
     pub tip_meta: Option<BlockMeta>,
 }
 
+/// A [`WaterfallResponse`] borrowing the script keys from the JSON it's parsed from.
+///
+/// Consumers transforming a scan right away avoid allocating a `String` per key with
+/// [`WaterfallResponseRef::from_slice_borrowed`]. Keys containing JSON escape sequences can't
+/// be borrowed and fail to parse, use [`WaterfallResponse`] for those.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct WaterfallResponseRef<'a> {
+    #[serde(borrow)]
+    pub txs_seen: BTreeMap<&'a str, Vec<Vec<TxSeen>>>,
+    pub page: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tip: Option<BlockHash>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tip_meta: Option<BlockMeta>,
+}
+
+impl<'a> WaterfallResponseRef<'a> {
+    /// Parse a waterfalls response body, borrowing the script keys from `bytes`
+    #[cfg(feature = "json")]
+    pub fn from_slice_borrowed(bytes: &'a [u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    /// Copy the borrowed keys into an owned [`WaterfallResponse`]
+    pub fn into_owned(self) -> WaterfallResponse {
+        WaterfallResponse {
+            txs_seen: self
+                .txs_seen
+                .into_iter()
+                .map(|(key, scripts)| (key.to_string(), scripts))
+                .collect(),
+            page: self.page,
+            tip: self.tip,
+            tip_meta: self.tip_meta,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Ord, PartialOrd)]
 pub struct BlockMeta {
     /// The block hash
     pub b: BlockHash,
 
     /// The block timestamp
-    pub t: u32,
+    pub t: Timestamp,
 
     /// The block height
-    pub h: u32,
+    pub h: Height,
 }
 
 /// A transaction seen in the blockchain for a specific script
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct TxSeen {
     pub txid: Txid,
-    pub height: u32,
+    /// The height of the block confirming the transaction, zero if unconfirmed
+    pub height: Height,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub block_hash: Option<BlockHash>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub block_timestamp: Option<u32>,
+    pub block_timestamp: Option<Timestamp>,
     #[serde(skip_serializing_if = "V::is_undefined", default)]
     pub v: V,
 }
 
 /// Enum representing whether a transaction was seen in a vout or vin
-#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
 pub enum V {
     #[default]
     Undefined,
@@ -95,13 +286,686 @@ impl<'de> Deserialize<'de> for V {
     }
 }
 
+impl TxSeen {
+    /// Returns true if the transaction is in a block
+    pub fn is_confirmed(&self) -> bool {
+        self.height != Height::ZERO
+    }
+
+    /// The confirmations of the transaction when the tip is at `tip`
+    pub fn confirmations(&self, tip: Height) -> Confirmations {
+        if self.is_confirmed() {
+            self.height.confirmations(tip)
+        } else {
+            Confirmations(0)
+        }
+    }
+}
+
 impl WaterfallResponse {
     pub fn is_empty(&self) -> bool {
         self.txs_seen
-            .iter()
-            .flat_map(|(_, v)| v.iter())
+            .values()
+            .flat_map(|v| v.iter())
             .all(|a| a.is_empty())
     }
+
+    /// The hash of the tip the response was computed at, from `tip` or `tip_meta`
+    pub fn tip_hash(&self) -> Option<BlockHash> {
+        self.tip
+            .or_else(|| self.tip_meta.as_ref().map(|meta| meta.b))
+    }
+
+    /// Mark the transactions confirmed above `fork_height` as unconfirmed and drop a tip above
+    /// it, after the blocks above `fork_height` have been reorged out. Returns true if anything
+    /// changed.
+    pub fn rollback(&mut self, fork_height: u32) -> bool {
+        let mut changed = false;
+        for tx in self.txs_seen.values_mut().flatten().flatten() {
+            if tx.height.to_u32() > fork_height {
+                tx.height = Height::ZERO;
+                tx.block_hash = None;
+                tx.block_timestamp = None;
+                changed = true;
+            }
+        }
+        if self
+            .tip_meta
+            .as_ref()
+            .map_or(false, |meta| meta.h.to_u32() > fork_height)
+        {
+            self.tip_meta = None;
+            self.tip = None;
+            changed = true;
+        }
+        changed
+    }
+
+    /// The unique txids seen in this response, in order of appearance
+    pub fn txids(&self) -> Vec<Txid> {
+        let mut seen = std::collections::HashSet::new();
+        self.txs_seen
+            .values()
+            .flatten()
+            .flatten()
+            .map(|tx| tx.txid)
+            .filter(|txid| seen.insert(*txid))
+            .collect()
+    }
+
+    /// The unique txids with an entry unconfirmed or confirmed above `watermark`, in order of
+    /// appearance, e.g. the ones an incremental sync hasn't seen final yet
+    pub fn txids_above(&self, watermark: Height) -> Vec<Txid> {
+        let mut seen = std::collections::HashSet::new();
+        self.txs_seen
+            .values()
+            .flatten()
+            .flatten()
+            .filter(|tx| !tx.is_confirmed() || tx.height > watermark)
+            .map(|tx| tx.txid)
+            .filter(|txid| seen.insert(*txid))
+            .collect()
+    }
+
+    /// Report the unused index gaps of the branch `key`, assuming the scripts of the branch are
+    /// ordered by derivation index from zero as returned by a descriptor scan.
+    ///
+    /// Returns `None` if the response has no branch `key`.
+    pub fn gap_report(&self, key: &str, gap_limit: u32) -> Option<GapReport> {
+        let scripts = self.txs_seen.get(key)?;
+        let mut gaps = vec![];
+        let mut last_used = None;
+        for (index, history) in scripts.iter().enumerate() {
+            let index = index as u32;
+            if history.is_empty() {
+                continue;
+            }
+            let first_unused = last_used.map_or(0, |last| last + 1);
+            if index > first_unused {
+                gaps.push((first_unused, index - first_unused));
+            }
+            last_used = Some(index);
+        }
+        let largest_gap = gaps.iter().map(|(_, len)| *len).max().unwrap_or(0);
+        Some(GapReport {
+            key: key.to_string(),
+            scanned: scripts.len() as u32,
+            last_used,
+            gaps,
+            largest_gap,
+            gap_limit_at_risk: largest_gap > 0 && largest_gap * 2 >= gap_limit,
+        })
+    }
+
+    /// The `to_index` to scan up to so that every branch has `gap_limit` unused scripts after
+    /// its last used one, or `None` if the response already covers them
+    pub fn required_to_index(&self, gap_limit: u32) -> Option<u32> {
+        let mut required = None;
+        for scripts in self.txs_seen.values() {
+            let first_unused = scripts
+                .iter()
+                .rposition(|history| !history.is_empty())
+                .map_or(0, |last| last as u32 + 1);
+            let needed = first_unused + gap_limit;
+            if needed > scripts.len() as u32 {
+                required = required.max(Some(needed - 1));
+            }
+        }
+        required
+    }
+
+    /// The highest derivation index the server scanned in every branch, `None` if the
+    /// response has no scripts
+    pub fn scanned_to_index(&self) -> Option<u32> {
+        let scanned = self.txs_seen.values().map(|scripts| scripts.len() as u32);
+        scanned.min().and_then(|len| len.checked_sub(1))
+    }
+
+    /// The scripts of the receive and change branches of a descriptor scan, see [`Keychain`].
+    ///
+    /// Branches whose key isn't a `/0/*` or `/1/*` derivation, such as `addresses`, are
+    /// skipped.
+    pub fn keychains(&self) -> BTreeMap<Keychain, KeychainScan> {
+        let mut keychains = BTreeMap::new();
+        for (key, scripts) in &self.txs_seen {
+            if let Some(keychain) = Keychain::from_key(key) {
+                let last_used = scripts
+                    .iter()
+                    .rposition(|history| !history.is_empty())
+                    .map(|index| index as u32);
+                keychains.insert(
+                    keychain,
+                    KeychainScan {
+                        key: key.clone(),
+                        scripts: scripts.clone(),
+                        last_used,
+                    },
+                );
+            }
+        }
+        keychains
+    }
+
+    /// Compute the [`WalletSummary`] of the response given the transactions of
+    /// [`Self::txids`], e.g. the ones returned by hydrating the response
+    pub fn summary(&self, txs: &BTreeMap<Txid, Transaction>) -> WalletSummary {
+        let utxos = unspent(
+            self.txs_seen
+                .iter()
+                .map(|(key, scripts)| (key.as_str(), scripts)),
+            txs,
+            self.tip_meta.as_ref().map(|meta| meta.h),
+        );
+        WalletSummary {
+            balance: utxos.iter().map(|utxo| utxo.txout.value).sum(),
+            utxos,
+            last_used: self
+                .keychains()
+                .into_iter()
+                .filter_map(|(keychain, scan)| Some((keychain, scan.last_used?)))
+                .collect(),
+            tip: self.tip_meta.clone(),
+            tx_count: self.txids().len(),
+        }
+    }
+
+    /// The [`GapReport`] of every branch, see [`Self::gap_report`]
+    pub fn gap_reports(&self, gap_limit: u32) -> Vec<GapReport> {
+        self.txs_seen
+            .keys()
+            .filter_map(|key| self.gap_report(key, gap_limit))
+            .collect()
+    }
+
+    /// Pagination metadata of this response given the server `page_size`.
+    ///
+    /// The server stops deriving scripts once the gap limit is reached, so a page returning
+    /// fewer than `page_size` scripts for every key is the last one; a full page means more
+    /// pages may follow.
+    pub fn pagination(&self, page_size: usize) -> Pagination {
+        Pagination {
+            page: self.page,
+            has_more: page_size > 0 && self.txs_seen.values().any(|s| s.len() >= page_size),
+            page_size,
+        }
+    }
+
+    /// Put the history of every script in canonical order and remove duplicates.
+    ///
+    /// Confirmed transactions come first by ascending height, followed by unconfirmed ones
+    /// (height 0), ties are ordered by txid and then by [`V`] with `Undefined` before `Vin`
+    /// before `Vout`. Entries with the same txid and `V` are duplicates, only the first in
+    /// canonical order is kept, so a confirmed entry wins over an unconfirmed one.
+    pub fn normalize(&mut self) {
+        for history in self.txs_seen.values_mut().flatten() {
+            history.sort_by(|a, b| {
+                (a.height == Height::ZERO, a.height, a.txid, &a.v).cmp(&(
+                    b.height == Height::ZERO,
+                    b.height,
+                    b.txid,
+                    &b.v,
+                ))
+            });
+            let mut seen = BTreeSet::new();
+            history.retain(|tx| seen.insert((tx.txid, tx.v.clone())));
+        }
+    }
+
+    /// A stable digest of the [normalized](Self::normalize) script histories.
+    ///
+    /// The digest doesn't depend on the order of the transactions within a history nor on
+    /// `page`, `tip` and `tip_meta`, so polling the same wallet state after new blocks gives the
+    /// same digest and a wallet can skip re-processing an unchanged response.
+    pub fn content_hash(&self) -> sha256::Hash {
+        let mut normalized = self.clone();
+        normalized.normalize();
+        let mut engine = sha256::Hash::engine();
+        fn input_len(engine: &mut sha256::HashEngine, len: usize) {
+            engine.input(&(len as u64).to_le_bytes())
+        }
+        input_len(&mut engine, normalized.txs_seen.len());
+        for (key, scripts) in &normalized.txs_seen {
+            input_len(&mut engine, key.len());
+            engine.input(key.as_bytes());
+            input_len(&mut engine, scripts.len());
+            for history in scripts {
+                input_len(&mut engine, history.len());
+                for tx in history {
+                    engine.input(tx.txid.as_byte_array());
+                    engine.input(&tx.height.0.to_le_bytes());
+                    match tx.block_hash {
+                        Some(hash) => {
+                            engine.input(&[1]);
+                            engine.input(hash.as_byte_array());
+                        }
+                        None => engine.input(&[0]),
+                    }
+                    match tx.block_timestamp {
+                        Some(timestamp) => {
+                            engine.input(&[1]);
+                            // Encoded on 4 bytes as sent by the server, keeping digests stable
+                            engine.input(&(timestamp.0 as u32).to_le_bytes());
+                        }
+                        None => engine.input(&[0]),
+                    }
+                    match tx.v {
+                        V::Undefined => engine.input(&[0]),
+                        V::Vin(n) => {
+                            engine.input(&[1]);
+                            engine.input(&n.to_le_bytes());
+                        }
+                        V::Vout(n) => {
+                            engine.input(&[2]);
+                            engine.input(&n.to_le_bytes());
+                        }
+                    }
+                }
+            }
+        }
+        sha256::Hash::from_engine(engine)
+    }
+
+    /// Merge `other` into `self`.
+    ///
+    /// Script histories under the same key are appended, the highest page is kept and the tip
+    /// with the greatest height wins, `tip` and `tip_meta` are taken from the same response. The
+    /// result is [normalized](Self::normalize), so merging
+    /// the same pages or chunks always gives the same response.
+    pub fn merge(&mut self, other: WaterfallResponse) {
+        for (key, scripts) in other.txs_seen {
+            self.txs_seen.entry(key).or_default().extend(scripts);
+        }
+        self.normalize();
+        self.page = self.page.max(other.page);
+        let other_is_higher = match (&self.tip_meta, &other.tip_meta) {
+            (Some(current), Some(other)) => other.h > current.h,
+            (None, Some(_)) => true,
+            (Some(_), None) => false,
+            (None, None) => self.tip.is_none(),
+        };
+        if other_is_higher {
+            self.tip = other.tip;
+            self.tip_meta = other.tip_meta;
+        }
+    }
+}
+
+/// The family of the chain served by a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChainFamily {
+    /// Bitcoin and its test networks
+    Bitcoin,
+    /// Elements based chains such as Liquid, whose headers aren't Bitcoin headers
+    Elements,
+}
+
+/// The network of a server, identified by its genesis block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NetworkInfo {
+    /// The Bitcoin network, `None` for Elements chains and custom signets
+    pub network: Option<Network>,
+    /// The hash of the block at height zero
+    pub genesis_hash: BlockHash,
+    /// The family of the chain
+    pub family: ChainFamily,
+}
+
+impl NetworkInfo {
+    /// Identify the network of a chain of `family` with the given genesis block hash
+    pub fn new(genesis_hash: BlockHash, family: ChainFamily) -> Self {
+        let network = match family {
+            ChainFamily::Bitcoin => {
+                Network::from_chain_hash(ChainHash::from_genesis_block_hash(genesis_hash))
+            }
+            ChainFamily::Elements => None,
+        };
+        NetworkInfo {
+            network,
+            genesis_hash,
+            family,
+        }
+    }
+}
+
+/// The script type of the standard single-signature descriptors, see [`ScriptKind::descriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptKind {
+    /// Native segwit, `wpkh(...)`
+    Wpkh,
+    /// Taproot key path, `tr(...)`
+    Tr,
+    /// Nested segwit, `sh(wpkh(...))`
+    ShWpkh,
+}
+
+impl ScriptKind {
+    /// The descriptor deriving the external and internal addresses of `xpub`, with the
+    /// `<0;1>` multipath
+    pub fn descriptor(&self, xpub: &Xpub) -> String {
+        match self {
+            ScriptKind::Wpkh => format!("wpkh({xpub}/<0;1>/*)"),
+            ScriptKind::Tr => format!("tr({xpub}/<0;1>/*)"),
+            ScriptKind::ShWpkh => format!("sh(wpkh({xpub}/<0;1>/*))"),
+        }
+    }
+}
+
+/// The branch of a wallet descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Keychain {
+    /// The receive addresses, derived at `/0/*`
+    External,
+    /// The change addresses, derived at `/1/*`
+    Internal,
+}
+
+impl Keychain {
+    /// The keychain of a key of [`WaterfallResponse::txs_seen`], which the server sets to the
+    /// single path descriptor of the branch, e.g. `wpkh(xpub/1/*)#checksum`
+    pub fn from_key(key: &str) -> Option<Keychain> {
+        let descriptor = key.split('#').next().unwrap_or_default();
+        if descriptor.contains("/0/*") {
+            Some(Keychain::External)
+        } else if descriptor.contains("/1/*") {
+            Some(Keychain::Internal)
+        } else {
+            None
+        }
+    }
+}
+
+/// The scripts of one [`Keychain`] in a [`WaterfallResponse`], see
+/// [`WaterfallResponse::keychains`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeychainScan {
+    /// The key of the branch in [`WaterfallResponse::txs_seen`]
+    pub key: String,
+    /// The history of every script, by derivation index
+    pub scripts: Vec<Vec<TxSeen>>,
+    /// The highest index with history
+    pub last_used: Option<u32>,
+}
+
+impl KeychainScan {
+    /// The first index after the last used one, where the next address should be derived
+    pub fn next_index(&self) -> u32 {
+        self.last_used.map_or(0, |last| last + 1)
+    }
+
+    /// The unique txids of the branch, in order of appearance
+    pub fn txids(&self) -> Vec<Txid> {
+        let mut seen = BTreeSet::new();
+        self.scripts
+            .iter()
+            .flatten()
+            .map(|tx| tx.txid)
+            .filter(|txid| seen.insert(*txid))
+            .collect()
+    }
+
+    /// The value of the unspent outputs of the branch, given the transactions of
+    /// [`Self::txids`].
+    ///
+    /// Only entries with a known [`V`], as sent by the v4 endpoint, are counted; outputs of
+    /// transactions missing from `txs` are skipped.
+    pub fn balance(&self, txs: &BTreeMap<Txid, Transaction>) -> Amount {
+        unspent([(self.key.as_str(), &self.scripts)], txs, None)
+            .iter()
+            .map(|utxo| utxo.txout.value)
+            .sum()
+    }
+}
+
+/// An unspent output of a wallet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Utxo {
+    /// The output
+    pub outpoint: OutPoint,
+    /// The value and script of the output
+    pub txout: TxOut,
+    /// The keychain of the script, `None` for address scans and non standard paths
+    pub keychain: Option<Keychain>,
+    /// The derivation index of the script
+    pub index: u32,
+    /// The height of the block confirming the output, zero if unconfirmed
+    pub height: Height,
+    /// Number of blocks confirming the output, zero if unconfirmed or if the tip is unknown
+    pub confirmations: Confirmations,
+    /// Whether the output is created by a coinbase transaction, spendable only after 100
+    /// confirmations
+    pub is_coinbase: bool,
+    /// Whether the script received funds in more than one transaction
+    pub reused: bool,
+}
+
+/// The outputs received by the scripts of `branches` not spent by their transactions.
+///
+/// Only entries with a known [`V`] are considered; outputs of transactions missing from `txs`
+/// are skipped.
+fn unspent<'a>(
+    branches: impl IntoIterator<Item = (&'a str, &'a Vec<Vec<TxSeen>>)>,
+    txs: &BTreeMap<Txid, Transaction>,
+    tip: Option<Height>,
+) -> Vec<Utxo> {
+    let mut received = vec![];
+    let mut spent = BTreeSet::new();
+    for (key, scripts) in branches {
+        let keychain = Keychain::from_key(key);
+        for (index, history) in scripts.iter().enumerate() {
+            let receiving: BTreeSet<Txid> = history
+                .iter()
+                .filter(|tx| matches!(tx.v, V::Vout(_)))
+                .map(|tx| tx.txid)
+                .collect();
+            let reused = receiving.len() > 1;
+            for tx in history {
+                match tx.v {
+                    V::Vout(vout) => {
+                        let outpoint = OutPoint::new(tx.txid, vout);
+                        received.push((outpoint, keychain, index as u32, tx.height, reused));
+                    }
+                    V::Vin(vin) => {
+                        let input = txs.get(&tx.txid).and_then(|t| t.input.get(vin as usize));
+                        if let Some(input) = input {
+                            spent.insert(input.previous_output);
+                        }
+                    }
+                    V::Undefined => {}
+                }
+            }
+        }
+    }
+    let mut seen = BTreeSet::new();
+    received
+        .into_iter()
+        .filter(|(outpoint, ..)| !spent.contains(outpoint) && seen.insert(*outpoint))
+        .filter_map(|(outpoint, keychain, index, height, reused)| {
+            let tx = txs.get(&outpoint.txid)?;
+            let txout = tx.output.get(outpoint.vout as usize)?;
+            let confirmations = match tip {
+                Some(tip) if height != Height::ZERO => height.confirmations(tip),
+                _ => Confirmations(0),
+            };
+            Some(Utxo {
+                outpoint,
+                txout: txout.clone(),
+                keychain,
+                index,
+                height,
+                confirmations,
+                is_coinbase: tx.is_coinbase(),
+                reused,
+            })
+        })
+        .collect()
+}
+
+/// The state of a wallet computed from a scan, see [`WaterfallResponse::summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletSummary {
+    /// The value of the unspent outputs, confirmed or not
+    pub balance: Amount,
+    /// The unspent outputs
+    pub utxos: Vec<Utxo>,
+    /// The highest used index of each keychain with history
+    pub last_used: BTreeMap<Keychain, u32>,
+    /// The tip the scan was computed at
+    pub tip: Option<BlockMeta>,
+    /// Number of distinct transactions of the wallet
+    pub tx_count: usize,
+}
+
+/// Usage of the derivation indexes of one descriptor branch in a [`WaterfallResponse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GapReport {
+    /// The key of the branch in [`WaterfallResponse::txs_seen`]
+    pub key: String,
+    /// Number of derived scripts in the response
+    pub scanned: u32,
+    /// The highest index with history
+    pub last_used: Option<u32>,
+    /// Runs of unused indexes followed by a used one, as `(first unused index, length)`
+    pub gaps: Vec<(u32, u32)>,
+    /// The length of the longest run in `gaps`, zero if none
+    pub largest_gap: u32,
+    /// Whether `largest_gap` reaches half of the gap limit the report was made with.
+    ///
+    /// Such gaps suggest the wallet was used by software with a larger gap limit, so funds
+    /// past the scanned indexes might be missed.
+    pub gap_limit_at_risk: bool,
+}
+
+/// Pagination metadata of a [`WaterfallResponse`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    /// The page number of the response
+    pub page: u16,
+    /// Whether the server may have more pages for the same query
+    pub has_more: bool,
+    /// The page size, in scripts per descriptor branch, used to infer `has_more`
+    pub page_size: usize,
+}
+
+/// The progress of a paginated descriptor scan, used to resume it after a failure.
+///
+/// Pages are requested in order and merged into `response`, so a failure at page `N` keeps
+/// pages `0..N` and a later call resumes from `next_page`. The cursor can be serialized to
+/// resume a scan across restarts.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ScanCursor {
+    /// The scanned descriptor
+    pub descriptor: String,
+    /// The page size, in scripts per descriptor branch, see [`WaterfallResponse::pagination`]
+    pub page_size: usize,
+    /// The next page to request
+    pub next_page: u32,
+    /// Whether the last page was received
+    pub complete: bool,
+    /// The merged pages received so far
+    pub response: WaterfallResponse,
+}
+
+impl ScanCursor {
+    /// Start a scan of `descriptor` from the first page
+    pub fn new(descriptor: &str, page_size: usize) -> Self {
+        ScanCursor {
+            descriptor: descriptor.to_string(),
+            page_size,
+            next_page: 0,
+            complete: false,
+            response: WaterfallResponse::default(),
+        }
+    }
+
+    /// Merge the response for `next_page` and advance the cursor
+    pub fn advance(&mut self, page_response: WaterfallResponse) {
+        let pagination = page_response.pagination(self.page_size);
+        self.response.merge(page_response);
+        if pagination.has_more {
+            self.next_page += 1;
+        } else {
+            self.complete = true;
+        }
+    }
+}
+
+/// How the transactions of a scan are fetched along with the outputs their inputs spend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HydrationStrategy {
+    /// Request the verbose transaction, which includes the previous outputs, falling back to
+    /// the raw transactions for each transaction the verbose endpoint doesn't return. Other
+    /// errors are reported for the transaction without falling back.
+    VerboseFirst,
+    /// Request the raw transaction and the raw parent of every input
+    RawFirst,
+    /// Request the verbose transaction until the server doesn't return one, then use the raw
+    /// transactions for the rest of the scan
+    #[default]
+    Auto,
+}
+
+/// A transaction with the outputs spent by its inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HydratedTx {
+    /// The transaction
+    pub tx: Transaction,
+    /// The output spent by every input, in order, `None` for coinbase inputs
+    pub prevouts: Vec<Option<TxOut>>,
+}
+
+impl HydratedTx {
+    /// The fee paid by the transaction, `None` if a previous output is unknown
+    pub fn fee(&self) -> Option<Amount> {
+        let inputs = self
+            .prevouts
+            .iter()
+            .map(|prevout| prevout.as_ref().map(|p| p.value))
+            .sum::<Option<Amount>>()?;
+        let outputs = self.tx.output.iter().map(|o| o.value).sum();
+        inputs.checked_sub(outputs)
+    }
+}
+
+impl From<Tx> for HydratedTx {
+    fn from(tx: Tx) -> Self {
+        HydratedTx {
+            tx: tx.to_tx(),
+            prevouts: tx.previous_outputs(),
+        }
+    }
+}
+
+/// A waterfalls query mixing a descriptor with standalone addresses (e.g. imported keys).
+///
+/// Clients resolve it with the minimal number of server calls and return a single merged
+/// [`WaterfallResponse`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WaterfallsQuery {
+    /// The descriptor to scan, if any.
+    pub descriptor: Option<String>,
+    /// Addresses to scan in addition to the descriptor.
+    pub addresses: Vec<Address>,
+}
+
+impl WaterfallsQuery {
+    /// Create a query for the given descriptor
+    pub fn descriptor(descriptor: &str) -> Self {
+        WaterfallsQuery {
+            descriptor: Some(descriptor.to_string()),
+            addresses: vec![],
+        }
+    }
+
+    /// Add addresses to the query
+    pub fn with_addresses(mut self, addresses: &[Address]) -> Self {
+        self.addresses.extend_from_slice(addresses);
+        self
+    }
+
+    /// Returns true if the query has neither a descriptor nor addresses
+    pub fn is_empty(&self) -> bool {
+        self.descriptor.is_none() && self.addresses.is_empty()
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -117,7 +981,7 @@ pub struct Vin {
     // None if coinbase
     pub prevout: Option<PrevOut>,
     pub scriptsig: ScriptBuf,
-    #[serde(deserialize_with = "deserialize_witness", default)]
+    #[serde(with = "serde_helpers::witness", default)]
     pub witness: Vec<Vec<u8>>,
     pub sequence: u32,
     pub is_coinbase: bool,
@@ -132,14 +996,14 @@ pub struct Vout {
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct TxStatus {
     pub confirmed: bool,
-    pub block_height: Option<u32>,
+    pub block_height: Option<Height>,
     pub block_hash: Option<BlockHash>,
-    pub block_time: Option<u64>,
+    pub block_time: Option<Timestamp>,
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct MerkleProof {
-    pub block_height: u32,
+    pub block_height: Height,
     pub merkle: Vec<Txid>,
     pub pos: usize,
 }
@@ -152,10 +1016,35 @@ pub struct OutputStatus {
     pub status: Option<TxStatus>,
 }
 
+impl OutputStatus {
+    /// The input spending the output, `None` if unspent
+    pub fn spend(&self) -> Option<Spend> {
+        if !self.spent {
+            return None;
+        }
+        Some(Spend {
+            txid: self.txid?,
+            vin: u32::try_from(self.vin?).ok()?,
+            height: self.status.as_ref().and_then(|status| status.block_height),
+        })
+    }
+}
+
+/// The input spending an output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spend {
+    /// The spending transaction
+    pub txid: Txid,
+    /// The index of the spending input
+    pub vin: u32,
+    /// The height of the block confirming the spending transaction, `None` if unconfirmed
+    pub height: Option<Height>,
+}
+
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct BlockStatus {
     pub in_best_chain: bool,
-    pub height: Option<u32>,
+    pub height: Option<Height>,
     pub next_best: Option<BlockHash>,
 }
 
@@ -176,8 +1065,8 @@ pub struct Tx {
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct BlockTime {
-    pub timestamp: u64,
-    pub height: u32,
+    pub timestamp: Timestamp,
+    pub height: Height,
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
@@ -279,15 +1168,234 @@ impl Tx {
     pub fn fee(&self) -> Amount {
         Amount::from_sat(self.fee)
     }
+
+    /// The minimum fee of a transaction of `replacement_weight` replacing this unconfirmed one
+    /// under the BIP125 rules, paying at least `target`.
+    ///
+    /// The replacement pays for the fees of the replaced transaction and of its descendants,
+    /// known only with its `entry`, plus [`INCREMENTAL_RELAY_FEERATE`] for its own size, and
+    /// has a higher feerate than the replaced transaction.
+    pub fn min_replacement_fee(
+        &self,
+        entry: Option<&MempoolEntry>,
+        target: FeeRate,
+        replacement_weight: Weight,
+    ) -> Amount {
+        let replaced = entry.map_or(self.fee, |entry| entry.descendant_fees.max(entry.fee));
+        let relay =
+            Amount::from_sat(replaced) + fee_for(INCREMENTAL_RELAY_FEERATE, replacement_weight);
+        let original_rate =
+            FeeRate::from_sat_per_kwu(self.fee.saturating_mul(1000) / self.weight.max(1));
+        let higher_rate = fee_for(original_rate, replacement_weight) + Amount::from_sat(1);
+        relay
+            .max(higher_rate)
+            .max(fee_for(target, replacement_weight))
+    }
+}
+
+/// The mempool data of an unconfirmed transaction and of its in-mempool ancestors and
+/// descendants, sizes are in virtual bytes and fees in satoshi.
+///
+/// The ancestor and descendant values include the transaction itself.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MempoolEntry {
+    /// Transaction weight units
+    pub weight: u64,
+    /// Fee of the transaction
+    pub fee: u64,
+    /// Number of unconfirmed ancestors
+    pub ancestor_count: u64,
+    /// Size of the unconfirmed ancestors
+    pub ancestor_size: u64,
+    /// Fees of the unconfirmed ancestors
+    pub ancestor_fees: u64,
+    /// Number of unconfirmed descendants
+    pub descendant_count: u64,
+    /// Size of the unconfirmed descendants
+    pub descendant_size: u64,
+    /// Fees of the unconfirmed descendants
+    pub descendant_fees: u64,
+}
+
+/// The state of the mempool, as returned by the Esplora `/mempool` endpoint.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MempoolInfo {
+    /// Number of transactions
+    pub count: u64,
+    /// Size of the transactions in virtual bytes
+    pub vsize: u64,
+    /// Fees of the transactions in satoshi
+    pub total_fee: u64,
+    /// Buckets of `(sat/vB, vsize)` by decreasing fee rate, each with the size of the
+    /// transactions paying at least its fee rate and less than the previous bucket's
+    pub fee_histogram: Vec<(f64, u64)>,
+}
+
+impl MempoolEntry {
+    pub fn weight(&self) -> Weight {
+        Weight::from_wu(self.weight)
+    }
+
+    pub fn fee(&self) -> Amount {
+        Amount::from_sat(self.fee)
+    }
+
+    /// The feerate of the transaction with its unconfirmed ancestors, the one miners see when
+    /// selecting the package
+    pub fn ancestor_feerate(&self) -> FeeRate {
+        package_feerate(self.ancestor_fees, self.ancestor_size)
+    }
+
+    /// The feerate of the transaction with its unconfirmed descendants
+    pub fn descendant_feerate(&self) -> FeeRate {
+        package_feerate(self.descendant_fees, self.descendant_size)
+    }
+
+    /// The fee a child of `child_vsize` virtual bytes must pay so that the package made of
+    /// the transaction, its ancestors and the child reaches `target`
+    pub fn cpfp_fee(&self, target: FeeRate, child_vsize: u64) -> Amount {
+        let package = Weight::from_vb_unchecked(self.ancestor_size + child_vsize);
+        fee_for(target, package)
+            .checked_sub(Amount::from_sat(self.ancestor_fees))
+            .unwrap_or(Amount::ZERO)
+    }
 }
 
-fn deserialize_witness<'de, D>(d: D) -> Result<Vec<Vec<u8>>, D::Error>
-where
-    D: serde::de::Deserializer<'de>,
-{
-    let list = Vec::<String>::deserialize(d)?;
-    list.into_iter()
-        .map(|hex_str| Vec::<u8>::from_hex(&hex_str))
-        .collect::<Result<Vec<Vec<u8>>, _>>()
-        .map_err(serde::de::Error::custom)
+fn package_feerate(fees: u64, vsize: u64) -> FeeRate {
+    let weight = Weight::from_vb_unchecked(vsize).to_wu().max(1);
+    FeeRate::from_sat_per_kwu(fees.saturating_mul(1000) / weight)
+}
+
+/// The fee paying at least `rate` for `weight`, rounded up
+pub(crate) fn fee_for(rate: FeeRate, weight: Weight) -> Amount {
+    let kwu = rate.to_sat_per_kwu().saturating_mul(weight.to_wu());
+    Amount::from_sat(kwu.saturating_add(999) / 1000)
+}
+
+/// The default incremental relay feerate of Bitcoin Core, the feerate a replacement must pay
+/// for its own size on top of the fees it replaces
+pub const INCREMENTAL_RELAY_FEERATE: FeeRate = FeeRate::from_sat_per_kwu(250);
+
+/// The default minimum relay feerate of Bitcoin Core, 1 sat/vB
+pub const DEFAULT_MIN_RELAY_FEERATE: FeeRate = FeeRate::from_sat_per_kwu(250);
+
+/// The maximum weight of a transaction relayed by Bitcoin Core
+pub const MAX_STANDARD_TX_WEIGHT: Weight = Weight::from_wu(400_000);
+
+/// The mempool policy of the node behind a server, each limit is `None` if the server doesn't
+/// expose it.
+///
+/// The accessors of the limits fall back to the Bitcoin Core defaults.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelayPolicy {
+    /// The minimum feerate of a relayed transaction, `minrelaytxfee` in Bitcoin Core
+    pub min_relay_feerate: Option<FeeRate>,
+    /// The feerate a replacement pays for its own size, `incrementalrelayfee` in Bitcoin Core
+    pub incremental_relay_feerate: Option<FeeRate>,
+    /// The maximum weight of a relayed transaction
+    pub max_standard_weight: Option<Weight>,
+}
+
+impl RelayPolicy {
+    /// The minimum relay feerate, [`DEFAULT_MIN_RELAY_FEERATE`] if unknown
+    pub fn min_relay_feerate(&self) -> FeeRate {
+        self.min_relay_feerate.unwrap_or(DEFAULT_MIN_RELAY_FEERATE)
+    }
+
+    /// The incremental relay feerate, [`INCREMENTAL_RELAY_FEERATE`] if unknown
+    pub fn incremental_relay_feerate(&self) -> FeeRate {
+        self.incremental_relay_feerate
+            .unwrap_or(INCREMENTAL_RELAY_FEERATE)
+    }
+
+    /// The maximum standard weight, [`MAX_STANDARD_TX_WEIGHT`] if unknown
+    pub fn max_standard_weight(&self) -> Weight {
+        self.max_standard_weight.unwrap_or(MAX_STANDARD_TX_WEIGHT)
+    }
+
+    /// Check that `transaction` paying `fee` would be relayed under this policy
+    pub fn check(&self, transaction: &Transaction, fee: Amount) -> Result<(), PolicyViolation> {
+        let weight = transaction.weight();
+        let max = self.max_standard_weight();
+        if weight > max {
+            return Err(PolicyViolation::WeightAboveStandard { weight, max });
+        }
+        let min = fee_for(self.min_relay_feerate(), weight);
+        if fee < min {
+            return Err(PolicyViolation::FeeBelowMinRelay { fee, min });
+        }
+        Ok(())
+    }
+}
+
+/// The reason a transaction wouldn't be relayed under a [`RelayPolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The transaction is heavier than the maximum standard weight
+    WeightAboveStandard { weight: Weight, max: Weight },
+    /// The fee is lower than the minimum relay feerate requires
+    FeeBelowMinRelay { fee: Amount, min: Amount },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Serde adapters for the encodings used by the Waterfalls and Esplora APIs.
+///
+/// Each module has a `serialize` and a `deserialize` function, so it can be used with
+/// `#[serde(with = "...")]` by downstream types persisting or extending the API types.
+pub mod serde_helpers {
+    /// A `Vec<Vec<u8>>` as a list of hex strings, as witnesses are encoded
+    pub mod witness {
+        use bitcoin::hex::{DisplayHex, FromHex};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(witness: &[Vec<u8>], s: S) -> Result<S::Ok, S::Error> {
+            s.collect_seq(witness.iter().map(|item| item.to_lower_hex_string()))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<u8>>, D::Error> {
+            let list = Vec::<String>::deserialize(d)?;
+            list.into_iter()
+                .map(|hex_str| Vec::<u8>::from_hex(&hex_str))
+                .collect::<Result<Vec<Vec<u8>>, _>>()
+                .map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// A `Vec<u8>` as a hex string
+    pub mod hex_bytes {
+        use bitcoin::hex::{DisplayHex, FromHex};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(bytes: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&bytes.to_lower_hex_string())
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+            let hex_str = String::deserialize(d)?;
+            Vec::<u8>::from_hex(&hex_str).map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// Any consensus encodable type, such as a [`bitcoin::Transaction`], as the hex string of
+    /// its consensus encoding
+    pub mod consensus_hex {
+        use bitcoin::consensus::{encode, Decodable, Encodable};
+        use bitcoin::hex::{DisplayHex, FromHex};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<T: Encodable, S: Serializer>(value: &T, s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_str(&encode::serialize(value).to_lower_hex_string())
+        }
+
+        pub fn deserialize<'de, T: Decodable, D: Deserializer<'de>>(d: D) -> Result<T, D::Error> {
+            let hex_str = String::deserialize(d)?;
+            let bytes = Vec::<u8>::from_hex(&hex_str).map_err(serde::de::Error::custom)?;
+            encode::deserialize(&bytes).map_err(serde::de::Error::custom)
+        }
+    }
 }
@@ -0,0 +1,236 @@
+//! Contiguous storage of the script histories of a scan.
+//!
+//! A [`WaterfallArena`] is parsed like a [`WaterfallResponse`] but stores every [`TxSeen`] of
+//! the response in a single buffer, with the histories and keys as index ranges into it. An
+//! indexer parsing responses with hundreds of thousands of entries makes a few large
+//! allocations instead of one per script, and walks the entries in memory order.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+use bitcoin::BlockHash;
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use crate::{BlockMeta, TxSeen, WaterfallResponse};
+
+/// The script histories of a waterfalls response in one buffer, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WaterfallArena {
+    /// Every entry, script after script
+    entries: Vec<TxSeen>,
+    /// The end of the history of each script in `entries`, it starts at the end of the previous
+    histories: Vec<usize>,
+    /// The range of the scripts of each key in `histories`
+    keys: BTreeMap<String, Range<usize>>,
+    pub page: u16,
+    pub tip: Option<BlockHash>,
+    pub tip_meta: Option<BlockMeta>,
+}
+
+impl WaterfallArena {
+    /// The number of [`TxSeen`] entries of every script
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no script has history
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every [`TxSeen`] entry, ordered by key, script and position in the history
+    pub fn entries(&self) -> &[TxSeen] {
+        &self.entries
+    }
+
+    /// The keys of the response, descriptors or `addresses`, in order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(String::as_str)
+    }
+
+    /// The histories of the scripts of `key` by derivation index, like
+    /// [`WaterfallResponse::txs_seen`]
+    pub fn scripts(&self, key: &str) -> Option<impl Iterator<Item = &[TxSeen]>> {
+        let range = self.keys.get(key)?.clone();
+        Some(range.map(move |script| self.history_at(script)))
+    }
+
+    /// The history of the script of `key` at `index`
+    pub fn history(&self, key: &str, index: usize) -> Option<&[TxSeen]> {
+        let range = self.keys.get(key)?;
+        let script = range.start.checked_add(index).filter(|s| *s < range.end)?;
+        Some(self.history_at(script))
+    }
+
+    /// Every key with the histories of its scripts
+    pub fn iter(&self) -> impl Iterator<Item = (&str, impl Iterator<Item = &[TxSeen]>)> {
+        self.keys.iter().map(move |(key, range)| {
+            let range = range.clone();
+            (
+                key.as_str(),
+                range.map(move |script| self.history_at(script)),
+            )
+        })
+    }
+
+    fn history_at(&self, script: usize) -> &[TxSeen] {
+        let start = match script {
+            0 => 0,
+            _ => self.histories[script - 1],
+        };
+        &self.entries[start..self.histories[script]]
+    }
+
+    /// Convert into the standard [`WaterfallResponse`]
+    pub fn to_response(&self) -> WaterfallResponse {
+        WaterfallResponse {
+            txs_seen: self
+                .iter()
+                .map(|(key, scripts)| (key.to_string(), scripts.map(<[_]>::to_vec).collect()))
+                .collect(),
+            page: self.page,
+            tip: self.tip,
+            tip_meta: self.tip_meta.clone(),
+        }
+    }
+}
+
+impl From<&WaterfallResponse> for WaterfallArena {
+    fn from(response: &WaterfallResponse) -> Self {
+        let mut arena = WaterfallArena {
+            page: response.page,
+            tip: response.tip,
+            tip_meta: response.tip_meta.clone(),
+            ..Default::default()
+        };
+        for (key, scripts) in &response.txs_seen {
+            let start = arena.histories.len();
+            for history in scripts {
+                arena.entries.extend_from_slice(history);
+                arena.histories.push(arena.entries.len());
+            }
+            arena.keys.insert(key.clone(), start..arena.histories.len());
+        }
+        arena
+    }
+}
+
+impl<'de> Deserialize<'de> for WaterfallArena {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(ResponseVisitor)
+    }
+}
+
+struct ResponseVisitor;
+
+impl<'de> Visitor<'de> for ResponseVisitor {
+    type Value = WaterfallArena;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a waterfalls response")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut arena = WaterfallArena::default();
+        let mut page = None;
+        while let Some(field) = map.next_key::<String>()? {
+            match field.as_str() {
+                "txs_seen" => map.next_value_seed(KeysSeed(&mut arena))?,
+                "page" => page = Some(map.next_value()?),
+                "tip" => arena.tip = map.next_value()?,
+                "tip_meta" => arena.tip_meta = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        arena.page = page.ok_or_else(|| serde::de::Error::missing_field("page"))?;
+        Ok(arena)
+    }
+}
+
+/// Appends the scripts of every key to the arena
+struct KeysSeed<'a>(&'a mut WaterfallArena);
+
+impl<'de> DeserializeSeed<'de> for KeysSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeysSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of script histories")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            let start = self.0.histories.len();
+            map.next_value_seed(ScriptsSeed(self.0))?;
+            self.0.keys.insert(key, start..self.0.histories.len());
+        }
+        Ok(())
+    }
+}
+
+/// Appends the history of every script of a key to the arena
+struct ScriptsSeed<'a>(&'a mut WaterfallArena);
+
+impl<'de> DeserializeSeed<'de> for ScriptsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ScriptsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of script histories")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq
+            .next_element_seed(HistorySeed(&mut self.0.entries))?
+            .is_some()
+        {
+            self.0.histories.push(self.0.entries.len());
+        }
+        Ok(())
+    }
+}
+
+/// Appends the entries of a script history to the arena
+struct HistorySeed<'a>(&'a mut Vec<TxSeen>);
+
+impl<'de> DeserializeSeed<'de> for HistorySeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for HistorySeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a script history")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(entry) = seq.next_element()? {
+            self.0.push(entry);
+        }
+        Ok(())
+    }
+}
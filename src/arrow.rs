@@ -0,0 +1,140 @@
+//! Export of scan histories to Arrow and Parquet.
+//!
+//! [`WaterfallResponse::to_record_batch`] returns one row per [`TxSeen`] entry with the
+//! [`history_schema`] columns, so wallet histories can be queried with DataFusion or Polars.
+//! With the [`HydratedTx`] of the response the rows also have the value moved by the entry and
+//! the fee of its transaction. [`write_parquet`] stores the batches in a Parquet file.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::builder::{StringBuilder, TimestampSecondBuilder, UInt32Builder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use bitcoin::Txid;
+use parquet::arrow::ArrowWriter;
+
+use crate::{Error, HydratedTx, Keychain, TxSeen, WaterfallResponse, V};
+
+/// The columns of the batches returned by [`WaterfallResponse::to_record_batch`]:
+///
+/// - `key`: the descriptor or `addresses` key of the entry
+/// - `keychain`: `external` or `internal`, null for other keys
+/// - `index`: the derivation index of the script
+/// - `txid`: the transaction, hex encoded
+/// - `height`: the confirmation height, zero if unconfirmed
+/// - `block_hash`, `block_time`: the confirming block, if known
+/// - `vin`, `vout`: the input spending or the output paying the script, if known
+/// - `value`: the satoshi paid or spent by the entry, hydrated only
+/// - `fee`: the satoshi fee of the transaction, hydrated only
+pub fn history_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("key", DataType::Utf8, false),
+        Field::new("keychain", DataType::Utf8, true),
+        Field::new("index", DataType::UInt32, false),
+        Field::new("txid", DataType::Utf8, false),
+        Field::new("height", DataType::UInt32, false),
+        Field::new("block_hash", DataType::Utf8, true),
+        Field::new(
+            "block_time",
+            DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            true,
+        ),
+        Field::new("vin", DataType::UInt32, true),
+        Field::new("vout", DataType::UInt32, true),
+        Field::new("value", DataType::UInt64, true),
+        Field::new("fee", DataType::UInt64, true),
+    ]))
+}
+
+/// The satoshi moved by `entry` in `tx`, `None` if the entry direction or the output is unknown
+fn entry_value(entry: &TxSeen, tx: &HydratedTx) -> Option<u64> {
+    let output = match entry.v {
+        V::Vout(vout) => tx.tx.output.get(vout as usize),
+        V::Vin(vin) => tx.prevouts.get(vin as usize)?.as_ref(),
+        V::Undefined => None,
+    };
+    output.map(|output| output.value.to_sat())
+}
+
+impl WaterfallResponse {
+    /// The script histories as an Arrow batch with the [`history_schema`], with the value
+    /// and fee columns filled from `txs` when given, e.g. the items of
+    /// `hydrate_with_prevouts`
+    pub fn to_record_batch(
+        &self,
+        txs: Option<&BTreeMap<Txid, HydratedTx>>,
+    ) -> Result<RecordBatch, Error> {
+        let mut key = StringBuilder::new();
+        let mut keychain = StringBuilder::new();
+        let mut index = UInt32Builder::new();
+        let mut txid = StringBuilder::new();
+        let mut height = UInt32Builder::new();
+        let mut block_hash = StringBuilder::new();
+        let mut block_time = TimestampSecondBuilder::new().with_timezone("UTC");
+        let mut vin = UInt32Builder::new();
+        let mut vout = UInt32Builder::new();
+        let mut value = UInt64Builder::new();
+        let mut fee = UInt64Builder::new();
+
+        for (k, scripts) in &self.txs_seen {
+            let branch = Keychain::from_key(k).map(|keychain| match keychain {
+                Keychain::External => "external",
+                Keychain::Internal => "internal",
+            });
+            for (i, history) in scripts.iter().enumerate() {
+                for entry in history {
+                    key.append_value(k);
+                    keychain.append_option(branch);
+                    index.append_value(i as u32);
+                    txid.append_value(entry.txid.to_string());
+                    height.append_value(entry.height.to_u32());
+                    block_hash.append_option(entry.block_hash.map(|hash| hash.to_string()));
+                    block_time.append_option(
+                        entry
+                            .block_timestamp
+                            .map(|timestamp| timestamp.to_u64() as i64),
+                    );
+                    vin.append_option(match entry.v {
+                        V::Vin(n) => Some(n),
+                        _ => None,
+                    });
+                    vout.append_option(match entry.v {
+                        V::Vout(n) => Some(n),
+                        _ => None,
+                    });
+                    let tx = txs.and_then(|txs| txs.get(&entry.txid));
+                    value.append_option(tx.and_then(|tx| entry_value(entry, tx)));
+                    fee.append_option(tx.and_then(HydratedTx::fee).map(|fee| fee.to_sat()));
+                }
+            }
+        }
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(key.finish()),
+            Arc::new(keychain.finish()),
+            Arc::new(index.finish()),
+            Arc::new(txid.finish()),
+            Arc::new(height.finish()),
+            Arc::new(block_hash.finish()),
+            Arc::new(block_time.finish()),
+            Arc::new(vin.finish()),
+            Arc::new(vout.finish()),
+            Arc::new(value.finish()),
+            Arc::new(fee.finish()),
+        ];
+        RecordBatch::try_new(history_schema(), columns).map_err(|e| Error::Arrow(e.to_string()))
+    }
+}
+
+/// Write `batches` with the [`history_schema`] to `writer` as a Parquet file
+pub fn write_parquet<W: Write + Send>(batches: &[RecordBatch], writer: W) -> Result<(), Error> {
+    let arrow_error = |e: parquet::errors::ParquetError| Error::Arrow(e.to_string());
+    let mut parquet = ArrowWriter::try_new(writer, history_schema(), None).map_err(arrow_error)?;
+    for batch in batches {
+        parquet.write(batch).map_err(arrow_error)?;
+    }
+    parquet.close().map_err(arrow_error)?;
+    Ok(())
+}
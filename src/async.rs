@@ -11,20 +11,76 @@
 
 //! Waterfalls by way of `reqwest` HTTP client.
 
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::marker::PhantomData;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use bitcoin::bip32::Xpub;
 use bitcoin::consensus::{deserialize, serialize, Decodable, Encodable};
+use bitcoin::hashes::Hash;
 use bitcoin::hex::{DisplayHex, FromHex};
-use bitcoin::Address;
-use bitcoin::{block::Header as BlockHeader, BlockHash, Transaction, Txid};
+use bitcoin::{block::Header as BlockHeader, Amount, BlockHash, Transaction, Txid};
+use bitcoin::{Address, Network, OutPoint};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace};
 
 use reqwest::{header, Client, Response};
+use tokio::sync::Semaphore;
 
-use crate::{Builder, Error, WaterfallResponse, BASE_BACKOFF_MILLIS, RETRYABLE_ERROR_CODES};
+use crate::cache::SCRIPTS_TOKEN_HEADER;
+use crate::cancel::Cancellable;
+use crate::clock::{retry_after_delay, unix_now};
+use crate::decoy::{remove_decoys, without_decoys, DECOY_TXS_PER_BLOCK};
+use crate::dry_run::canned_response;
+use crate::eta::{DEFAULT_BLOCK_INTERVAL, ETA_INTERVAL_BLOCKS};
+#[cfg(feature = "test-utils")]
+use crate::fixtures::CapturedScan;
+use crate::meta::{MetaLog, PendingMeta};
+use crate::profile::Throttle;
+use crate::selector::parse_seconds_since_block;
+#[cfg(all(feature = "async-connection-stats", not(target_arch = "wasm32")))]
+use crate::stats::CountConnections;
+use crate::subscribe::BlockSubscription;
+use crate::warning::{page_warnings, stale_tip, LenientResponse};
+#[cfg(feature = "server-extensions")]
+use crate::MempoolEntry;
+use crate::{
+    decode_json, decode_lenient, decode_tolerant, estimate_eta, mean_block_interval, next_backoff,
+    parse_elements_header, AddressStats, AuditRecord, AuditSink, BatchResult, BlindingKeyPolicy,
+    BlockHashCache, BroadcastQueue, Builder, Cached, CancellationToken, ChainFamily, Checkpoint,
+    ClockOffset, ConfirmationEta, ConnectionStats, CosignerData, DecoyPool, DescriptorRegistry,
+    DryRun, ElementsHeader, EndpointClass, Error, FlushReport, HeaderCache, HeaderChain,
+    HydratedTx, HydrationStrategy, Issuance, LimitKind, Measured, MempoolInfo, NetworkInfo,
+    OfflineCache, OutputStatus, PartialTx, Peg, Progress, ProgressSink, RelayPolicy, RequestSigner,
+    ScanCursor, ScriptKind, ScriptsTokens, ServerProbe, ServerSelector, Session, Spend,
+    StaleWhileRevalidate, SyncPayload, SyncProfile, TextDecoding, TipQuorum, Transfer, Tx, TxCache,
+    WalletSummary, Warned, Warning, WaterfallResponse, WaterfallsQuery, HEADER_SYNC_BATCH,
+    RETRYABLE_ERROR_CODES,
+};
+#[cfg(feature = "watcher")]
+use crate::{WatchEvent, Watcher};
+
+/// Maximum bytes allocated up front for a body from its `Content-Length`, it grows as the
+/// chunks arrive beyond that
+const MAX_BODY_PREALLOCATION: u64 = 1024 * 1024;
+
+/// The lane of the requests of an [`AsyncClient`].
+///
+/// Background requests, such as the hydration of a large wallet, wait for one of the
+/// [`Builder::max_background_requests`] permits, interactive requests are sent at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    /// Requests a user is waiting for, never queued
+    #[default]
+    Interactive,
+    /// Bulk requests, limited in number when sent concurrently
+    Background,
+}
 
 #[derive(Debug, Clone)]
 pub struct AsyncClient<S = DefaultSleeper> {
@@ -34,6 +90,67 @@ pub struct AsyncClient<S = DefaultSleeper> {
     client: Client,
     /// Number of times to retry a request
     max_retries: usize,
+    base_backoff: Duration,
+    max_backoff: Option<Duration>,
+    /// Socket timeout, also set on the inner client
+    timeout: Option<u64>,
+    /// Seconds allowed to a GET request including its retries
+    deadline: Option<u64>,
+    /// Optional cache of block headers
+    header_cache: Option<HeaderCache>,
+    /// Optional cache of transactions
+    tx_cache: Option<TxCache>,
+    /// Optional cache of the block hashes by height
+    block_hash_cache: Option<BlockHashCache>,
+    /// Checkpoints used by header validation, compiled-in defaults if `None`
+    checkpoints: Option<Vec<Checkpoint>>,
+    /// Headers at or below this height skip proof of work validation
+    assume_valid_height: Option<u32>,
+    /// Whether requests go through a proxy
+    uses_proxy: bool,
+    /// Optional signer adding authentication headers to every request
+    signer: Option<Arc<dyn RequestSigner>>,
+    /// Optional estimate of the server clock offset, updated from the responses
+    clock_offset: Option<ClockOffset>,
+    /// Seconds of clock skew tolerated by freshness checks
+    clock_skew_tolerance: u64,
+    /// Optional token cancelling the requests of this client
+    cancellation_token: Option<CancellationToken>,
+    /// Optional counters of the requests sent and connections opened
+    connection_stats: Option<ConnectionStats>,
+    /// Optional tokens of the descriptors already derived by the server
+    scripts_tokens: Option<ScriptsTokens>,
+    /// Record requests instead of sending them
+    dry_run: Option<DryRun>,
+    /// Optional sink receiving a record of every request sent
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    /// Optional sink receiving the progress of the transfers
+    progress_sink: Option<Arc<dyn ProgressSink>>,
+    /// The network of the server, once identified
+    network_info: Arc<Mutex<Option<NetworkInfo>>>,
+    /// The metadata of the responses of the current [`Self::measured`] call
+    meta_log: Option<MetaLog>,
+    /// Optional Esplora server for the endpoints the Waterfalls server lacks, with a client
+    /// not sending the configured headers
+    esplora_fallback: Option<(String, Client)>,
+    /// The lane of the requests of this client
+    priority: Priority,
+    /// Permits of the background requests, shared by the clones of this client
+    background_lane: Option<Arc<Semaphore>>,
+    /// The load this client puts on the server
+    sync_profile: SyncProfile,
+    /// Send the requests of batched calls one at a time, in input order
+    deterministic: bool,
+    /// When the next request may be sent under the sync profile
+    throttle: Throttle,
+    /// What this client sends in place of the blinding key of `ct(...)` descriptors
+    blinding_key_policy: BlindingKeyPolicy,
+    /// How the bodies of text endpoints are decoded
+    text_decoding: TextDecoding,
+    /// Optional cache answering the calls in offline mode
+    offline_cache: Option<OfflineCache>,
+    decoy_pool: Option<DecoyPool>,
+    descriptor_registry: Option<DescriptorRegistry>,
 
     /// Marker for the type of sleeper used
     marker: PhantomData<S>,
@@ -42,19 +159,14 @@ pub struct AsyncClient<S = DefaultSleeper> {
 impl<S: Sleeper> AsyncClient<S> {
     /// Build an async client from a builder
     pub fn from_builder(builder: Builder) -> Result<Self, Error> {
-        let mut client_builder = Client::builder();
-
-        #[cfg(not(target_arch = "wasm32"))]
-        if let Some(proxy) = &builder.proxy {
-            client_builder = client_builder.proxy(reqwest::Proxy::all(proxy)?);
-        }
+        let mut client_builder = connection_builder(&builder)?;
 
-        #[cfg(not(target_arch = "wasm32"))]
-        if let Some(timeout) = builder.timeout {
-            client_builder = client_builder.timeout(core::time::Duration::from_secs(timeout));
-        }
+        let esplora_fallback = match &builder.esplora_fallback {
+            Some(url) => Some((url.clone(), connection_builder(&builder)?.build()?)),
+            None => None,
+        };
 
-        if !builder.headers.is_empty() {
+        if !builder.headers.is_empty() || builder.basic_auth.is_some() {
             let mut headers = header::HeaderMap::new();
             for (k, v) in &builder.headers {
                 let header_name = header::HeaderName::from_lowercase(k.to_lowercase().as_bytes())
@@ -63,6 +175,12 @@ impl<S: Sleeper> AsyncClient<S> {
                     .map_err(|_| Error::InvalidHttpHeaderValue(v.clone()))?;
                 headers.insert(header_name, header_value);
             }
+            if let Some(basic_auth) = &builder.basic_auth {
+                let mut header_value = header::HeaderValue::from_str(&basic_auth.header_value())
+                    .map_err(|_| Error::InvalidHttpHeaderValue(basic_auth.username.clone()))?;
+                header_value.set_sensitive(true);
+                headers.insert(header::AUTHORIZATION, header_value);
+            }
             client_builder = client_builder.default_headers(headers);
         }
 
@@ -70,6 +188,40 @@ impl<S: Sleeper> AsyncClient<S> {
             url: builder.base_url,
             client: client_builder.build()?,
             max_retries: builder.max_retries,
+            base_backoff: builder.base_backoff,
+            max_backoff: builder.max_backoff,
+            timeout: builder.timeout,
+            deadline: builder.deadline,
+            header_cache: builder.header_cache,
+            tx_cache: builder.tx_cache,
+            block_hash_cache: builder.block_hash_cache,
+            checkpoints: builder.checkpoints,
+            assume_valid_height: builder.assume_valid_height,
+            uses_proxy: builder.proxy.is_some() && !cfg!(target_arch = "wasm32"),
+            signer: builder.signer,
+            clock_offset: builder.clock_offset,
+            clock_skew_tolerance: builder.clock_skew_tolerance,
+            cancellation_token: builder.cancellation_token,
+            esplora_fallback,
+            connection_stats: builder.connection_stats,
+            scripts_tokens: builder.scripts_tokens,
+            dry_run: builder.dry_run,
+            audit_sink: builder.audit_sink,
+            progress_sink: builder.progress_sink,
+            network_info: Arc::new(Mutex::new(None)),
+            meta_log: None,
+            priority: Priority::Interactive,
+            background_lane: builder
+                .max_background_requests
+                .map(|count| Arc::new(Semaphore::new(count))),
+            sync_profile: builder.sync_profile,
+            deterministic: builder.deterministic,
+            throttle: Throttle::default(),
+            blinding_key_policy: builder.blinding_key_policy,
+            text_decoding: builder.text_decoding,
+            offline_cache: builder.offline_cache,
+            decoy_pool: builder.decoy_pool,
+            descriptor_registry: builder.descriptor_registry,
             marker: PhantomData,
         })
     }
@@ -79,10 +231,320 @@ impl<S: Sleeper> AsyncClient<S> {
             url,
             client,
             max_retries: crate::DEFAULT_MAX_RETRIES,
+            base_backoff: crate::DEFAULT_BASE_BACKOFF,
+            max_backoff: None,
+            timeout: None,
+            deadline: None,
+            header_cache: None,
+            tx_cache: None,
+            block_hash_cache: None,
+            checkpoints: None,
+            assume_valid_height: None,
+            uses_proxy: false,
+            signer: None,
+            clock_offset: None,
+            clock_skew_tolerance: 0,
+            cancellation_token: None,
+            esplora_fallback: None,
+            connection_stats: None,
+            scripts_tokens: None,
+            dry_run: None,
+            audit_sink: None,
+            progress_sink: None,
+            network_info: Arc::new(Mutex::new(None)),
+            meta_log: None,
+            priority: Priority::Interactive,
+            background_lane: None,
+            sync_profile: SyncProfile::Standard,
+            deterministic: false,
+            throttle: Throttle::default(),
+            blinding_key_policy: BlindingKeyPolicy::Keep,
+            text_decoding: TextDecoding::Strict,
+            offline_cache: None,
+            decoy_pool: None,
+            descriptor_registry: None,
             marker: PhantomData,
         }
     }
 
+    /// Use `token` to cancel the requests of this client.
+    ///
+    /// Clients are cheap to clone, so a clone with its own token can be used to cancel a
+    /// single long running operation such as [`AsyncClient::sync_headers`].
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// The token cancelling the requests of this client, if any
+    pub fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
+    /// Send the requests of this client in the `priority` lane.
+    ///
+    /// The lanes are shared by the clones of a client, so a UI can refresh the tip with the
+    /// client while a background clone hydrates a wallet:
+    /// `client.clone().with_priority(Priority::Background)`.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// The lane of the requests of this client
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Run `future` until completion, or fail with [`Error::Cancelled`] as soon as the
+    /// cancellation token is cancelled
+    pub(crate) async fn cancellable<F: std::future::Future>(
+        &self,
+        future: F,
+    ) -> Result<F::Output, Error> {
+        match &self.cancellation_token {
+            Some(token) => Cancellable::new(token, future)
+                .await
+                .ok_or(Error::Cancelled),
+            None => Ok(future.await),
+        }
+    }
+
+    /// Send `request`, classifying connection failures caused by the proxy and updating the
+    /// clock offset estimate
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Response, Error> {
+        self.send_signed(request, true).await
+    }
+
+    /// Like [`Self::send`], adding the headers of the configured signer only if `sign`
+    async fn send_signed(
+        &self,
+        request: reqwest::RequestBuilder,
+        sign: bool,
+    ) -> Result<Response, Error> {
+        if self
+            .offline_cache
+            .as_ref()
+            .map_or(false, OfflineCache::is_offline)
+        {
+            return Err(Error::Offline);
+        }
+        let (client, request) = request.build_split();
+        let mut request = request?;
+        // Dry runs record the requests unsigned, without involving the signer
+        if let Some(dry_run) = &self.dry_run {
+            let (method, url) = (request.method().as_str(), request.url().as_str());
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            dry_run.record(method, url, body);
+            let (status, body) = canned_response(method, url);
+            let response = http::Response::builder()
+                .status(status)
+                .body(body)
+                .expect("canned responses are valid");
+            return Ok(Response::from(response));
+        }
+        if let Some(signer) = self.signer.as_ref().filter(|_| sign) {
+            let url = request.url();
+            let path = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .unwrap_or_default();
+            for (key, value) in signer.sign(request.method().as_str(), &path, body) {
+                let name = header::HeaderName::from_bytes(key.as_bytes())
+                    .map_err(|_| Error::InvalidHttpHeaderName(key.clone()))?;
+                let value = header::HeaderValue::from_str(&value)
+                    .map_err(|_| Error::InvalidHttpHeaderValue(value.clone()))?;
+                request.headers_mut().insert(name, value);
+            }
+        }
+        // The permit is held until the response headers are received
+        let _permit = match (&self.background_lane, self.priority) {
+            (Some(lane), Priority::Background) => self.cancellable(lane.acquire()).await?.ok(),
+            _ => None,
+        };
+        if S::RETRIES {
+            let wait = self.throttle.reserve(self.sync_profile.request_spacing());
+            if !wait.is_zero() {
+                self.cancellable(S::sleep(wait)).await?;
+            }
+        }
+        if let Some(stats) = &self.connection_stats {
+            stats.record_request();
+        }
+        let sent_at = self.clock_offset.as_ref().map(|_| unix_now());
+        let audit = self.audit_sink.as_ref().map(|sink| {
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map_or(0, |b| b.len());
+            let (method, url) = (request.method().to_string(), request.url().to_string());
+            (sink, method, url, body, unix_now(), Instant::now())
+        });
+        let upload = self.progress_sink.as_ref().map(|sink| {
+            let body = request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map_or(0, |b| b.len() as u64);
+            let endpoint =
+                EndpointClass::classify(request.method().as_str(), request.url().as_str());
+            (sink, endpoint, body)
+        });
+        let meta = self.meta_log.as_ref().map(|log| {
+            let endpoint =
+                EndpointClass::classify(request.method().as_str(), request.url().as_str());
+            (log, endpoint, Instant::now())
+        });
+        // The query may hold a descriptor, timeout errors report only the path
+        let endpoint = request.url().path().to_string();
+        let response = self.cancellable(client.execute(request)).await?;
+        if let (Some((sink, endpoint, body)), Ok(_)) = (upload, &response) {
+            if body > 0 {
+                sink.progress(Progress {
+                    transfer: Transfer::Upload,
+                    endpoint,
+                    bytes: body,
+                    total: Some(body),
+                });
+            }
+        }
+        if let Some((sink, method, url, body, timestamp, started)) = audit {
+            // The body isn't read yet, its size is the one announced by the server
+            let (bytes_down, status) = match &response {
+                Ok(response) => (
+                    response.content_length().unwrap_or_default() as usize,
+                    Some(response.status().as_u16()),
+                ),
+                Err(_) => (0, None),
+            };
+            let duration = started.elapsed();
+            sink.record(AuditRecord::new(
+                &method, &url, timestamp, duration, body, bytes_down, status,
+            ));
+        }
+        let response = response.map_err(|e| {
+            if e.is_timeout() {
+                return match e.is_connect() {
+                    true => Error::ConnectTimeout {
+                        endpoint,
+                        attempt: 1,
+                    },
+                    false => Error::ReadTimeout {
+                        endpoint,
+                        attempt: 1,
+                    },
+                };
+            }
+            if !self.uses_proxy {
+                return Error::Reqwest(e);
+            }
+            if e.status() == Some(reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED) {
+                return Error::ProxyAuthFailed(e.to_string());
+            }
+            if !e.is_connect() {
+                return Error::Reqwest(e);
+            }
+            let mut source = std::error::Error::source(&e);
+            let mut messages = vec![e.to_string()];
+            let mut refused = false;
+            while let Some(inner) = source {
+                if let Some(io) = inner.downcast_ref::<std::io::Error>() {
+                    refused |= matches!(
+                        io.kind(),
+                        std::io::ErrorKind::ConnectionRefused
+                            | std::io::ErrorKind::TimedOut
+                            | std::io::ErrorKind::AddrNotAvailable
+                    );
+                }
+                messages.push(inner.to_string());
+                source = inner.source();
+            }
+            let message = messages.join(": ");
+            if refused {
+                Error::ProxyUnreachable(message)
+            } else {
+                Error::ProxyHostUnreachable(message)
+            }
+        })?;
+        let mut response = response;
+        if let Some((log, endpoint, started)) = meta {
+            let pending = PendingMeta {
+                endpoint,
+                status: response.status().as_u16(),
+                content_length: response.content_length(),
+                started,
+                time_to_first_byte: started.elapsed(),
+            };
+            // Error bodies are read as text, their announced length stands for them
+            match response.status().is_success() {
+                true => drop(response.extensions_mut().insert(pending)),
+                false => {
+                    let length = pending.content_length.unwrap_or_default();
+                    log.record(pending.finish(length));
+                }
+            }
+        }
+        if let (Some(offset), Some(sent_at)) = (&self.clock_offset, sent_at) {
+            if let Some(date) = response.headers().get(header::DATE) {
+                if let Ok(date) = date.to_str() {
+                    offset.observe_date(date, sent_at, unix_now());
+                }
+            }
+        }
+        if self.sync_profile.strict_rate_limits() && is_status_retryable(response.status()) {
+            let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok());
+            let retry_after = header(header::RETRY_AFTER).and_then(|value| {
+                retry_after_delay(value, header(header::DATE), self.clock_offset.as_ref())
+            });
+            if let Some(retry_after) = retry_after {
+                self.throttle.defer(retry_after);
+            }
+        }
+        Ok(response)
+    }
+
+    /// Check that the Waterfalls server can be reached through the configured proxy.
+    ///
+    /// A single request is made to the server, any HTTP response is considered a success.
+    /// Proxy failures are reported as [`Error::ProxyUnreachable`], [`Error::ProxyAuthFailed`]
+    /// or [`Error::ProxyHostUnreachable`].
+    pub async fn check_proxy(&self) -> Result<(), Error> {
+        let url = format!("{}/blocks/tip/hash", self.url);
+        let response = self.send(self.client.get(url)).await?;
+        if response.status() == reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+            return Err(Error::ProxyAuthFailed(response.text().await?));
+        }
+        Ok(())
+    }
+
+    /// Run `call` with a clone of this client and return its result with the metadata of the
+    /// responses it received, see [`crate::meta`]
+    pub async fn measured<T, F, Fut>(&self, call: F) -> Measured<Result<T, Error>>
+    where
+        F: FnOnce(Self) -> Fut,
+        Fut: std::future::Future<Output = Result<T, Error>>,
+        S: Clone,
+    {
+        let log = MetaLog::default();
+        let client = AsyncClient {
+            meta_log: Some(log.clone()),
+            ..self.clone()
+        };
+        let started = Instant::now();
+        let value = call(client).await;
+        Measured {
+            value,
+            responses: log.take(),
+            duration: started.elapsed(),
+        }
+    }
+
     /// Make an HTTP GET request to given URL, deserializing to any `T` that
     /// implement [`bitcoin::consensus::Decodable`].
     ///
@@ -95,8 +557,7 @@ impl<S: Sleeper> AsyncClient<S> {
     /// This function will return an error either from the HTTP client, or the
     /// [`bitcoin::consensus::Decodable`] deserialization.
     async fn get_response<T: Decodable>(&self, path: &str) -> Result<T, Error> {
-        let url = format!("{}{}", self.url, path);
-        let response = self.get_with_retry(&url).await?;
+        let response = self.get_with_retry(path).await?;
 
         if !response.status().is_success() {
             return Err(Error::HttpResponse {
@@ -105,7 +566,41 @@ impl<S: Sleeper> AsyncClient<S> {
             });
         }
 
-        Ok(deserialize::<T>(&response.bytes().await?)?)
+        Ok(deserialize::<T>(&self.read_body(response).await?)?)
+    }
+
+    /// Read the body of `response`, reporting the progress of the download to the
+    /// [`ProgressSink`], if any
+    async fn read_body(&self, mut response: Response) -> Result<Vec<u8>, Error> {
+        let pending = response.extensions_mut().remove::<PendingMeta>();
+        let body = self.read_chunks(response).await?;
+        if let (Some(log), Some(pending)) = (&self.meta_log, pending) {
+            log.record(pending.finish(body.len() as u64));
+        }
+        Ok(body)
+    }
+
+    /// Read the body of `response`, see [`Self::read_body`]
+    async fn read_chunks(&self, mut response: Response) -> Result<Vec<u8>, Error> {
+        let sink = match &self.progress_sink {
+            Some(sink) => sink,
+            None => return Ok(response.bytes().await?.to_vec()),
+        };
+        let endpoint = EndpointClass::classify("GET", response.url().as_str());
+        let total = response.content_length();
+        // The length is announced by the server, don't trust it for more than a start
+        let capacity = total.unwrap_or_default().min(MAX_BODY_PREALLOCATION);
+        let mut body = Vec::with_capacity(capacity as usize);
+        while let Some(chunk) = self.cancellable(response.chunk()).await?? {
+            body.extend_from_slice(&chunk);
+            sink.progress(Progress {
+                transfer: Transfer::Download,
+                endpoint,
+                bytes: body.len() as u64,
+                total,
+            });
+        }
+        Ok(body)
     }
 
     /// Make an HTTP GET request to given URL, deserializing to `Option<T>`.
@@ -129,20 +624,56 @@ impl<S: Sleeper> AsyncClient<S> {
         query_params: &[(&str, &str)],
     ) -> Result<T, Error> {
         let url = format!("{}{}", self.url, path);
-        let mut request = self.client.get(&url);
-        for (key, value) in query_params {
-            request = request.query(&[(key, value)]);
-        }
-        let response = request.send().await?;
+        let descriptor = query_params
+            .iter()
+            .find(|(key, _)| *key == "descriptor")
+            .map(|(_, value)| self.blinding_key_policy.apply(value));
+        let query_params: Vec<(&str, &str)> = query_params
+            .iter()
+            .map(|(key, value)| match (*key, &descriptor) {
+                ("descriptor", Some(descriptor)) => (*key, descriptor.as_ref()),
+                _ => (*key, *value),
+            })
+            .collect();
+        let descriptor = descriptor.as_deref();
+        let tokens = self.scripts_tokens.as_ref().zip(descriptor);
+        let mut token = tokens.and_then(|(tokens, descriptor)| tokens.get(descriptor));
 
-        if !response.status().is_success() {
-            return Err(Error::HttpResponse {
-                status: response.status().as_u16(),
-                message: response.text().await?,
-            });
-        }
+        loop {
+            let mut request = self.client.get(&url);
+            for (key, value) in &query_params {
+                request = request.query(&[(key, value)]);
+            }
+            if let Some(token) = &token {
+                request = request.query(&[("scripts", token)]);
+            }
+            let response = self.send(request).await?;
 
-        response.json::<T>().await.map_err(Error::Reqwest)
+            if !response.status().is_success() {
+                if let (Some((tokens, descriptor)), Some(_)) = (tokens, &token) {
+                    if response.status().is_client_error() {
+                        // The server may have forgotten the token, derive the scripts again
+                        tokens.remove(descriptor);
+                        token = None;
+                        continue;
+                    }
+                }
+                return Err(Error::HttpResponse {
+                    status: response.status().as_u16(),
+                    message: response.text().await?,
+                });
+            }
+
+            if let Some((tokens, descriptor)) = tokens {
+                let header = response.headers().get(SCRIPTS_TOKEN_HEADER);
+                if let Some(new_token) = header.and_then(|v| v.to_str().ok()) {
+                    tokens.insert(descriptor, new_token);
+                }
+            }
+
+            let body = self.read_body(response).await?;
+            return decode_json(body);
+        }
     }
 
     /// Make an HTTP GET request to given URL, deserializing to any `T` that
@@ -157,8 +688,7 @@ impl<S: Sleeper> AsyncClient<S> {
     /// This function will return an error either from the HTTP client, or the
     /// [`bitcoin::consensus::Decodable`] deserialization.
     async fn get_response_hex<T: Decodable>(&self, path: &str) -> Result<T, Error> {
-        let url = format!("{}{}", self.url, path);
-        let response = self.get_with_retry(&url).await?;
+        let response = self.get_with_retry(path).await?;
 
         if !response.status().is_success() {
             return Err(Error::HttpResponse {
@@ -167,7 +697,7 @@ impl<S: Sleeper> AsyncClient<S> {
             });
         }
 
-        let hex_str = response.text().await?;
+        let hex_str = self.read_text(response).await?;
         Ok(deserialize(&Vec::from_hex(&hex_str)?)?)
     }
 
@@ -180,8 +710,7 @@ impl<S: Sleeper> AsyncClient<S> {
     ///
     /// This function will return an error either from the HTTP client.
     async fn get_response_text(&self, path: &str) -> Result<String, Error> {
-        let url = format!("{}{}", self.url, path);
-        let response = self.get_with_retry(&url).await?;
+        let response = self.get_with_retry(path).await?;
 
         if !response.status().is_success() {
             return Err(Error::HttpResponse {
@@ -190,7 +719,18 @@ impl<S: Sleeper> AsyncClient<S> {
             });
         }
 
-        Ok(response.text().await?)
+        self.read_text(response).await
+    }
+
+    /// Read the body of a text endpoint according to the [`TextDecoding`] of the client
+    async fn read_text(&self, response: Response) -> Result<String, Error> {
+        let body = self.read_body(response).await?;
+        match self.text_decoding {
+            TextDecoding::Strict => {
+                String::from_utf8(body).map_err(|e| Error::InvalidUtf8(e.utf8_error()))
+            }
+            TextDecoding::Tolerant => Ok(decode_tolerant(&body)),
+        }
     }
 
     /// Make an HTTP POST request to given URL, serializing from any `T` that
@@ -207,7 +747,7 @@ impl<S: Sleeper> AsyncClient<S> {
         let url = format!("{}{}", self.url, path);
         let body = serialize::<T>(&body).to_lower_hex_string();
 
-        let response = self.client.post(url).body(body).send().await?;
+        let response = self.send(self.client.post(url).body(body)).await?;
 
         if !response.status().is_success() {
             return Err(Error::HttpResponse {
@@ -215,13 +755,46 @@ impl<S: Sleeper> AsyncClient<S> {
                 message: response.text().await?,
             });
         }
+        // Read the body of the response for its metadata
+        self.read_body(response).await?;
 
         Ok(())
     }
 
     /// Get a [`Transaction`] option given its [`Txid`]
     pub async fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
-        self.get_opt_response(&format!("/tx/{txid}/raw")).await
+        if let Some(tx) = self.tx_cache.as_ref().and_then(|cache| cache.get(txid)) {
+            return Ok(Some(tx));
+        }
+        let tx: Option<Transaction> = self.get_opt_response(&format!("/tx/{txid}/raw")).await?;
+        if let (Some(cache), Some(tx)) = (&self.tx_cache, &tx) {
+            cache.insert(tx.clone());
+        }
+        Ok(tx)
+    }
+
+    /// Get the raw bytes of a transaction given its [`Txid`], e.g. an Elements transaction
+    /// which can't be decoded as a [`Transaction`]
+    pub async fn get_tx_raw(&self, txid: &Txid) -> Result<Option<Vec<u8>>, Error> {
+        let response = match self.get_with_retry(&format!("/tx/{txid}/raw")).await {
+            Ok(response) => response,
+            Err(Error::HttpResponse { status: 404, .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        match response.status().as_u16() {
+            404 => Ok(None),
+            status if !response.status().is_success() => Err(Error::HttpResponse {
+                status,
+                message: response.text().await?,
+            }),
+            _ => Ok(Some(self.read_body(response).await?)),
+        }
+    }
+
+    /// Get the transaction `txid` decoded with [`crate::decode_lenient`], for transactions
+    /// [`Self::get_tx`] fails to decode
+    pub async fn get_tx_lenient(&self, txid: &Txid) -> Result<Option<PartialTx>, Error> {
+        Ok(self.get_tx_raw(txid).await?.as_deref().map(decode_lenient))
     }
 
     /// Get a [`Transaction`] given its [`Txid`].
@@ -238,13 +811,330 @@ impl<S: Sleeper> AsyncClient<S> {
         let path = "/v4/waterfalls";
         self.get_response_json_with_query(path, &[("descriptor", descriptor)])
             .await
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Descriptor, descriptor.len()))
+            .map(|response| self.check_reorg(response))
+    }
+
+    /// Query the waterfalls endpoint with an Elements descriptor, keeping only the history of
+    /// the transactions moving the asset with hex id `asset`.
+    ///
+    /// The filter is applied by the server: a server without asset filtering may ignore the
+    /// parameter and return the whole history. The client can't filter the history itself,
+    /// since the assets of Liquid outputs are blinded.
+    pub async fn waterfalls_asset(
+        &self,
+        descriptor: &str,
+        asset: &str,
+    ) -> Result<WaterfallResponse, Error> {
+        let path = "/v4/waterfalls";
+        self.get_response_json_with_query(path, &[("descriptor", descriptor), ("asset", asset)])
+            .await
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Descriptor, descriptor.len()))
+            .map(|response| self.check_reorg(response))
+    }
+
+    /// Like [`Self::waterfalls`], making sure the response was computed at the tip returned by
+    /// [`Self::get_tip_hash`].
+    ///
+    /// While the server processes a new block its endpoints may briefly disagree, so both are
+    /// queried again with backoff up to `max_retries` times before failing with
+    /// [`Error::TipInconsistent`]. A response without a tip is returned as is.
+    pub async fn waterfalls_consistent(
+        &self,
+        descriptor: &str,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut delay = self.base_backoff;
+        let mut attempts = 0;
+        loop {
+            let response = self.waterfalls(descriptor).await?;
+            let waterfalls = match response.tip_hash() {
+                Some(waterfalls) => waterfalls,
+                None => return Ok(response),
+            };
+            let tip = self.get_tip_hash().await?;
+            if tip == waterfalls {
+                return Ok(response);
+            }
+            if !S::RETRIES || attempts >= self.max_retries {
+                return Err(Error::TipInconsistent { waterfalls, tip });
+            }
+            self.cancellable(S::sleep(delay)).await?;
+            attempts += 1;
+            delay = next_backoff(delay, self.max_backoff);
+        }
+    }
+
+    /// Scan `descriptor` and return the response sanitized as a test fixture, see
+    /// [`CapturedScan`]
+    #[cfg(feature = "test-utils")]
+    pub async fn capture_scan(&self, descriptor: &str) -> Result<CapturedScan, Error> {
+        Ok(CapturedScan::from_descriptor(
+            self.waterfalls(descriptor).await?,
+        ))
+    }
+
+    /// Scan the external and internal addresses of `xpub` using the standard descriptor of
+    /// `script_kind`, see [`ScriptKind::descriptor`].
+    ///
+    /// If the server stopped before `gap_limit` unused scripts after the last used one of a
+    /// branch, the scan is repeated up to the index required by the gap limit, see
+    /// [`Self::waterfalls_to_gap_limit`].
+    /// [`WaterfallResponse::keychains`] splits the result into receive and change branches.
+    pub async fn scan_xpub(
+        &self,
+        xpub: &Xpub,
+        script_kind: ScriptKind,
+        gap_limit: u32,
+    ) -> Result<WaterfallResponse, Error> {
+        self.waterfalls_to_gap_limit(&script_kind.descriptor(xpub), gap_limit)
+            .await
+    }
+
+    /// Scan `descriptor` with the gap limit registered in the [`DescriptorRegistry`] of the
+    /// client if any, see [`Self::waterfalls_to_gap_limit`], or the one of the server
+    pub async fn waterfalls_registered(
+        &self,
+        descriptor: &str,
+    ) -> Result<WaterfallResponse, Error> {
+        let gap_limit = self
+            .descriptor_registry
+            .as_ref()
+            .and_then(|registry| registry.get(descriptor)?.gap_limit);
+        match gap_limit {
+            Some(gap_limit) => self.waterfalls_to_gap_limit(descriptor, gap_limit).await,
+            None => self.waterfalls(descriptor).await,
+        }
+    }
+
+    /// Scan `descriptor` with every branch covering `gap_limit` unused scripts after its last
+    /// used one.
+    ///
+    /// The server scans up to its own gap limit by default, so while the response is short of
+    /// `gap_limit` the scan is repeated with a higher `to_index`, see
+    /// [`WaterfallResponse::required_to_index`]. Fails with [`Error::ScanTruncated`] if the
+    /// server stops short of the requested index, e.g. at its page size, instead of returning
+    /// a truncated history.
+    pub async fn waterfalls_to_gap_limit(
+        &self,
+        descriptor: &str,
+        gap_limit: u32,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut response = self.waterfalls(descriptor).await?;
+        let mut requested = None;
+        while let Some(required) = response.required_to_index(gap_limit) {
+            if requested.map_or(false, |requested| required <= requested) {
+                return Err(Error::ScanTruncated {
+                    required,
+                    scanned: response.scanned_to_index().unwrap_or(0),
+                });
+            }
+            requested = Some(required);
+            response = self
+                .waterfalls_version(descriptor, 4, None, Some(required), false)
+                .await?;
+        }
+        Ok(response)
+    }
+
+    /// Like [`Self::waterfalls_to_gap_limit`] with a `gap_limit`, or [`Self::waterfalls`]
+    /// without, reporting the anomalies as [`Warning`]s instead of failing, see
+    /// [`crate::warning`].
+    ///
+    /// The tip of the response is compared once with [`Self::get_tip_hash`].
+    pub async fn waterfalls_soft(
+        &self,
+        descriptor: &str,
+        gap_limit: Option<u32>,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        let mut warned = self.waterfalls_lenient(descriptor, None, None).await?;
+        if let Some(gap_limit) = gap_limit {
+            let mut requested = None;
+            while let Some(required) = warned.value.required_to_index(gap_limit) {
+                if requested.map_or(false, |requested| required <= requested) {
+                    let scanned = warned.value.scanned_to_index().unwrap_or(0);
+                    warned
+                        .warnings
+                        .push(Warning::ScanTruncated { required, scanned });
+                    break;
+                }
+                requested = Some(required);
+                warned = self
+                    .waterfalls_lenient(descriptor, None, Some(required))
+                    .await?;
+            }
+        }
+        self.check_tip_soft(warned).await
+    }
+
+    /// Like [`Self::waterfalls_all_pages`], reporting the anomalies as [`Warning`]s instead of
+    /// failing, see [`crate::warning`]
+    pub async fn waterfalls_all_pages_soft(
+        &self,
+        descriptor: &str,
+        page_size: usize,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        let mut cursor = ScanCursor::new(descriptor, page_size);
+        let (mut warnings, mut first) = (vec![], None);
+        while !cursor.complete {
+            let requested = cursor.next_page;
+            let page = self
+                .waterfalls_lenient(descriptor, Some(requested), None)
+                .await?;
+            first = first.or(page.value.tip_hash());
+            warnings.extend(page.warnings);
+            warnings.extend(page_warnings(requested, first, &page.value));
+            cursor.advance(page.value);
+        }
+        let value = cursor.response;
+        self.check_tip_soft(Warned { value, warnings }).await
+    }
+
+    /// Query a page of the waterfalls endpoint, skipping the malformed history entries
+    async fn waterfalls_lenient(
+        &self,
+        descriptor: &str,
+        page: Option<u32>,
+        to_index: Option<u32>,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        let mut query_params = vec![("descriptor", descriptor.to_string())];
+        if let Some(page) = page {
+            query_params.push(("page", page.to_string()));
+        }
+        if let Some(to_index) = to_index {
+            query_params.push(("to_index", to_index.to_string()));
+        }
+        let query_refs: Vec<(&str, &str)> =
+            query_params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let response: LenientResponse = self
+            .get_response_json_with_query("/v4/waterfalls", &query_refs)
+            .await
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Descriptor, descriptor.len()))?;
+        let mut warned = response.into_warned();
+        warned.value = self.check_reorg(warned.value);
+        Ok(warned)
+    }
+
+    /// Add the tip warnings of the response of `warned`
+    async fn check_tip_soft(
+        &self,
+        mut warned: Warned<WaterfallResponse>,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        if let Some(waterfalls) = warned.value.tip_hash() {
+            let tip = self.get_tip_hash().await?;
+            if tip != waterfalls {
+                warned
+                    .warnings
+                    .push(Warning::TipInconsistent { waterfalls, tip });
+            }
+        }
+        let offset = self.clock_offset.clone().unwrap_or_default();
+        let stale = stale_tip(&warned.value, self.clock_skew_tolerance, |timestamp| {
+            offset.elapsed_since(timestamp)
+        });
+        warned.warnings.extend(stale);
+        Ok(warned)
+    }
+
+    /// Return the last response of `descriptor` cached in `poller`, with the future refreshing
+    /// it to spawn on the runtime, see [`StaleWhileRevalidate`].
+    ///
+    /// No future is returned while a refresh is in progress.
+    pub fn poll_waterfalls(
+        &self,
+        descriptor: &str,
+        poller: &StaleWhileRevalidate,
+    ) -> (
+        Option<WaterfallResponse>,
+        Option<impl std::future::Future<Output = ()>>,
+    )
+    where
+        S: Clone,
+    {
+        let refresh = poller.start_refresh().then(|| {
+            self.clone()
+                .refresh_poller(descriptor.to_string(), poller.clone())
+        });
+        (poller.cached(), refresh)
+    }
+
+    async fn refresh_poller(self, descriptor: String, poller: StaleWhileRevalidate) {
+        poller.finish_refresh(self.waterfalls(&descriptor).await)
+    }
+
+    /// Poll the wallets of `session` due for a poll, returning how many were polled.
+    ///
+    /// The transactions of a wallet missing from the [`crate::TxCache`] of the session are
+    /// fetched before its [`crate::WalletEvent::Updated`] is sent. The wallets are scanned with
+    /// [`Self::waterfalls_registered`]. Call it again at [`Session::next_poll`].
+    pub async fn poll_session(&self, session: &Session) -> usize {
+        let due = session.due(Instant::now());
+        for (id, descriptor) in &due {
+            let result = self.poll_session_wallet(session, descriptor).await;
+            session.record(*id, result, Instant::now());
+        }
+        due.len()
+    }
+
+    async fn poll_session_wallet(
+        &self,
+        session: &Session,
+        descriptor: &str,
+    ) -> Result<WaterfallResponse, Error> {
+        let response = self.waterfalls_registered(descriptor).await?;
+        for txid in session.missing_txids(&response) {
+            session.tx_cache().insert(self.get_tx_no_opt(&txid).await?);
+        }
+        Ok(response)
     }
 
     /// Query the waterfalls endpoint with addresses
+    ///
+    /// With a [`DecoyPool`] the query includes decoys, removed from the response, see
+    /// [`crate::decoy`].
     pub async fn waterfalls_addresses(
         &self,
         addresses: &[Address],
     ) -> Result<WaterfallResponse, Error> {
+        match &self.decoy_pool {
+            Some(pool) => {
+                let (mixed, positions) = pool.mix(addresses);
+                self.query_addresses(&mixed)
+                    .await
+                    .map(|response| remove_decoys(response, &positions))
+                    .map_err(|e| without_decoys(e, pool.ratio(), addresses.len()))
+            }
+            None => self.query_addresses(addresses).await,
+        }
+    }
+
+    /// Fill the [`DecoyPool`] of the client with the output addresses of up to
+    /// [`DECOY_TXS_PER_BLOCK`] transactions of each of the last `blocks` blocks.
+    ///
+    /// Requires the Esplora block transactions endpoint and a Bitcoin network.
+    pub async fn refresh_decoys(&self, blocks: u32) -> Result<(), Error> {
+        let pool = self.decoy_pool.as_ref().ok_or_else(|| {
+            Error::InvalidConfiguration("the client has no decoy pool".to_string())
+        })?;
+        let network = self.network_info().await?.network.ok_or_else(|| {
+            Error::InvalidConfiguration("decoys require a Bitcoin network".to_string())
+        })?;
+        let mut hash = self.get_tip_hash().await?;
+        for _ in 0..blocks {
+            // The coinbase is skipped
+            let txids = self.get_block_txids(&hash).await?;
+            let txids: Vec<_> = txids
+                .into_iter()
+                .skip(1)
+                .take(DECOY_TXS_PER_BLOCK)
+                .collect();
+            let txs = self.get_txs(&txids).await.into_result()?;
+            pool.insert_from_txs(txs.iter().map(|(_, tx)| tx), network);
+            hash = self.get_header_by_hash(&hash).await?.prev_blockhash;
+        }
+        Ok(())
+    }
+
+    async fn query_addresses(&self, addresses: &[Address]) -> Result<WaterfallResponse, Error> {
         let addresses_str = addresses
             .iter()
             .map(|a| a.to_string())
@@ -253,6 +1143,339 @@ impl<S: Sleeper> AsyncClient<S> {
         let path = "/v4/waterfalls";
         self.get_response_json_with_query(path, &[("addresses", &addresses_str)])
             .await
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Addresses, addresses.len()))
+            .map(|response| self.check_reorg(response))
+    }
+
+    /// Query the waterfalls endpoint with addresses, splitting them in requests of at most
+    /// `chunk_size` addresses and merging the results.
+    ///
+    /// If the server answers with [`Error::LimitExceeded`] the chunk size is lowered to the
+    /// server limit and the request is retried. Fails if any chunk fails, see
+    /// [`Self::waterfalls_addresses_batch`] to keep the chunks fetched successfully.
+    pub async fn waterfalls_addresses_chunked(
+        &self,
+        addresses: &[Address],
+        chunk_size: usize,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut response = WaterfallResponse::default();
+        let chunks = self
+            .waterfalls_addresses_batch(addresses, chunk_size)
+            .await
+            .into_result()?;
+        for (_, chunk_response) in chunks {
+            response.merge(chunk_response);
+        }
+        Ok(response)
+    }
+
+    /// Like [`Self::waterfalls_addresses_chunked`], but a failed chunk doesn't stop the
+    /// following ones and the response of every chunk is returned with its addresses.
+    pub async fn waterfalls_addresses_batch(
+        &self,
+        addresses: &[Address],
+        chunk_size: usize,
+    ) -> BatchResult<Vec<Address>, WaterfallResponse> {
+        let mut chunk_size = chunk_size.max(1);
+        let mut result = BatchResult::default();
+        let mut remaining = addresses;
+        while !remaining.is_empty() {
+            let chunk = &remaining[..chunk_size.min(remaining.len())];
+            match self.waterfalls_addresses(chunk).await {
+                Err(Error::LimitExceeded {
+                    kind: LimitKind::Addresses,
+                    limit,
+                    ..
+                }) if limit > 0 && limit < chunk.len() => {
+                    chunk_size = limit;
+                    continue;
+                }
+                Ok(chunk_response) => result.items.push((chunk.to_vec(), chunk_response)),
+                Err(e) => result.errors.push((chunk.to_vec(), e)),
+            }
+            remaining = &remaining[chunk.len()..];
+        }
+        result
+    }
+
+    /// Fetch the transactions with the given `txids`, without stopping at the first failure
+    pub async fn get_txs(&self, txids: &[Txid]) -> BatchResult<Txid, Transaction> {
+        let mut result = BatchResult::default();
+        for txid in txids {
+            match self.get_tx_no_opt(txid).await {
+                Ok(tx) => result.items.push((*txid, tx)),
+                Err(e) => result.errors.push((*txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Fetch the Liquid transactions seen in `response` and find their peg-ins and peg-outs,
+    /// see [`crate::pegs`]. Transactions without pegs aren't in the items.
+    pub async fn find_pegs(&self, response: &WaterfallResponse) -> BatchResult<Txid, Vec<Peg>> {
+        self.parse_raw_txs(response, crate::pegs::find_pegs).await
+    }
+
+    /// Fetch the Liquid transactions seen in `response` and find their asset issuances and
+    /// reissuances, see [`crate::issuance`]. Transactions without issuances aren't in the items.
+    pub async fn find_issuances(
+        &self,
+        response: &WaterfallResponse,
+    ) -> BatchResult<Txid, Vec<Issuance>> {
+        self.parse_raw_txs(response, crate::issuance::find_issuances)
+            .await
+    }
+
+    /// Fetch the raw transactions seen in `response` and `parse` them, skipping the ones
+    /// without items
+    async fn parse_raw_txs<T>(
+        &self,
+        response: &WaterfallResponse,
+        parse: fn(&[u8]) -> Result<Vec<T>, Error>,
+    ) -> BatchResult<Txid, Vec<T>> {
+        let mut result = BatchResult::default();
+        for txid in response.txids() {
+            let items = match self.get_tx_raw(&txid).await {
+                Ok(Some(raw)) => parse(&raw),
+                Ok(None) => Err(Error::TransactionNotFound(txid)),
+                Err(e) => Err(e),
+            };
+            match items {
+                Ok(items) if items.is_empty() => {}
+                Ok(items) => result.items.push((txid, items)),
+                Err(e) => result.errors.push((txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Fetch every transaction seen in `response`, see [`Self::get_txs`]
+    pub async fn hydrate(&self, response: &WaterfallResponse) -> BatchResult<Txid, Transaction> {
+        self.get_txs(&response.txids()).await
+    }
+
+    /// Scan `descriptor`, fetch its transactions and compute the [`WalletSummary`], see
+    /// [`WaterfallResponse::summary`]
+    pub async fn wallet_summary(&self, descriptor: &str) -> Result<WalletSummary, Error> {
+        let response = self.waterfalls(descriptor).await?;
+        let txs = self.hydrate(&response).await.into_result()?;
+        Ok(response.summary(&txs.into_iter().collect()))
+    }
+
+    /// Scan the descriptor of `watcher` with [`Self::waterfalls_registered`], fetch the
+    /// transactions it lacks and return the events of the poll, see [`crate::watcher`]
+    #[cfg(feature = "watcher")]
+    pub async fn poll_watcher(&self, watcher: &mut Watcher) -> Result<Vec<WatchEvent>, Error> {
+        let response = self.waterfalls_registered(watcher.descriptor()).await?;
+        let missing = watcher.missing_txids(&response);
+        let txs = self.get_txs(&missing).await.into_result()?;
+        watcher.insert_txs(txs.into_iter().map(|(_, tx)| tx));
+        Ok(watcher.observe(&response))
+    }
+
+    /// Scan `descriptor`, recording the response in the [`OfflineCache`] of the client if
+    /// any. In offline mode the last recorded scan is returned instead, marked stale, see
+    /// [`crate::offline`].
+    pub async fn waterfalls_cached(
+        &self,
+        descriptor: &str,
+    ) -> Result<Cached<WaterfallResponse>, Error> {
+        match &self.offline_cache {
+            Some(cache) if cache.is_offline() => cache.scan(descriptor).ok_or(Error::Offline),
+            cache => {
+                let response = self.waterfalls(descriptor).await?;
+                if let Some(cache) = cache {
+                    cache.insert_scan(descriptor, &response);
+                }
+                Ok(Cached::fresh(response))
+            }
+        }
+    }
+
+    /// Like [`Self::wallet_summary`], recording the scan and its transactions in the
+    /// [`OfflineCache`] of the client if any. In offline mode the summary is computed from the
+    /// cache instead, marked stale, see [`crate::offline`].
+    pub async fn wallet_summary_cached(
+        &self,
+        descriptor: &str,
+    ) -> Result<Cached<WalletSummary>, Error> {
+        match &self.offline_cache {
+            Some(cache) if cache.is_offline() => cache.summary(descriptor).ok_or(Error::Offline),
+            cache => {
+                let response = self.waterfalls(descriptor).await?;
+                let txs = self.hydrate(&response).await.into_result()?;
+                let txs: BTreeMap<_, _> = txs.into_iter().collect();
+                if let Some(cache) = cache {
+                    cache.insert_scan(descriptor, &response);
+                    cache.insert_txs(txs.values().cloned());
+                }
+                Ok(Cached::fresh(response.summary(&txs)))
+            }
+        }
+    }
+
+    /// Scan `descriptor`, fetch its transactions and collect the [`CosignerData`] another
+    /// signer of the wallet needs to build a PSBT, see [`crate::cosigner`]
+    pub async fn cosigner_data(&self, descriptor: &str) -> Result<CosignerData, Error> {
+        let response = self.waterfalls(descriptor).await?;
+        let txs = self.hydrate(&response).await.into_result()?;
+        let txs = txs.into_iter().collect();
+        Ok(CosignerData::new(&response.summary(&txs).utxos, &txs))
+    }
+
+    /// Scan `descriptor`, fetch its transactions and collect the [`SyncPayload`] an air-gapped
+    /// signer of the wallet needs, see [`crate::payload`]
+    pub async fn sync_payload(&self, descriptor: &str) -> Result<SyncPayload, Error> {
+        let response = self.waterfalls(descriptor).await?;
+        let txs = self.hydrate(&response).await.into_result()?;
+        let txs = txs.into_iter().collect();
+        Ok(SyncPayload::new(&response.summary(&txs).utxos, &txs))
+    }
+
+    /// Get the verbose [`Tx`] of `txid`, including the outputs spent by its inputs, or `None`
+    /// if the server doesn't have it
+    pub async fn get_tx_info(&self, txid: &Txid) -> Result<Option<Tx>, Error> {
+        match self
+            .get_response_json_with_query(&format!("/tx/{txid}"), &[])
+            .await
+        {
+            Ok(tx) => Ok(Some(tx)),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the [`MempoolEntry`] of the unconfirmed transaction `txid`, or `None` if it isn't in
+    /// the mempool or the server doesn't expose mempool entries
+    ///
+    /// `/mempool/tx/{txid}` isn't an Esplora or Waterfalls endpoint, the server must expose it as
+    /// an extension. Against a Bitcoin Core node use `CoreRpcClient::get_mempool_entry` instead.
+    #[cfg(feature = "server-extensions")]
+    pub async fn get_mempool_entry(&self, txid: &Txid) -> Result<Option<MempoolEntry>, Error> {
+        match self
+            .get_response_json_with_query(&format!("/mempool/tx/{txid}"), &[])
+            .await
+        {
+            Ok(entry) => Ok(Some(entry)),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the [`RelayPolicy`] of the node behind the server, with every limit unknown if the
+    /// server doesn't expose it
+    ///
+    /// `/v1/relay_policy` isn't an Esplora or Waterfalls endpoint, the server must expose it as an
+    /// extension. Against a Bitcoin Core node use `CoreRpcClient::relay_policy` instead.
+    #[cfg(feature = "server-extensions")]
+    pub async fn relay_policy(&self) -> Result<RelayPolicy, Error> {
+        match self
+            .get_response_json_with_query("/v1/relay_policy", &[])
+            .await
+        {
+            Ok(policy) => Ok(policy),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(RelayPolicy::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check that `transaction` paying `fee` would be relayed under the [`RelayPolicy`] of
+    /// the server before broadcasting it, the Bitcoin Core defaults without the
+    /// `server-extensions` feature
+    pub async fn check_broadcast(
+        &self,
+        transaction: &Transaction,
+        fee: Amount,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "server-extensions")]
+        let policy = self.relay_policy().await?;
+        #[cfg(not(feature = "server-extensions"))]
+        let policy = RelayPolicy::default();
+        Ok(policy.check(transaction, fee)?)
+    }
+
+    /// Fetch every transaction seen in `response` with the outputs spent by its inputs, using
+    /// the verbose or the raw transactions according to `strategy`
+    pub async fn hydrate_with_prevouts(
+        &self,
+        response: &WaterfallResponse,
+        strategy: HydrationStrategy,
+    ) -> BatchResult<Txid, HydratedTx> {
+        let mut result = BatchResult::default();
+        let mut raw_txs = HashMap::new();
+        let mut verbose = strategy != HydrationStrategy::RawFirst;
+        for txid in response.txids() {
+            let verbose_tx = match verbose {
+                true => self.get_tx_info(&txid).await,
+                false => Ok(None),
+            };
+            let hydrated = match verbose_tx {
+                Ok(Some(tx)) => Ok(HydratedTx::from(tx)),
+                Ok(None) => {
+                    // The server doesn't return verbose transactions
+                    verbose &= strategy != HydrationStrategy::Auto;
+                    self.hydrate_raw(&txid, &mut raw_txs).await
+                }
+                Err(e) => Err(e),
+            };
+            match hydrated {
+                Ok(tx) => result.items.push((txid, tx)),
+                Err(e) => result.errors.push((txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Fetch the raw transaction `txid` and the parents of its inputs, reusing those in
+    /// `raw_txs`
+    async fn hydrate_raw(
+        &self,
+        txid: &Txid,
+        raw_txs: &mut HashMap<Txid, Transaction>,
+    ) -> Result<HydratedTx, Error> {
+        let tx = match raw_txs.get(txid) {
+            Some(tx) => tx.clone(),
+            None => self.get_tx_no_opt(txid).await?,
+        };
+        raw_txs.insert(*txid, tx.clone());
+        let mut prevouts = Vec::with_capacity(tx.input.len());
+        for input in &tx.input {
+            let previous_output = input.previous_output;
+            if previous_output.is_null() {
+                prevouts.push(None);
+                continue;
+            }
+            if let Entry::Vacant(entry) = raw_txs.entry(previous_output.txid) {
+                entry.insert(self.get_tx_no_opt(&previous_output.txid).await?);
+            }
+            let parent = &raw_txs[&previous_output.txid];
+            prevouts.push(parent.output.get(previous_output.vout as usize).cloned());
+        }
+        Ok(HydratedTx { tx, prevouts })
+    }
+
+    /// Query the waterfalls endpoint with a descriptor and/or addresses, merging the results
+    /// into a single [`WaterfallResponse`].
+    ///
+    /// One request is made for the descriptor and one for the addresses, unless the addresses
+    /// exceed the server limit and must be split, see [`Self::waterfalls_addresses_chunked`].
+    /// An empty query returns an empty response without contacting the server.
+    pub async fn waterfalls_query(
+        &self,
+        query: &WaterfallsQuery,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut response = match &query.descriptor {
+            Some(descriptor) => self.waterfalls(descriptor).await?,
+            None => WaterfallResponse::default(),
+        };
+        if !query.addresses.is_empty() {
+            let addresses = &query.addresses;
+            response.merge(
+                self.waterfalls_addresses_chunked(addresses, addresses.len())
+                    .await?,
+            );
+        }
+        Ok(response)
     }
 
     /// Query waterfalls with version-specific parameters
@@ -282,10 +1505,196 @@ impl<S: Sleeper> AsyncClient<S> {
         self.get_response_json_with_query(&path, &query_refs).await
     }
 
+    /// Query the waterfalls endpoint with a descriptor, following pages until the server has
+    /// no more, and merge them into a single [`WaterfallResponse`].
+    ///
+    /// `page_size` is the number of scripts per page returned by the server, see
+    /// [`WaterfallResponse::pagination`].
+    /// Use [`Self::waterfalls_resume`] with a [`ScanCursor`] to keep the pages received before a
+    /// failure.
+    pub async fn waterfalls_all_pages(
+        &self,
+        descriptor: &str,
+        page_size: usize,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut cursor = ScanCursor::new(descriptor, page_size);
+        self.waterfalls_resume(&mut cursor).await?;
+        Ok(cursor.response)
+    }
+
+    /// Continue the scan of `cursor` from its next page until the last one.
+    ///
+    /// On failure the pages received so far are kept in `cursor`, so the scan can be resumed
+    /// by calling this again later.
+    pub async fn waterfalls_resume(&self, cursor: &mut ScanCursor) -> Result<(), Error> {
+        while !cursor.complete {
+            let page = Some(cursor.next_page);
+            let page_response = self
+                .waterfalls_version(&cursor.descriptor, 4, page, None, false)
+                .await?;
+            cursor.advance(page_response);
+        }
+        Ok(())
+    }
+
     /// Get a [`BlockHeader`] given a particular block hash.
+    ///
+    /// If the client has a [`HeaderCache`] it is looked up first and filled on miss.
     pub async fn get_header_by_hash(&self, block_hash: &BlockHash) -> Result<BlockHeader, Error> {
-        self.get_response_hex(&format!("/block/{block_hash}/header"))
-            .await
+        if let Some(header) = self
+            .header_cache
+            .as_ref()
+            .and_then(|cache| cache.header_by_hash(block_hash))
+        {
+            return Ok(header);
+        }
+        let header: BlockHeader = self
+            .get_response_hex(&format!("/block/{block_hash}/header"))
+            .await?;
+        if let Some(cache) = &self.header_cache {
+            cache.insert(None, header);
+        }
+        Ok(header)
+    }
+
+    /// Get the [`HeaderCache`] of this client, if any.
+    pub fn header_cache(&self) -> Option<&HeaderCache> {
+        self.header_cache.as_ref()
+    }
+
+    /// Get the [`TxCache`] of this client, if any.
+    pub fn tx_cache(&self) -> Option<&TxCache> {
+        self.tx_cache.as_ref()
+    }
+
+    /// Get the header of the block `block_hash` of an Elements chain such as Liquid, with its
+    /// dynafed fields, see [`ElementsHeader`].
+    ///
+    /// Fails with [`Error::InvalidResponse`] if the header doesn't hash to `block_hash`.
+    pub async fn get_elements_header(
+        &self,
+        block_hash: &BlockHash,
+    ) -> Result<ElementsHeader, Error> {
+        let hex = self
+            .get_response_text(&format!("/block/{block_hash}/header"))
+            .await?;
+        let header = parse_elements_header(&Vec::from_hex(hex.trim())?)?;
+        if header.block_hash() != *block_hash {
+            return Err(Error::InvalidResponse);
+        }
+        Ok(header)
+    }
+
+    /// Get the txids of the block `block_hash`, in block order.
+    ///
+    /// The txids are checked against the merkle root of the block header, fetched with
+    /// [`Self::get_header_by_hash`], see [`crate::verify_block_txids`].
+    pub async fn get_block_txids(&self, block_hash: &BlockHash) -> Result<Vec<Txid>, Error> {
+        let path = format!("/block/{block_hash}/txids");
+        let txids: Vec<Txid> = self.get_response_json_with_query(&path, &[]).await?;
+        let header = self.get_header_by_hash(block_hash).await?;
+        crate::verify_block_txids(&header, &txids).map_err(Error::HeaderValidation)?;
+        Ok(txids)
+    }
+
+    /// Drop the cached headers and unconfirm the cached scan entries above `fork_height`, after
+    /// the blocks above it have been reorged out.
+    ///
+    /// Called automatically when a scan shows a [`crate::Reorg`] of the [`HeaderCache`].
+    pub fn invalidate_above(&self, fork_height: u32) {
+        if let Some(cache) = &self.header_cache {
+            cache.remove_above(fork_height);
+        }
+        if let Some(cache) = &self.block_hash_cache {
+            cache.remove_above(fork_height);
+        }
+        if let Some(cache) = &self.offline_cache {
+            cache.rollback(fork_height);
+        }
+    }
+
+    /// The number of concurrent requests of a batched call asking for `requested`, one in
+    /// deterministic mode
+    fn concurrency(&self, requested: usize) -> usize {
+        if self.deterministic {
+            1
+        } else {
+            self.sync_profile.concurrency(requested)
+        }
+    }
+
+    /// Invalidate the caches if `response` shows a reorg, and record its tip in the block hash
+    /// cache
+    fn check_reorg(&self, response: WaterfallResponse) -> WaterfallResponse {
+        let reorg = self
+            .header_cache
+            .as_ref()
+            .and_then(|cache| cache.find_reorg(&response));
+        if let Some(reorg) = reorg {
+            info!(
+                "block {} at height {} reorged out, invalidating the caches",
+                reorg.cached, reorg.height
+            );
+            self.invalidate_above(reorg.fork_height());
+        }
+        if let (Some(cache), Some(meta)) = (&self.block_hash_cache, &response.tip_meta) {
+            cache.observe_tip(meta.h.to_u32());
+        }
+        response
+    }
+
+    /// Create a [`HeaderChain`] for `network` anchored at the highest checkpoint at or below
+    /// `start_height`, using the checkpoints and assume valid height of the [`Builder`].
+    pub fn header_chain(&self, network: Network, start_height: u32) -> HeaderChain {
+        let checkpoints = self
+            .checkpoints
+            .clone()
+            .unwrap_or_else(|| Checkpoint::defaults(network));
+        HeaderChain::new(network, checkpoints, start_height)
+            .with_assume_valid_height(self.assume_valid_height)
+    }
+
+    /// Fetch the headers from the tip of `chain` up to `to_height`, validating and connecting
+    /// them to `chain`.
+    ///
+    /// A chain anchored above genesis is first seeded with the headers from the start of the
+    /// difficulty period of its anchor, see [`HeaderChain::seed`].
+    ///
+    /// Validated headers are added to the [`HeaderCache`], if any, indexed by height.
+    /// If the sync fails or is cancelled, `chain` keeps the headers connected so far and can be
+    /// synced again later.
+    pub async fn sync_headers(&self, chain: &mut HeaderChain, to_height: u32) -> Result<(), Error> {
+        if !chain.is_seeded(to_height) {
+            let hashes = self
+                .get_block_hashes(
+                    chain.seed_height()..=chain.anchor().height,
+                    self.sync_profile.header_sync_concurrency(),
+                )
+                .await?;
+            let mut headers = Vec::with_capacity(hashes.len());
+            for hash in &hashes {
+                headers.push(self.get_header_by_hash(hash).await?);
+            }
+            chain.seed(headers)?;
+        }
+        while chain.tip_height() < to_height {
+            let from = chain.tip_height() + 1;
+            let to = to_height.min(from.saturating_add(HEADER_SYNC_BATCH - 1));
+            let hashes = self
+                .get_block_hashes(from..=to, self.sync_profile.header_sync_concurrency())
+                .await?;
+            for (height, hash) in (from..=to).zip(hashes) {
+                let header = self.get_header_by_hash(&hash).await?;
+                if header.block_hash() != hash {
+                    return Err(Error::InvalidResponse);
+                }
+                chain.connect(header)?;
+                if let Some(cache) = &self.header_cache {
+                    cache.insert(Some(height), header);
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Get the server's public key for encryption
@@ -308,6 +1717,40 @@ impl<S: Sleeper> AsyncClient<S> {
         self.post_request_hex("/tx", transaction).await
     }
 
+    /// Broadcast the transactions of `queue` in order, removing those the server accepted,
+    /// already knows or rejected.
+    ///
+    /// The flush stops at the first connection or server error, keeping the remaining
+    /// transactions queued so that a later flush retries them.
+    pub async fn flush_broadcast_queue(&self, queue: &BroadcastQueue) -> FlushReport {
+        let mut report = FlushReport::default();
+        for tx in queue.pending() {
+            let result = self.broadcast(&tx).await;
+            if !queue.record(&mut report, tx.compute_txid(), result) {
+                break;
+            }
+        }
+        report
+    }
+
+    /// Seconds elapsed since the timestamp of the tip block, measured on the server clock
+    /// when a [`ClockOffset`] estimate is available.
+    ///
+    /// Block timestamps may be ahead of the current time, in which case the age is zero.
+    pub async fn tip_age(&self) -> Result<u64, Error> {
+        let tip = self.get_tip_hash().await?;
+        let header = self.get_header_by_hash(&tip).await?;
+        let offset = self.clock_offset.clone().unwrap_or_default();
+        Ok(offset.elapsed_since(u64::from(header.time)))
+    }
+
+    /// Returns true if the tip block is at most `max_age` seconds old, allowing for the
+    /// configured clock skew tolerance.
+    pub async fn is_tip_fresh(&self, max_age: u64) -> Result<bool, Error> {
+        let age = self.tip_age().await?;
+        Ok(age <= max_age.saturating_add(self.clock_skew_tolerance))
+    }
+
     /// Get the [`BlockHash`] of the current blockchain tip.
     pub async fn get_tip_hash(&self) -> Result<BlockHash, Error> {
         self.get_response_text("/blocks/tip/hash")
@@ -315,11 +1758,216 @@ impl<S: Sleeper> AsyncClient<S> {
             .map(|block_hash| BlockHash::from_str(&block_hash).map_err(Error::HexToArray))?
     }
 
-    /// Get the [`BlockHash`] of a specific block height
+    /// Measure the tip, the time since the last block and the latency of the server, see
+    /// [`crate::selector`]
+    pub async fn probe(&self) -> Result<ServerProbe, Error> {
+        let started = Instant::now();
+        let tip = self.get_tip_hash().await?;
+        let seconds_since_block = parse_seconds_since_block(&self.time_since_last_block().await?);
+        Ok(ServerProbe {
+            tip,
+            seconds_since_block,
+            latency: started.elapsed() / 2,
+        })
+    }
+
+    /// Probe every server of `selector` concurrently and promote the best one as primary,
+    /// returning its index, see [`crate::selector`]
+    pub async fn score_servers(selector: &ServerSelector<Self>) -> usize {
+        let servers = selector.servers();
+        let probes = buffered(servers.iter().map(|client| client.probe()), servers.len()).await;
+        selector.record(probes)
+    }
+
+    /// Query the tip of every server in `clients` concurrently and return the one at least
+    /// `threshold` of them agree on, see [`crate::quorum`]
+    pub async fn tip_quorum(clients: &[Self], threshold: usize) -> Result<TipQuorum, Error> {
+        let tips = buffered(
+            clients.iter().map(|client| client.get_tip_hash()),
+            clients.len(),
+        )
+        .await;
+        TipQuorum::from_tips(tips, threshold)
+    }
+
+    /// Identify the network of the server from its genesis block, see [`NetworkInfo`].
+    ///
+    /// Elements servers are recognized by their genesis header, which isn't a Bitcoin header.
+    /// The result is cached and shared with the clones of the client.
+    pub async fn network_info(&self) -> Result<NetworkInfo, Error> {
+        let cached = *self.network_info.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(info) = cached {
+            return Ok(info);
+        }
+        let genesis_hash = self.get_block_hash(0).await?;
+        let family = match self.get_header_by_hash(&genesis_hash).await {
+            Ok(_) => ChainFamily::Bitcoin,
+            Err(Error::BitcoinEncoding(_)) => ChainFamily::Elements,
+            Err(e) => return Err(e),
+        };
+        let info = NetworkInfo::new(genesis_hash, family);
+        *self.network_info.lock().unwrap_or_else(|e| e.into_inner()) = Some(info);
+        Ok(info)
+    }
+
+    /// Get the [`BlockHash`] of a specific block height, from the [`BlockHashCache`] if set
     pub async fn get_block_hash(&self, block_height: u32) -> Result<BlockHash, Error> {
-        self.get_response_text(&format!("/block-height/{block_height}"))
+        if let Some(hash) = self
+            .block_hash_cache
+            .as_ref()
+            .and_then(|c| c.get(block_height))
+        {
+            return Ok(hash);
+        }
+        let hash = self
+            .get_response_text(&format!("/block-height/{block_height}"))
             .await
-            .map(|block_hash| BlockHash::from_str(&block_hash).map_err(Error::HexToArray))?
+            .map(|block_hash| BlockHash::from_str(&block_hash).map_err(Error::HexToArray))??;
+        if let Some(cache) = &self.block_hash_cache {
+            cache.insert(block_height, hash);
+        }
+        Ok(hash)
+    }
+
+    /// Subscribe to the blocks from `from_height`, e.g. the tip height plus one, see
+    /// [`BlockSubscription`].
+    pub fn subscribe_blocks(&self, from_height: u32) -> BlockSubscription<S>
+    where
+        S: Clone,
+    {
+        BlockSubscription::new(self.clone(), from_height)
+    }
+
+    /// Get the [`BlockHash`] of every height in `heights`, in order, with up to `concurrency`
+    /// requests in flight
+    pub async fn get_block_hashes(
+        &self,
+        heights: RangeInclusive<u32>,
+        concurrency: usize,
+    ) -> Result<Vec<BlockHash>, Error> {
+        let hashes = buffered(
+            heights.map(|height| self.get_block_hash(height)),
+            self.concurrency(concurrency),
+        )
+        .await;
+        hashes.into_iter().collect()
+    }
+
+    /// Get the fee estimates in sat/vB indexed by confirmation target in blocks.
+    ///
+    /// Requested from the Esplora fallback if set, see [`Builder::esplora_fallback`].
+    pub async fn get_fee_estimates(&self) -> Result<HashMap<u16, f64>, Error> {
+        let response = self.get_with_retry("/fee-estimates").await?;
+
+        if !response.status().is_success() {
+            return Err(Error::HttpResponse {
+                status: response.status().as_u16(),
+                message: response.text().await?,
+            });
+        }
+
+        let estimates: HashMap<String, f64> = decode_json(self.read_body(response).await?)?;
+        estimates
+            .into_iter()
+            .map(|(target, rate)| Ok((target.parse()?, rate)))
+            .collect()
+    }
+
+    /// Get the state of the mempool, with its fee histogram.
+    ///
+    /// Requested from the Esplora fallback if set, see [`Builder::esplora_fallback`].
+    pub async fn get_mempool_info(&self) -> Result<MempoolInfo, Error> {
+        let response = self.get_with_retry("/mempool").await?;
+
+        if !response.status().is_success() {
+            return Err(Error::HttpResponse {
+                status: response.status().as_u16(),
+                message: response.text().await?,
+            });
+        }
+
+        decode_json(self.read_body(response).await?)
+    }
+
+    /// The mean interval of the last [`ETA_INTERVAL_BLOCKS`] blocks, from their headers
+    pub async fn recent_block_interval(&self) -> Result<Option<Duration>, Error> {
+        let mut hash = self.get_tip_hash().await?;
+        let mut times = vec![];
+        for _ in 0..=ETA_INTERVAL_BLOCKS {
+            let header = self.get_header_by_hash(&hash).await?;
+            times.push(header.time);
+            if header.prev_blockhash == BlockHash::all_zeros() {
+                break;
+            }
+            hash = header.prev_blockhash;
+        }
+        times.reverse();
+        Ok(mean_block_interval(&times))
+    }
+
+    /// Estimate when a transaction paying `fee_rate` sat/vB would confirm, from the mempool
+    /// fee histogram and the recent block interval, see [`crate::eta`]
+    pub async fn estimate_confirmation_eta(&self, fee_rate: f64) -> Result<ConfirmationEta, Error> {
+        let mempool = self.get_mempool_info().await?;
+        let interval = self.recent_block_interval().await?;
+        Ok(estimate_eta(
+            &mempool.fee_histogram,
+            fee_rate,
+            interval.unwrap_or(DEFAULT_BLOCK_INTERVAL),
+        ))
+    }
+
+    /// Get the spending status of the output `index` of `txid`, or `None` if the server
+    /// doesn't know the transaction.
+    ///
+    /// Requested from the Esplora fallback if set, see [`Builder::esplora_fallback`].
+    pub async fn get_output_status(
+        &self,
+        txid: &Txid,
+        index: u32,
+    ) -> Result<Option<OutputStatus>, Error> {
+        let response = self
+            .get_with_retry(&format!("/tx/{txid}/outspend/{index}"))
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(Error::HttpResponse {
+                status: response.status().as_u16(),
+                message: response.text().await?,
+            });
+        }
+
+        Ok(Some(decode_json(self.read_body(response).await?)?))
+    }
+
+    /// Find the input spending each of `outpoints`, `None` if unspent, with up to
+    /// `concurrency` requests in flight, see [`Self::get_output_status`]
+    pub async fn find_spends(
+        &self,
+        outpoints: &[OutPoint],
+        concurrency: usize,
+    ) -> BatchResult<OutPoint, Option<Spend>> {
+        let statuses = buffered(
+            outpoints
+                .iter()
+                .map(|outpoint| self.get_output_status(&outpoint.txid, outpoint.vout)),
+            self.concurrency(concurrency),
+        )
+        .await;
+        let mut result = BatchResult::default();
+        for (outpoint, status) in outpoints.iter().zip(statuses) {
+            match status {
+                Ok(Some(status)) => result.items.push((*outpoint, status.spend())),
+                Ok(None) => result
+                    .errors
+                    .push((*outpoint, Error::TransactionNotFound(outpoint.txid))),
+                Err(e) => result.errors.push((*outpoint, e)),
+            }
+        }
+        result
     }
 
     /// Get transaction history for the specified address in Esplora-compatible format
@@ -328,6 +1976,79 @@ impl<S: Sleeper> AsyncClient<S> {
         self.get_response_text(&path).await
     }
 
+    /// Get the confirmed transactions of `address`, newest first and 25 per page. Pass the
+    /// last txid of a page as `last_seen` to get the following one.
+    pub async fn get_address_txs_chain(
+        &self,
+        address: &Address,
+        last_seen: Option<&Txid>,
+    ) -> Result<Vec<Tx>, Error> {
+        let path = match last_seen {
+            Some(last_seen) => format!("/address/{address}/txs/chain/{last_seen}"),
+            None => format!("/address/{address}/txs/chain"),
+        };
+        self.get_response_json_with_query(&path, &[]).await
+    }
+
+    /// Get a page of the transactions of `address`, newest first, as Esplora pages them:
+    /// without `last_seen` the unconfirmed ones and the first 25 confirmed ones, with
+    /// `last_seen` the 25 confirmed ones following it.
+    ///
+    /// Pass the last confirmed txid of a page to get the next one, an empty page ends the
+    /// history.
+    pub async fn get_address_txs_after(
+        &self,
+        address: &Address,
+        last_seen: Option<Txid>,
+    ) -> Result<Vec<Tx>, Error> {
+        match last_seen {
+            Some(last_seen) => self.get_address_txs_chain(address, Some(&last_seen)).await,
+            None => {
+                let path = format!("/address/{address}/txs");
+                self.get_response_json_with_query(&path, &[]).await
+            }
+        }
+    }
+
+    /// Get the unconfirmed transactions of `address`, newest first and at most 50
+    pub async fn get_address_txs_mempool(&self, address: &Address) -> Result<Vec<Tx>, Error> {
+        let path = format!("/address/{address}/txs/mempool");
+        self.get_response_json_with_query(&path, &[]).await
+    }
+
+    /// Get the funded and spent outputs and the transaction count of `address`, on chain and
+    /// in the mempool
+    pub async fn get_address_stats(&self, address: &Address) -> Result<AddressStats, Error> {
+        let path = format!("/address/{address}");
+        self.get_response_json_with_query(&path, &[]).await
+    }
+
+    /// Return a client for the server at `path` on the same host, e.g. `/liquid/api` for the
+    /// Liquid instance behind the domain of a Bitcoin one.
+    ///
+    /// The path replaces the one of the base URL. The returned client shares the connection
+    /// pool and the transport settings of this one, while the state tied to the server chain
+    /// is reset: the header and transaction caches, the checkpoints, the scripts tokens, the
+    /// Esplora fallback, the decoy pool and the identified network.
+    pub fn with_base_path(&self, path: &str) -> Self
+    where
+        S: Clone,
+    {
+        AsyncClient {
+            url: crate::with_base_path(&self.url, path),
+            header_cache: None,
+            tx_cache: None,
+            block_hash_cache: None,
+            checkpoints: None,
+            scripts_tokens: None,
+            esplora_fallback: None,
+            offline_cache: None,
+            decoy_pool: None,
+            network_info: Arc::new(Mutex::new(None)),
+            ..self.clone()
+        }
+    }
+
     /// Get the underlying base URL.
     pub fn url(&self) -> &str {
         &self.url
@@ -338,18 +2059,61 @@ impl<S: Sleeper> AsyncClient<S> {
         &self.client
     }
 
-    /// Sends a GET request to the given `url`, retrying failed attempts
-    /// for retryable error codes until max retries hit.
-    async fn get_with_retry(&self, url: &str) -> Result<Response, Error> {
-        let mut delay = BASE_BACKOFF_MILLIS;
+    /// Sends a GET request to the given `path`, retrying failed attempts
+    /// for retryable error codes until max retries hit or the deadline would be exceeded.
+    ///
+    /// Endpoints the Waterfalls server lacks are requested from the Esplora fallback if set.
+    async fn get_with_retry(&self, path: &str) -> Result<Response, Error> {
+        let mut delay = self.base_backoff;
         let mut attempts = 0;
 
+        let fallback = self
+            .esplora_fallback
+            .as_ref()
+            .filter(|_| crate::is_esplora_only(path));
+        let (url, client) = match fallback {
+            Some((fallback, client)) => {
+                debug!("routing {path} to the esplora fallback {fallback}");
+                (format!("{fallback}{path}"), client)
+            }
+            None => (format!("{}{}", self.url, path), &self.client),
+        };
+
+        let deadline = self
+            .deadline
+            .map(|seconds| Instant::now() + Duration::from_secs(seconds));
+
         loop {
-            match self.client.get(url).send().await? {
-                resp if attempts < self.max_retries && is_status_retryable(resp.status()) => {
-                    S::sleep(delay).await;
+            let mut request = client.get(&url);
+            if let Some(deadline) = deadline {
+                // The timeout of the request replaces the one of the inner client
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let timeout = self.timeout.map(Duration::from_secs);
+                request = request.timeout(timeout.map_or(remaining, |t| t.min(remaining)));
+            }
+            let resp = self
+                .send_signed(request, fallback.is_none())
+                .await
+                .map_err(|e| e.at_attempt(attempts + 1).past_deadline(deadline))?;
+            match resp {
+                resp if S::RETRIES
+                    && attempts < self.max_retries
+                    && is_status_retryable(resp.status()) =>
+                {
+                    let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok());
+                    let retry_after = header(header::RETRY_AFTER).and_then(|value| {
+                        retry_after_delay(value, header(header::DATE), self.clock_offset.as_ref())
+                    });
+                    let wait = retry_after.unwrap_or(delay);
+                    if deadline.map_or(false, |deadline| Instant::now() + wait >= deadline) {
+                        return Err(Error::DeadlineExceeded {
+                            endpoint: resp.url().path().to_string(),
+                            attempt: attempts + 1,
+                        });
+                    }
+                    self.cancellable(S::sleep(wait)).await?;
                     attempts += 1;
-                    delay *= 2;
+                    delay = next_backoff(delay, self.max_backoff);
                 }
                 resp => return Ok(resp),
             }
@@ -357,13 +2121,131 @@ impl<S: Sleeper> AsyncClient<S> {
     }
 }
 
+/// A [`reqwest::ClientBuilder`] with the proxy and timeout of `builder`
+fn connection_builder(builder: &Builder) -> Result<reqwest::ClientBuilder, Error> {
+    #[allow(unused_mut)]
+    let mut client_builder = Client::builder();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(proxy) = crate::proxy_url(builder.proxy.as_deref(), builder.require_proxy_dns)? {
+        client_builder = client_builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(timeout) = builder.timeout {
+        client_builder = client_builder.timeout(core::time::Duration::from_secs(timeout));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(seconds) = builder.connect_timeout {
+        client_builder = client_builder.connect_timeout(core::time::Duration::from_secs(seconds));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(seconds) = builder.pool_idle_timeout {
+        client_builder = client_builder.pool_idle_timeout(core::time::Duration::from_secs(seconds));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(seconds) = builder.tcp_keepalive {
+        client_builder = client_builder.tcp_keepalive(core::time::Duration::from_secs(seconds));
+    }
+
+    #[cfg(all(feature = "async-connection-stats", not(target_arch = "wasm32")))]
+    if let Some(stats) = &builder.connection_stats {
+        client_builder = client_builder.connector_layer(CountConnections(stats.clone()));
+    }
+
+    Ok(client_builder)
+}
+
 fn is_status_retryable(status: reqwest::StatusCode) -> bool {
     RETRYABLE_ERROR_CODES.contains(&status.as_u16())
 }
 
+/// Await `futures` polling at most `limit` of them at once, returning their outputs in order
+fn buffered<F: std::future::Future>(
+    futures: impl IntoIterator<Item = F>,
+    limit: usize,
+) -> Buffered<F> {
+    let futures: Vec<_> = futures.into_iter().map(|f| Some(Box::pin(f))).collect();
+    Buffered {
+        outputs: futures.iter().map(|_| None).collect(),
+        futures,
+        limit: limit.max(1),
+    }
+}
+
+/// The future returned by [`buffered`]
+struct Buffered<F: std::future::Future> {
+    futures: Vec<Option<std::pin::Pin<Box<F>>>>,
+    outputs: Vec<Option<F::Output>>,
+    limit: usize,
+}
+
+// The futures are boxed and the outputs are never pinned
+impl<F: std::future::Future> Unpin for Buffered<F> {}
+
+impl<F: std::future::Future> std::future::Future for Buffered<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let this = self.get_mut();
+        // The first `limit` unfinished futures are the ones in flight, a finished one lets the
+        // next start in the same pass
+        let mut pending = 0;
+        for (future, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if pending == this.limit {
+                break;
+            }
+            if let Some(f) = future {
+                match f.as_mut().poll(cx) {
+                    std::task::Poll::Ready(out) => {
+                        *output = Some(out);
+                        *future = None;
+                    }
+                    std::task::Poll::Pending => pending += 1,
+                }
+            }
+        }
+        if pending > 0 {
+            return std::task::Poll::Pending;
+        }
+        let outputs = this.outputs.drain(..);
+        std::task::Poll::Ready(outputs.map(|o| o.expect("every future finished")).collect())
+    }
+}
+
 pub trait Sleeper: 'static {
     type Sleep: std::future::Future<Output = ()>;
     fn sleep(dur: std::time::Duration) -> Self::Sleep;
+
+    /// Whether the clients retry and pace their requests. When false every request is sent
+    /// once without waiting and the code waiting and retrying is removed from the build, see
+    /// [`SingleShot`].
+    const RETRIES: bool = true;
+}
+
+/// A [`Sleeper`] for runtimes without timers, such as wasm or serverless functions.
+///
+/// A client built with [`crate::Builder::build_async_single_shot`] sends every request once and
+/// returns its failures to the caller: retryable statuses, `Retry-After` headers and the spacing
+/// of the [`crate::SyncProfile`] are ignored, and [`AsyncClient::waterfalls_consistent`] fails
+/// at the first inconsistent tip. No timer is ever created.
+#[derive(Debug, Clone, Copy)]
+pub struct SingleShot;
+
+impl Sleeper for SingleShot {
+    type Sleep = std::future::Ready<()>;
+
+    fn sleep(_dur: std::time::Duration) -> Self::Sleep {
+        std::future::ready(())
+    }
+
+    const RETRIES: bool = false;
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,119 @@
+//! Audit trail of the network calls made by the clients.
+//!
+//! An [`AuditSink`] set with [`crate::Builder::audit_sink`] receives an [`AuditRecord`] for
+//! every request sent, including retries and requests that failed without a response.
+
+use std::fmt;
+use std::time::Duration;
+
+/// The kind of data a request is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndpointClass {
+    /// Descriptor or addresses scans
+    Waterfalls,
+    /// Transaction lookups
+    Transaction,
+    /// Transaction broadcasts
+    Broadcast,
+    /// Block headers
+    Header,
+    /// Block hashes, including the tip
+    Block,
+    /// Fee estimates
+    FeeEstimates,
+    /// Address histories
+    Address,
+    /// Server information, such as its recipient and address
+    Server,
+    /// Any other endpoint
+    Other,
+}
+
+impl EndpointClass {
+    /// Classify a request given its HTTP `method` and `url`
+    pub fn classify(method: &str, url: &str) -> Self {
+        let path = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest.find('/').map_or("", |i| &rest[i..]));
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').collect();
+        let has = |segment: &str| segments.contains(&segment);
+        if has("waterfalls") {
+            EndpointClass::Waterfalls
+        } else if has("tx") && method == "POST" {
+            EndpointClass::Broadcast
+        } else if has("tx") {
+            EndpointClass::Transaction
+        } else if has("header") {
+            EndpointClass::Header
+        } else if ["block", "blocks", "block-height", "time_since_last_block"]
+            .iter()
+            .any(|segment| has(segment))
+        {
+            EndpointClass::Block
+        } else if has("fee-estimates") {
+            EndpointClass::FeeEstimates
+        } else if has("address") {
+            EndpointClass::Address
+        } else if segments.iter().any(|s| s.starts_with("server_")) {
+            EndpointClass::Server
+        } else {
+            EndpointClass::Other
+        }
+    }
+}
+
+/// A request sent by a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Unix timestamp in seconds at which the request was sent
+    pub timestamp: u64,
+    /// The HTTP method
+    pub method: String,
+    /// The kind of data requested
+    pub endpoint: EndpointClass,
+    /// The server the request was sent to, as `scheme://host[:port]`
+    pub server: String,
+    /// Size of the request body
+    pub bytes_up: u64,
+    /// Size of the response body
+    pub bytes_down: u64,
+    /// Time elapsed until the response was received
+    pub duration: Duration,
+    /// The HTTP status, `None` if no response was received
+    pub status: Option<u16>,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl AuditRecord {
+    pub(crate) fn new(
+        method: &str,
+        url: &str,
+        timestamp: u64,
+        duration: Duration,
+        bytes_up: usize,
+        bytes_down: usize,
+        status: Option<u16>,
+    ) -> Self {
+        // Keep only `scheme://host[:port]`, without credentials
+        let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+        let authority = rest.split(['/', '?']).next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        AuditRecord {
+            timestamp,
+            method: method.to_string(),
+            endpoint: EndpointClass::classify(method, url),
+            server: format!("{scheme}://{host}"),
+            bytes_up: bytes_up as u64,
+            bytes_down: bytes_down as u64,
+            duration,
+            status,
+        }
+    }
+}
+
+/// Receives a record of every request sent by a client.
+pub trait AuditSink: fmt::Debug + Send + Sync {
+    /// Called once the response, or the error, of a request is received
+    fn record(&self, record: AuditRecord);
+}
@@ -0,0 +1,151 @@
+//! Authentication of requests to private Waterfalls deployments.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcoin::hashes::{hmac, sha256, Hash, HashEngine};
+
+/// Credentials for HTTP basic authentication.
+///
+/// The password is redacted from the `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    /// The user name
+    pub username: String,
+    /// The password
+    pub password: String,
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl BasicAuth {
+    /// Create basic authentication credentials
+    pub fn new(username: &str, password: &str) -> Self {
+        BasicAuth {
+            username: username.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    /// The value of the `Authorization` header for these credentials
+    pub fn header_value(&self) -> String {
+        let credentials = format!("{}:{}", self.username, self.password);
+        format!("Basic {}", base64_encode(credentials.as_bytes()))
+    }
+}
+
+/// Standard base64 encoding with padding
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Header carrying the unix timestamp a request was signed at.
+pub const TIMESTAMP_HEADER: &str = "X-Waterfalls-Timestamp";
+/// Header carrying the hex encoded request signature.
+pub const SIGNATURE_HEADER: &str = "X-Waterfalls-Signature";
+/// Header carrying the identifier of the signing key.
+pub const KEY_ID_HEADER: &str = "X-Waterfalls-Key";
+
+/// Computes authentication headers added to every request.
+pub trait RequestSigner: fmt::Debug + Send + Sync {
+    /// Headers to set on a request given its HTTP `method`, `path` including the query string,
+    /// and `body`
+    fn sign(&self, method: &str, path: &str, body: &[u8]) -> Vec<(String, String)>;
+}
+
+/// A [`RequestSigner`] computing an HMAC-SHA256 with a shared secret.
+///
+/// The signed message is `method`, `path`, and the unix timestamp separated by newlines,
+/// followed by a newline and the request body.
+#[derive(Clone)]
+pub struct HmacSigner {
+    secret: Vec<u8>,
+    key_id: Option<String>,
+}
+
+impl fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("secret", &"<redacted>")
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl HmacSigner {
+    /// Create a signer with the shared `secret`
+    pub fn new(secret: &[u8]) -> Self {
+        HmacSigner {
+            secret: secret.to_vec(),
+            key_id: None,
+        }
+    }
+
+    /// Also send `key_id` so the server can tell which secret was used
+    pub fn with_key_id(mut self, key_id: &str) -> Self {
+        self.key_id = Some(key_id.to_string());
+        self
+    }
+
+    /// The hex encoded signature of a request made at `timestamp`
+    pub fn signature(&self, method: &str, path: &str, body: &[u8], timestamp: u64) -> String {
+        let mut engine = hmac::HmacEngine::<sha256::Hash>::new(&self.secret);
+        engine.input(format!("{method}\n{path}\n{timestamp}\n").as_bytes());
+        engine.input(body);
+        hmac::Hmac::<sha256::Hash>::from_engine(engine).to_string()
+    }
+}
+
+impl RequestSigner for HmacSigner {
+    fn sign(&self, method: &str, path: &str, body: &[u8]) -> Vec<(String, String)> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut headers = vec![
+            (TIMESTAMP_HEADER.to_string(), timestamp.to_string()),
+            (
+                SIGNATURE_HEADER.to_string(),
+                self.signature(method, path, body, timestamp),
+            ),
+        ];
+        if let Some(key_id) = &self.key_id {
+            headers.push((KEY_ID_HEADER.to_string(), key_id.clone()));
+        }
+        headers
+    }
+}
+
+/// The path and query of `url`, which is what a [`RequestSigner`] signs
+#[cfg(feature = "blocking")]
+pub(crate) fn request_target(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    match without_scheme.find('/') {
+        Some(index) => &without_scheme[index..],
+        None => "/",
+    }
+}
@@ -0,0 +1,64 @@
+//! Blinding keys of Elements confidential descriptors.
+//!
+//! A Liquid descriptor such as `ct(slip77(...),elwpkh(xpub/<0;1>/*))` carries the master
+//! blinding key of the wallet, which unblinds the amounts and assets of all its outputs. A scan
+//! needs only the scripts, derived from the inner descriptor, so a server receiving the key
+//! learns more than it needs to answer.
+//!
+//! With [`crate::Builder::blinding_key_policy`] the clients remove the key before sending a
+//! descriptor: [`BlindingKeyPolicy::Strip`] sends the inner descriptor, for servers deriving
+//! scripts from it, and [`BlindingKeyPolicy::Replace`] keeps the `ct(...)` form with a key
+//! chosen by the caller, for servers requiring it. Either way the server can derive the scripts
+//! of the wallet but not unblind its transactions.
+//!
+//! The checksum of a rewritten descriptor is dropped since it no longer matches. Descriptors
+//! which aren't `ct(...)` are sent unchanged.
+
+use std::borrow::Cow;
+
+/// What the clients send in place of the blinding key of `ct(...)` descriptors, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum BlindingKeyPolicy {
+    /// Send the descriptor with its blinding key
+    #[default]
+    Keep,
+    /// Send only the inner descriptor
+    Strip,
+    /// Send the descriptor with the given blinding key, e.g. a random one
+    Replace(String),
+}
+
+impl BlindingKeyPolicy {
+    /// The descriptor to send for `descriptor` under this policy
+    pub fn apply<'a>(&self, descriptor: &'a str) -> Cow<'a, str> {
+        let inner = match (self, split_ct_descriptor(descriptor)) {
+            (BlindingKeyPolicy::Keep, _) | (_, None) => return Cow::Borrowed(descriptor),
+            (_, Some((_, inner))) => inner,
+        };
+        match self {
+            BlindingKeyPolicy::Replace(key) => Cow::Owned(format!("ct({key},{inner})")),
+            _ => Cow::Borrowed(inner),
+        }
+    }
+}
+
+/// Split a `ct(<blinding key>,<descriptor>)` descriptor in its blinding key and inner
+/// descriptor, ignoring the checksum. Returns `None` for other descriptors.
+pub fn split_ct_descriptor(descriptor: &str) -> Option<(&str, &str)> {
+    let descriptor = descriptor.split('#').next().unwrap_or_default().trim();
+    let args = descriptor.strip_prefix("ct(")?.strip_suffix(')')?;
+    let mut depth = 0usize;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                let (key, inner) = (args[..i].trim(), args[i + 1..].trim());
+                return (!key.is_empty() && !inner.is_empty()).then_some((key, inner));
+            }
+            _ => {}
+        }
+    }
+    None
+}
@@ -11,22 +11,61 @@
 
 //! Waterfalls by way of `minreq` HTTP client.
 
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace};
 
 use minreq::{Proxy, Request, Response};
+use serde::de::DeserializeOwned;
 
+use bitcoin::bip32::Xpub;
 use bitcoin::consensus::{deserialize, serialize, Decodable};
+use bitcoin::hashes::Hash;
 use bitcoin::hex::{DisplayHex, FromHex};
-use bitcoin::Address;
-use bitcoin::{block::Header as BlockHeader, BlockHash, Transaction, Txid};
+use bitcoin::{block::Header as BlockHeader, Amount, BlockHash, Transaction, Txid};
+use bitcoin::{Address, Network, OutPoint};
 
-use crate::{Builder, Error, WaterfallResponse, BASE_BACKOFF_MILLIS, RETRYABLE_ERROR_CODES};
+use crate::auth::request_target;
+use crate::cache::SCRIPTS_TOKEN_HEADER;
+use crate::clock::{retry_after_delay, unix_now};
+use crate::decoy::{remove_decoys, without_decoys, DECOY_TXS_PER_BLOCK};
+use crate::dry_run::canned_response;
+use crate::eta::{DEFAULT_BLOCK_INTERVAL, ETA_INTERVAL_BLOCKS};
+#[cfg(feature = "test-utils")]
+use crate::fixtures::CapturedScan;
+use crate::meta::MetaLog;
+use crate::profile::Throttle;
+use crate::selector::parse_seconds_since_block;
+use crate::warning::{page_warnings, stale_tip, LenientResponse};
+#[cfg(feature = "server-extensions")]
+use crate::MempoolEntry;
+use crate::{
+    decode_lenient, decode_tolerant, estimate_eta, mean_block_interval, next_backoff,
+    parse_elements_header, AddressStats, AuditRecord, AuditSink, BasicAuth, BatchResult,
+    BlindingKeyPolicy, BlockHashCache, BroadcastQueue, Builder, Cached, CancellationToken,
+    ChainFamily, Checkpoint, ClockOffset, ConfirmationEta, ConnectionStats, CosignerData,
+    DecoyPool, DescriptorRegistry, DryRun, ElementsHeader, EndpointClass, Error, FlushReport,
+    HeaderCache, HeaderChain, HydratedTx, HydrationStrategy, Issuance, LimitKind, Measured,
+    MempoolInfo, NetworkInfo, OfflineCache, OutputStatus, PartialTx, Peg, Progress, ProgressSink,
+    RelayPolicy, RequestSigner, ResponseMeta, ScanCursor, ScriptKind, ScriptsTokens, ServerProbe,
+    ServerSelector, Session, Spend, StaleWhileRevalidate, SyncPayload, SyncProfile, TextDecoding,
+    TipQuorum, Transfer, Tx, TxCache, WalletSummary, Warned, Warning, WaterfallResponse,
+    WaterfallsQuery, HEADER_SYNC_BATCH, RETRYABLE_ERROR_CODES,
+};
+#[cfg(feature = "watcher")]
+use crate::{WatchEvent, Watcher};
+
+/// How often a sleeping client checks its cancellation token
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(50);
 
 #[derive(Debug, Clone)]
 pub struct BlockingClient {
@@ -36,10 +75,70 @@ pub struct BlockingClient {
     pub proxy: Option<String>,
     /// Socket timeout.
     pub timeout: Option<u64>,
+    /// Seconds allowed to a GET request including its retries
+    pub deadline: Option<u64>,
     /// HTTP headers to set on every request made to Waterfalls server
     pub headers: HashMap<String, String>,
     /// Number of times to retry a request
     pub max_retries: usize,
+    /// Delay before the first retry, doubled at each following one
+    pub base_backoff: Duration,
+    /// Optional cap of the delay between two retries
+    pub max_backoff: Option<Duration>,
+    /// Optional cache of block headers
+    pub header_cache: Option<HeaderCache>,
+    /// Optional cache of transactions
+    pub tx_cache: Option<TxCache>,
+    /// Optional cache of the block hashes by height
+    pub block_hash_cache: Option<BlockHashCache>,
+    /// Checkpoints used by header validation, compiled-in defaults if `None`
+    pub checkpoints: Option<Vec<Checkpoint>>,
+    /// Headers at or below this height skip proof of work validation
+    pub assume_valid_height: Option<u32>,
+    /// Fail requests that would resolve the server hostname locally
+    pub require_proxy_dns: bool,
+    /// Optional signer adding authentication headers to every request
+    pub signer: Option<Arc<dyn RequestSigner>>,
+    /// Optional credentials for HTTP basic authentication
+    pub basic_auth: Option<BasicAuth>,
+    /// Optional estimate of the server clock offset, updated from the responses
+    pub clock_offset: Option<ClockOffset>,
+    /// Seconds of clock skew tolerated by freshness checks
+    pub clock_skew_tolerance: u64,
+    /// Optional Esplora server for the endpoints the Waterfalls server lacks
+    pub esplora_fallback: Option<String>,
+    /// Optional counters of the requests sent and connections opened
+    pub connection_stats: Option<ConnectionStats>,
+    /// Optional tokens of the descriptors already derived by the server
+    pub scripts_tokens: Option<ScriptsTokens>,
+    /// Record requests instead of sending them, see [`DryRun`]
+    pub dry_run: Option<DryRun>,
+    /// Optional sink receiving a record of every request sent
+    pub audit_sink: Option<Arc<dyn AuditSink>>,
+    /// Optional sink receiving the progress of the transfers
+    pub progress_sink: Option<Arc<dyn ProgressSink>>,
+    /// Optional token cancelling the requests of this client
+    pub cancellation_token: Option<CancellationToken>,
+    /// The load this client puts on the server
+    pub sync_profile: SyncProfile,
+    /// Send the requests of batched calls one at a time, in input order
+    pub deterministic: bool,
+    /// When the next request may be sent under the sync profile
+    throttle: Throttle,
+    /// What this client sends in place of the blinding key of `ct(...)` descriptors
+    pub blinding_key_policy: BlindingKeyPolicy,
+    /// How the bodies of text endpoints are decoded
+    pub text_decoding: TextDecoding,
+    /// Optional cache answering the calls in offline mode
+    pub offline_cache: Option<OfflineCache>,
+    /// Optional pool of decoys mixed into address queries
+    pub decoy_pool: Option<DecoyPool>,
+    /// Optional settings of the scanned descriptors
+    pub descriptor_registry: Option<DescriptorRegistry>,
+    /// The network of the server, once identified
+    network_info: Arc<Mutex<Option<NetworkInfo>>>,
+    /// The metadata of the responses of the current [`Self::measured`] call
+    meta_log: Option<MetaLog>,
 }
 
 impl BlockingClient {
@@ -49,8 +148,282 @@ impl BlockingClient {
             url: builder.base_url,
             proxy: builder.proxy,
             timeout: builder.timeout,
+            deadline: builder.deadline,
             headers: builder.headers,
             max_retries: builder.max_retries,
+            base_backoff: builder.base_backoff,
+            max_backoff: builder.max_backoff,
+            header_cache: builder.header_cache,
+            tx_cache: builder.tx_cache,
+            block_hash_cache: builder.block_hash_cache,
+            checkpoints: builder.checkpoints,
+            assume_valid_height: builder.assume_valid_height,
+            require_proxy_dns: builder.require_proxy_dns,
+            signer: builder.signer,
+            basic_auth: builder.basic_auth,
+            clock_offset: builder.clock_offset,
+            clock_skew_tolerance: builder.clock_skew_tolerance,
+            esplora_fallback: builder.esplora_fallback,
+            connection_stats: builder.connection_stats,
+            scripts_tokens: builder.scripts_tokens,
+            dry_run: builder.dry_run,
+            audit_sink: builder.audit_sink,
+            progress_sink: builder.progress_sink,
+            cancellation_token: builder.cancellation_token,
+            sync_profile: builder.sync_profile,
+            deterministic: builder.deterministic,
+            throttle: Throttle::default(),
+            blinding_key_policy: builder.blinding_key_policy,
+            text_decoding: builder.text_decoding,
+            offline_cache: builder.offline_cache,
+            decoy_pool: builder.decoy_pool,
+            descriptor_registry: builder.descriptor_registry,
+            network_info: Arc::new(Mutex::new(None)),
+            meta_log: None,
+        }
+    }
+
+    /// Return a client using `token` to cancel its requests.
+    ///
+    /// The token is checked before every request and during the sleeps between retries, a
+    /// request already sent runs until its response or timeout.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Fail with [`Error::Cancelled`] if the cancellation token is cancelled
+    fn check_cancelled(&self) -> Result<(), Error> {
+        match &self.cancellation_token {
+            Some(token) if token.is_cancelled() => Err(Error::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sleep for `duration`, waking up early with [`Error::Cancelled`] if the cancellation
+    /// token is cancelled
+    fn sleep(&self, duration: Duration) -> Result<(), Error> {
+        let deadline = Instant::now() + duration;
+        loop {
+            self.check_cancelled()?;
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            thread::sleep((deadline - now).min(CANCEL_CHECK_INTERVAL));
+        }
+    }
+
+    /// Add the basic authentication credentials and the headers of the configured
+    /// [`RequestSigner`] to `request`
+    fn sign(&self, mut request: Request, method: &str, url: &str, body: &[u8]) -> Request {
+        if let Some(basic_auth) = &self.basic_auth {
+            request = request.with_header("Authorization", basic_auth.header_value());
+        }
+        // Dry runs record the requests unsigned, without involving the signer
+        if let Some(signer) = self.signer.as_ref().filter(|_| self.dry_run.is_none()) {
+            for (key, value) in signer.sign(method, request_target(url), body) {
+                request = request.with_header(key, value);
+            }
+        }
+        request
+    }
+
+    /// The proxy to set on requests, if any
+    fn request_proxy(&self) -> Result<Option<Proxy>, Error> {
+        match crate::proxy_url(self.proxy.as_deref(), self.require_proxy_dns)? {
+            Some(proxy) => Ok(Some(Proxy::new(proxy)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Send `request` with the given `method`, `url` and `body`, classifying connection
+    /// failures caused by the proxy and updating the clock offset estimate
+    fn send(&self, request: Request, method: &str, url: &str, body: &[u8]) -> Result<Reply, Error> {
+        self.check_cancelled()?;
+        if self
+            .offline_cache
+            .as_ref()
+            .map_or(false, OfflineCache::is_offline)
+        {
+            return Err(Error::Offline);
+        }
+        if let Some(dry_run) = &self.dry_run {
+            dry_run.record(method, url, body);
+            let (status, body) = canned_response(method, url);
+            return Ok(Reply {
+                status_code: status.into(),
+                headers: HashMap::new(),
+                body: body.into(),
+            });
+        }
+        let wait = self.throttle.reserve(self.sync_profile.request_spacing());
+        if !wait.is_zero() {
+            self.sleep(wait)?;
+        }
+        let audit = self
+            .audit_sink
+            .as_ref()
+            .map(|sink| (sink, unix_now(), Instant::now()));
+        if let Some(stats) = &self.connection_stats {
+            // minreq doesn't keep connections open between requests
+            stats.record_request();
+            stats.record_connection();
+        }
+        let sent_at = self.clock_offset.as_ref().map(|_| unix_now());
+        let started = Instant::now();
+        let resp = request.send();
+        if let Some((sink, timestamp, started)) = audit {
+            let (bytes_down, status) = match &resp {
+                Ok(resp) => (resp.as_bytes().len(), u16::try_from(resp.status_code).ok()),
+                Err(_) => (0, None),
+            };
+            let duration = started.elapsed();
+            sink.record(AuditRecord::new(
+                method,
+                url,
+                timestamp,
+                duration,
+                body.len(),
+                bytes_down,
+                status,
+            ));
+        }
+        let resp = resp.map_err(|e| match (&self.proxy, e) {
+            (_, minreq::Error::IoError(e)) if is_timeout(&e) => {
+                let endpoint = endpoint(url);
+                // minreq reports its own timeout alike while connecting and reading, only a
+                // connection timed out by the OS is told apart
+                match e.raw_os_error() {
+                    Some(_) => Error::ConnectTimeout {
+                        endpoint,
+                        attempt: 1,
+                    },
+                    None => Error::ReadTimeout {
+                        endpoint,
+                        attempt: 1,
+                    },
+                }
+            }
+            (None, e) => Error::Minreq(e),
+            (Some(_), e @ (minreq::Error::InvalidProxyCreds | minreq::Error::BadProxyCreds)) => {
+                Error::ProxyAuthFailed(e.to_string())
+            }
+            (Some(_), e @ (minreq::Error::ProxyConnect | minreq::Error::AddressNotFound)) => {
+                Error::ProxyUnreachable(e.to_string())
+            }
+            (Some(_), minreq::Error::IoError(e)) if is_connect_failure(&e) => {
+                Error::ProxyUnreachable(e.to_string())
+            }
+            // The proxy answered the tunnel request with an error
+            (Some(_), e @ minreq::Error::BadProxy) => Error::ProxyHostUnreachable(e.to_string()),
+            (Some(_), e) => Error::Minreq(e),
+        })?;
+        let resp = Reply::from(resp);
+        if let Some(log) = &self.meta_log {
+            // minreq receives the whole response at once
+            let decoded_bytes = resp.as_bytes().len() as u64;
+            let content_length = resp.headers.get("content-length");
+            log.record(ResponseMeta {
+                endpoint: EndpointClass::classify(method, url),
+                status: u16::try_from(resp.status_code).unwrap_or_default(),
+                wire_bytes: content_length
+                    .and_then(|length| length.trim().parse().ok())
+                    .unwrap_or(decoded_bytes),
+                decoded_bytes,
+                time_to_first_byte: None,
+                duration: started.elapsed(),
+            });
+        }
+        if let Some(sink) = &self.progress_sink {
+            // minreq sends and receives the bodies at once
+            let endpoint = EndpointClass::classify(method, url);
+            let report = |transfer, bytes: usize| {
+                sink.progress(Progress {
+                    transfer,
+                    endpoint,
+                    bytes: bytes as u64,
+                    total: Some(bytes as u64),
+                })
+            };
+            if !body.is_empty() {
+                report(Transfer::Upload, body.len());
+            }
+            report(Transfer::Download, resp.as_bytes().len());
+        }
+        if let (Some(offset), Some(sent_at)) = (&self.clock_offset, sent_at) {
+            if let Some(date) = resp.headers.get("date") {
+                offset.observe_date(date, sent_at, unix_now());
+            }
+        }
+        if self.sync_profile.strict_rate_limits() && is_status_retryable(resp.status_code) {
+            let retry_after = resp.headers.get("retry-after").and_then(|value| {
+                let date = resp.headers.get("date").map(String::as_str);
+                retry_after_delay(value, date, self.clock_offset.as_ref())
+            });
+            if let Some(retry_after) = retry_after {
+                self.throttle.defer(retry_after);
+            }
+        }
+        Ok(resp)
+    }
+
+    /// Check that the Waterfalls server can be reached through the configured proxy.
+    ///
+    /// A single request is made to the server, any HTTP response is considered a success.
+    /// Proxy failures are reported as [`Error::ProxyUnreachable`], [`Error::ProxyAuthFailed`]
+    /// or [`Error::ProxyHostUnreachable`].
+    pub fn check_proxy(&self) -> Result<(), Error> {
+        let (request, url) = self.get_request_to("/blocks/tip/hash")?;
+        let resp = self.send(request, "GET", &url, &[])?;
+        if resp.status_code == 407 {
+            return Err(Error::ProxyAuthFailed(
+                resp.as_str().unwrap_or_default().to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run `call` with a clone of this client and return its result with the metadata of the
+    /// responses it received, see [`crate::meta`]
+    pub fn measured<T>(
+        &self,
+        call: impl FnOnce(&BlockingClient) -> Result<T, Error>,
+    ) -> Measured<Result<T, Error>> {
+        let log = MetaLog::default();
+        let client = BlockingClient {
+            meta_log: Some(log.clone()),
+            ..self.clone()
+        };
+        let started = Instant::now();
+        let value = call(&client);
+        Measured {
+            value,
+            responses: log.take(),
+            duration: started.elapsed(),
+        }
+    }
+
+    /// Return a client for the server at `path` on the same host, e.g. `/liquid/api` for the
+    /// Liquid instance behind the domain of a Bitcoin one.
+    ///
+    /// The path replaces the one of the base URL. The transport settings are kept, while the
+    /// state tied to the server chain is reset: the header and transaction caches, the
+    /// checkpoints, the scripts tokens, the Esplora fallback, the decoy pool and the identified
+    /// network.
+    pub fn with_base_path(&self, path: &str) -> Self {
+        BlockingClient {
+            url: crate::with_base_path(&self.url, path),
+            header_cache: None,
+            tx_cache: None,
+            block_hash_cache: None,
+            checkpoints: None,
+            scripts_tokens: None,
+            esplora_fallback: None,
+            offline_cache: None,
+            decoy_pool: None,
+            network_info: Arc::new(Mutex::new(None)),
+            ..self.clone()
         }
     }
 
@@ -60,11 +433,35 @@ impl BlockingClient {
     }
 
     /// Perform a raw HTTP GET request with the given URI `path`.
+    ///
+    /// Endpoints the Waterfalls server lacks are requested from the Esplora fallback if set.
     pub fn get_request(&self, path: &str) -> Result<Request, Error> {
-        let mut request = minreq::get(format!("{}{}", self.url, path));
+        self.get_request_to(path).map(|(request, _)| request)
+    }
+
+    /// Like [`Self::get_request`], returning also the URL of the request
+    fn get_request_to(&self, path: &str) -> Result<(Request, String), Error> {
+        let fallback = self
+            .esplora_fallback
+            .as_ref()
+            .filter(|_| crate::is_esplora_only(path));
+        let (mut request, url) = match fallback {
+            Some(fallback) => {
+                debug!("routing {path} to the esplora fallback {fallback}");
+                let url = format!("{fallback}{path}");
+                (minreq::get(&url), url)
+            }
+            None => {
+                let url = format!("{}{}", self.url, path);
+                let mut request = self.sign(minreq::get(&url), "GET", &url, &[]);
+                for (key, value) in &self.headers {
+                    request = request.with_header(key, value);
+                }
+                (request, url)
+            }
+        };
 
-        if let Some(proxy) = &self.proxy {
-            let proxy = Proxy::new(proxy.as_str())?;
+        if let Some(proxy) = self.request_proxy()? {
             request = request.with_proxy(proxy);
         }
 
@@ -72,13 +469,7 @@ impl BlockingClient {
             request = request.with_timeout(*timeout);
         }
 
-        if !self.headers.is_empty() {
-            for (key, value) in &self.headers {
-                request = request.with_header(key, value);
-            }
-        }
-
-        Ok(request)
+        Ok((request, url))
     }
 
     fn get_opt_response<T: Decodable>(&self, path: &str) -> Result<Option<T>, Error> {
@@ -90,6 +481,7 @@ impl BlockingClient {
                 Err(Error::HttpResponse { status, message })
             }
             Ok(resp) => Ok(Some(deserialize::<T>(resp.as_bytes())?)),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
             Err(e) => Err(e),
         }
     }
@@ -102,8 +494,8 @@ impl BlockingClient {
                 Err(Error::HttpResponse { status, message })
             }
             Ok(resp) => {
-                let hex_str = resp.as_str().map_err(Error::Minreq)?;
-                let hex_vec = Vec::from_hex(hex_str).unwrap();
+                let hex_str = self.read_text(&resp)?;
+                let hex_vec = Vec::from_hex(&hex_str).unwrap();
                 deserialize::<T>(&hex_vec).map_err(Error::BitcoinEncoding)
             }
             Err(e) => Err(e),
@@ -115,45 +507,79 @@ impl BlockingClient {
         path: &str,
         query_params: &[(&str, &str)],
     ) -> Result<T, Error> {
-        let mut url = format!("{}{}", self.url, path);
-        if !query_params.is_empty() {
-            url.push('?');
-            for (i, (key, value)) in query_params.iter().enumerate() {
-                if i > 0 {
-                    url.push('&');
+        let descriptor = query_params
+            .iter()
+            .find(|(key, _)| *key == "descriptor")
+            .map(|(_, value)| self.blinding_key_policy.apply(value));
+        let query_params: Vec<(&str, &str)> = query_params
+            .iter()
+            .map(|(key, value)| match (*key, &descriptor) {
+                ("descriptor", Some(descriptor)) => (*key, descriptor.as_ref()),
+                _ => (*key, *value),
+            })
+            .collect();
+        let descriptor = descriptor.as_deref();
+        let tokens = self.scripts_tokens.as_ref().zip(descriptor);
+        let mut token = tokens.and_then(|(tokens, descriptor)| tokens.get(descriptor));
+
+        loop {
+            let mut query_params = query_params.to_vec();
+            if let Some(token) = &token {
+                query_params.push(("scripts", token));
+            }
+            let mut url = format!("{}{}", self.url, path);
+            if !query_params.is_empty() {
+                url.push('?');
+                for (i, (key, value)) in query_params.iter().enumerate() {
+                    if i > 0 {
+                        url.push('&');
+                    }
+                    // URL encode the key and value to handle special characters
+                    let encoded_key = urlencoding::encode(key);
+                    let encoded_value = urlencoding::encode(value);
+                    url.push_str(&format!("{encoded_key}={encoded_value}"));
                 }
-                // URL encode the key and value to handle special characters
-                let encoded_key = urlencoding::encode(key);
-                let encoded_value = urlencoding::encode(value);
-                url.push_str(&format!("{encoded_key}={encoded_value}"));
             }
-        }
 
-        let mut request = minreq::get(&url);
+            let mut request = self.sign(minreq::get(&url), "GET", &url, &[]);
 
-        if let Some(proxy) = &self.proxy {
-            let proxy = Proxy::new(proxy.as_str())?;
-            request = request.with_proxy(proxy);
-        }
+            if let Some(proxy) = self.request_proxy()? {
+                request = request.with_proxy(proxy);
+            }
 
-        if let Some(timeout) = &self.timeout {
-            request = request.with_timeout(*timeout);
-        }
+            if let Some(timeout) = &self.timeout {
+                request = request.with_timeout(*timeout);
+            }
 
-        if !self.headers.is_empty() {
-            for (key, value) in &self.headers {
-                request = request.with_header(key, value);
+            if !self.headers.is_empty() {
+                for (key, value) in &self.headers {
+                    request = request.with_header(key, value);
+                }
             }
-        }
 
-        match request.send() {
-            Ok(resp) if !is_status_ok(resp.status_code) => {
+            let resp = self.send(request, "GET", &url, &[])?;
+            if !is_status_ok(resp.status_code) {
+                if let (Some((tokens, descriptor)), Some(_)) = (tokens, &token) {
+                    if (400..500).contains(&resp.status_code) {
+                        // The server may have forgotten the token, derive the scripts again
+                        tokens.remove(descriptor);
+                        token = None;
+                        continue;
+                    }
+                }
                 let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
                 let message = resp.as_str().unwrap_or_default().to_string();
-                Err(Error::HttpResponse { status, message })
+                return Err(Error::HttpResponse { status, message });
             }
-            Ok(resp) => Ok(resp.json::<T>()?),
-            Err(e) => Err(Error::Minreq(e)),
+
+            if let Some((tokens, descriptor)) = tokens {
+                let header = resp.headers.get(&SCRIPTS_TOKEN_HEADER.to_lowercase());
+                if let Some(new_token) = header {
+                    tokens.insert(descriptor, new_token);
+                }
+            }
+
+            return resp.json();
         }
     }
 
@@ -164,14 +590,51 @@ impl BlockingClient {
                 let message = resp.as_str().unwrap_or_default().to_string();
                 Err(Error::HttpResponse { status, message })
             }
-            Ok(resp) => Ok(resp.as_str()?.to_string()),
+            Ok(resp) => self.read_text(&resp),
             Err(e) => Err(e),
         }
     }
 
+    /// Read the body of a text endpoint according to the [`TextDecoding`] of the client
+    fn read_text(&self, resp: &Reply) -> Result<String, Error> {
+        match self.text_decoding {
+            TextDecoding::Strict => Ok(resp.as_str()?.to_string()),
+            TextDecoding::Tolerant => Ok(decode_tolerant(resp.as_bytes())),
+        }
+    }
+
     /// Get a [`Transaction`] option given its [`Txid`]
     pub fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
-        self.get_opt_response(&format!("/tx/{txid}/raw"))
+        if let Some(tx) = self.tx_cache.as_ref().and_then(|cache| cache.get(txid)) {
+            return Ok(Some(tx));
+        }
+        let tx: Option<Transaction> = self.get_opt_response(&format!("/tx/{txid}/raw"))?;
+        if let (Some(cache), Some(tx)) = (&self.tx_cache, &tx) {
+            cache.insert(tx.clone());
+        }
+        Ok(tx)
+    }
+
+    /// Get the raw bytes of a transaction given its [`Txid`], e.g. an Elements transaction
+    /// which can't be decoded as a [`Transaction`]
+    pub fn get_tx_raw(&self, txid: &Txid) -> Result<Option<Vec<u8>>, Error> {
+        match self.get_with_retry(&format!("/tx/{txid}/raw")) {
+            Ok(resp) if is_status_not_found(resp.status_code) => Ok(None),
+            Ok(resp) if !is_status_ok(resp.status_code) => {
+                let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
+                let message = resp.as_str().unwrap_or_default().to_string();
+                Err(Error::HttpResponse { status, message })
+            }
+            Ok(resp) => Ok(Some(resp.into_bytes())),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the transaction `txid` decoded with [`crate::decode_lenient`], for transactions
+    /// [`Self::get_tx`] fails to decode
+    pub fn get_tx_lenient(&self, txid: &Txid) -> Result<Option<PartialTx>, Error> {
+        Ok(self.get_tx_raw(txid)?.as_deref().map(decode_lenient))
     }
 
     /// Get a [`Transaction`] given its [`Txid`].
@@ -187,10 +650,293 @@ impl BlockingClient {
     pub fn waterfalls(&self, descriptor: &str) -> Result<WaterfallResponse, Error> {
         let path = "/v4/waterfalls";
         self.get_response_json_with_query(path, &[("descriptor", descriptor)])
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Descriptor, descriptor.len()))
+            .map(|response| self.check_reorg(response))
+    }
+
+    /// Query the waterfalls endpoint with an Elements descriptor, keeping only the history of
+    /// the transactions moving the asset with hex id `asset`.
+    ///
+    /// The filter is applied by the server: a server without asset filtering may ignore the
+    /// parameter and return the whole history. The client can't filter the history itself,
+    /// since the assets of Liquid outputs are blinded.
+    pub fn waterfalls_asset(
+        &self,
+        descriptor: &str,
+        asset: &str,
+    ) -> Result<WaterfallResponse, Error> {
+        let path = "/v4/waterfalls";
+        self.get_response_json_with_query(path, &[("descriptor", descriptor), ("asset", asset)])
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Descriptor, descriptor.len()))
+            .map(|response| self.check_reorg(response))
+    }
+
+    /// Like [`Self::waterfalls`], making sure the response was computed at the tip returned by
+    /// [`Self::get_tip_hash`].
+    ///
+    /// While the server processes a new block its endpoints may briefly disagree, so both are
+    /// queried again with backoff up to `max_retries` times before failing with
+    /// [`Error::TipInconsistent`]. A response without a tip is returned as is.
+    pub fn waterfalls_consistent(&self, descriptor: &str) -> Result<WaterfallResponse, Error> {
+        let mut delay = self.base_backoff;
+        let mut attempts = 0;
+        loop {
+            let response = self.waterfalls(descriptor)?;
+            let waterfalls = match response.tip_hash() {
+                Some(waterfalls) => waterfalls,
+                None => return Ok(response),
+            };
+            let tip = self.get_tip_hash()?;
+            if tip == waterfalls {
+                return Ok(response);
+            }
+            if attempts >= self.max_retries {
+                return Err(Error::TipInconsistent { waterfalls, tip });
+            }
+            self.sleep(delay)?;
+            attempts += 1;
+            delay = next_backoff(delay, self.max_backoff);
+        }
+    }
+
+    /// Scan `descriptor` and return the response sanitized as a test fixture, see
+    /// [`CapturedScan`]
+    #[cfg(feature = "test-utils")]
+    pub fn capture_scan(&self, descriptor: &str) -> Result<CapturedScan, Error> {
+        Ok(CapturedScan::from_descriptor(self.waterfalls(descriptor)?))
+    }
+
+    /// Scan the external and internal addresses of `xpub` using the standard descriptor of
+    /// `script_kind`, see [`ScriptKind::descriptor`].
+    ///
+    /// If the server stopped before `gap_limit` unused scripts after the last used one of a
+    /// branch, the scan is repeated up to the index required by the gap limit, see
+    /// [`Self::waterfalls_to_gap_limit`].
+    /// [`WaterfallResponse::keychains`] splits the result into receive and change branches.
+    pub fn scan_xpub(
+        &self,
+        xpub: &Xpub,
+        script_kind: ScriptKind,
+        gap_limit: u32,
+    ) -> Result<WaterfallResponse, Error> {
+        self.waterfalls_to_gap_limit(&script_kind.descriptor(xpub), gap_limit)
+    }
+
+    /// Scan `descriptor` with the gap limit registered in the [`DescriptorRegistry`] of the
+    /// client if any, see [`Self::waterfalls_to_gap_limit`], or the one of the server
+    pub fn waterfalls_registered(&self, descriptor: &str) -> Result<WaterfallResponse, Error> {
+        let gap_limit = self
+            .descriptor_registry
+            .as_ref()
+            .and_then(|registry| registry.get(descriptor)?.gap_limit);
+        match gap_limit {
+            Some(gap_limit) => self.waterfalls_to_gap_limit(descriptor, gap_limit),
+            None => self.waterfalls(descriptor),
+        }
+    }
+
+    /// Scan `descriptor` with every branch covering `gap_limit` unused scripts after its last
+    /// used one.
+    ///
+    /// The server scans up to its own gap limit by default, so while the response is short of
+    /// `gap_limit` the scan is repeated with a higher `to_index`, see
+    /// [`WaterfallResponse::required_to_index`]. Fails with [`Error::ScanTruncated`] if the
+    /// server stops short of the requested index, e.g. at its page size, instead of returning
+    /// a truncated history.
+    pub fn waterfalls_to_gap_limit(
+        &self,
+        descriptor: &str,
+        gap_limit: u32,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut response = self.waterfalls(descriptor)?;
+        let mut requested = None;
+        while let Some(required) = response.required_to_index(gap_limit) {
+            if requested.map_or(false, |requested| required <= requested) {
+                return Err(Error::ScanTruncated {
+                    required,
+                    scanned: response.scanned_to_index().unwrap_or(0),
+                });
+            }
+            requested = Some(required);
+            response = self.waterfalls_version(descriptor, 4, None, Some(required), false)?;
+        }
+        Ok(response)
+    }
+
+    /// Like [`Self::waterfalls_to_gap_limit`] with a `gap_limit`, or [`Self::waterfalls`]
+    /// without, reporting the anomalies as [`Warning`]s instead of failing, see
+    /// [`crate::warning`].
+    ///
+    /// The tip of the response is compared once with [`Self::get_tip_hash`].
+    pub fn waterfalls_soft(
+        &self,
+        descriptor: &str,
+        gap_limit: Option<u32>,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        let mut warned = self.waterfalls_lenient(descriptor, None, None)?;
+        if let Some(gap_limit) = gap_limit {
+            let mut requested = None;
+            while let Some(required) = warned.value.required_to_index(gap_limit) {
+                if requested.map_or(false, |requested| required <= requested) {
+                    let scanned = warned.value.scanned_to_index().unwrap_or(0);
+                    warned
+                        .warnings
+                        .push(Warning::ScanTruncated { required, scanned });
+                    break;
+                }
+                requested = Some(required);
+                warned = self.waterfalls_lenient(descriptor, None, Some(required))?;
+            }
+        }
+        self.check_tip_soft(warned)
+    }
+
+    /// Like [`Self::waterfalls_all_pages`], reporting the anomalies as [`Warning`]s instead of
+    /// failing, see [`crate::warning`]
+    pub fn waterfalls_all_pages_soft(
+        &self,
+        descriptor: &str,
+        page_size: usize,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        let mut cursor = ScanCursor::new(descriptor, page_size);
+        let (mut warnings, mut first) = (vec![], None);
+        while !cursor.complete {
+            let requested = cursor.next_page;
+            let page = self.waterfalls_lenient(descriptor, Some(requested), None)?;
+            first = first.or(page.value.tip_hash());
+            warnings.extend(page.warnings);
+            warnings.extend(page_warnings(requested, first, &page.value));
+            cursor.advance(page.value);
+        }
+        let value = cursor.response;
+        self.check_tip_soft(Warned { value, warnings })
+    }
+
+    /// Query a page of the waterfalls endpoint, skipping the malformed history entries
+    fn waterfalls_lenient(
+        &self,
+        descriptor: &str,
+        page: Option<u32>,
+        to_index: Option<u32>,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        let mut query_params = vec![("descriptor", descriptor.to_string())];
+        if let Some(page) = page {
+            query_params.push(("page", page.to_string()));
+        }
+        if let Some(to_index) = to_index {
+            query_params.push(("to_index", to_index.to_string()));
+        }
+        let query_refs: Vec<(&str, &str)> =
+            query_params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let response: LenientResponse = self
+            .get_response_json_with_query("/v4/waterfalls", &query_refs)
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Descriptor, descriptor.len()))?;
+        let mut warned = response.into_warned();
+        warned.value = self.check_reorg(warned.value);
+        Ok(warned)
+    }
+
+    /// Add the tip warnings of the response of `warned`
+    fn check_tip_soft(
+        &self,
+        mut warned: Warned<WaterfallResponse>,
+    ) -> Result<Warned<WaterfallResponse>, Error> {
+        if let Some(waterfalls) = warned.value.tip_hash() {
+            let tip = self.get_tip_hash()?;
+            if tip != waterfalls {
+                warned
+                    .warnings
+                    .push(Warning::TipInconsistent { waterfalls, tip });
+            }
+        }
+        let offset = self.clock_offset.clone().unwrap_or_default();
+        let stale = stale_tip(&warned.value, self.clock_skew_tolerance, |timestamp| {
+            offset.elapsed_since(timestamp)
+        });
+        warned.warnings.extend(stale);
+        Ok(warned)
+    }
+
+    /// Return the last response of `descriptor` cached in `poller` and refresh it in a
+    /// background thread, see [`StaleWhileRevalidate`].
+    ///
+    /// No new refresh is started while one is in progress.
+    pub fn poll_waterfalls(
+        &self,
+        descriptor: &str,
+        poller: &StaleWhileRevalidate,
+    ) -> Option<WaterfallResponse> {
+        if poller.start_refresh() {
+            let (client, descriptor, poller) =
+                (self.clone(), descriptor.to_string(), poller.clone());
+            thread::spawn(move || poller.finish_refresh(client.waterfalls(&descriptor)));
+        }
+        poller.cached()
+    }
+
+    /// Poll the wallets of `session` due for a poll, returning how many were polled.
+    ///
+    /// The transactions of a wallet missing from the [`crate::TxCache`] of the session are
+    /// fetched before its [`crate::WalletEvent::Updated`] is sent. The wallets are scanned with
+    /// [`Self::waterfalls_registered`]. Call it again at [`Session::next_poll`].
+    pub fn poll_session(&self, session: &Session) -> usize {
+        let due = session.due(Instant::now());
+        for (id, descriptor) in &due {
+            let result = self.waterfalls_registered(descriptor).and_then(|response| {
+                for txid in session.missing_txids(&response) {
+                    session.tx_cache().insert(self.get_tx_no_opt(&txid)?);
+                }
+                Ok(response)
+            });
+            session.record(*id, result, Instant::now());
+        }
+        due.len()
     }
 
     /// Query the waterfalls endpoint with addresses
+    ///
+    /// With a [`DecoyPool`] the query includes decoys, removed from the response, see
+    /// [`crate::decoy`].
     pub fn waterfalls_addresses(&self, addresses: &[Address]) -> Result<WaterfallResponse, Error> {
+        match &self.decoy_pool {
+            Some(pool) => {
+                let (mixed, positions) = pool.mix(addresses);
+                self.query_addresses(&mixed)
+                    .map(|response| remove_decoys(response, &positions))
+                    .map_err(|e| without_decoys(e, pool.ratio(), addresses.len()))
+            }
+            None => self.query_addresses(addresses),
+        }
+    }
+
+    /// Fill the [`DecoyPool`] of the client with the output addresses of up to
+    /// [`DECOY_TXS_PER_BLOCK`] transactions of each of the last `blocks` blocks.
+    ///
+    /// Requires the Esplora block transactions endpoint and a Bitcoin network.
+    pub fn refresh_decoys(&self, blocks: u32) -> Result<(), Error> {
+        let pool = self.decoy_pool.as_ref().ok_or_else(|| {
+            Error::InvalidConfiguration("the client has no decoy pool".to_string())
+        })?;
+        let network = self.network_info()?.network.ok_or_else(|| {
+            Error::InvalidConfiguration("decoys require a Bitcoin network".to_string())
+        })?;
+        let mut hash = self.get_tip_hash()?;
+        for _ in 0..blocks {
+            // The coinbase is skipped
+            let txids = self.get_block_txids(&hash)?;
+            let txids: Vec<_> = txids
+                .into_iter()
+                .skip(1)
+                .take(DECOY_TXS_PER_BLOCK)
+                .collect();
+            let txs = self.get_txs(&txids).into_result()?;
+            pool.insert_from_txs(txs.iter().map(|(_, tx)| tx), network);
+            hash = self.get_header_by_hash(&hash)?.prev_blockhash;
+        }
+        Ok(())
+    }
+
+    fn query_addresses(&self, addresses: &[Address]) -> Result<WaterfallResponse, Error> {
         let addresses_str = addresses
             .iter()
             .map(|a| a.to_string())
@@ -198,6 +944,347 @@ impl BlockingClient {
             .join(",");
         let path = "/v4/waterfalls";
         self.get_response_json_with_query(path, &[("addresses", &addresses_str)])
+            .map_err(|e| e.into_limit_exceeded(LimitKind::Addresses, addresses.len()))
+            .map(|response| self.check_reorg(response))
+    }
+
+    /// Query the waterfalls endpoint with addresses, splitting them in requests of at most
+    /// `chunk_size` addresses and merging the results.
+    ///
+    /// If the server answers with [`Error::LimitExceeded`] the chunk size is lowered to the
+    /// server limit and the request is retried. Fails if any chunk fails, see
+    /// [`Self::waterfalls_addresses_batch`] to keep the chunks fetched successfully.
+    pub fn waterfalls_addresses_chunked(
+        &self,
+        addresses: &[Address],
+        chunk_size: usize,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut response = WaterfallResponse::default();
+        let chunks = self
+            .waterfalls_addresses_batch(addresses, chunk_size)
+            .into_result()?;
+        for (_, chunk_response) in chunks {
+            response.merge(chunk_response);
+        }
+        Ok(response)
+    }
+
+    /// Like [`Self::waterfalls_addresses_chunked`], but a failed chunk doesn't stop the
+    /// following ones and the response of every chunk is returned with its addresses.
+    pub fn waterfalls_addresses_batch(
+        &self,
+        addresses: &[Address],
+        chunk_size: usize,
+    ) -> BatchResult<Vec<Address>, WaterfallResponse> {
+        let mut chunk_size = chunk_size.max(1);
+        let mut result = BatchResult::default();
+        let mut remaining = addresses;
+        while !remaining.is_empty() {
+            let chunk = &remaining[..chunk_size.min(remaining.len())];
+            match self.waterfalls_addresses(chunk) {
+                Err(Error::LimitExceeded {
+                    kind: LimitKind::Addresses,
+                    limit,
+                    ..
+                }) if limit > 0 && limit < chunk.len() => {
+                    chunk_size = limit;
+                    continue;
+                }
+                Ok(chunk_response) => result.items.push((chunk.to_vec(), chunk_response)),
+                Err(e) => result.errors.push((chunk.to_vec(), e)),
+            }
+            remaining = &remaining[chunk.len()..];
+        }
+        result
+    }
+
+    /// Like [`Self::waterfalls_addresses_batch`], fetching up to `threads` chunks concurrently
+    pub fn waterfalls_addresses_parallel(
+        &self,
+        addresses: &[Address],
+        chunk_size: usize,
+        threads: usize,
+    ) -> BatchResult<Vec<Address>, WaterfallResponse> {
+        let threads = self.concurrency(threads);
+        let chunks: Vec<&[Address]> = addresses.chunks(chunk_size.max(1)).collect();
+        let mut result = BatchResult::default();
+        for batch in parallel_map(&chunks, threads, |chunk| {
+            self.waterfalls_addresses_batch(chunk, chunk_size)
+        }) {
+            result.items.extend(batch.items);
+            result.errors.extend(batch.errors);
+        }
+        result
+    }
+
+    /// Fetch the transactions with the given `txids`, without stopping at the first failure
+    pub fn get_txs(&self, txids: &[Txid]) -> BatchResult<Txid, Transaction> {
+        let mut result = BatchResult::default();
+        for txid in txids {
+            match self.get_tx_no_opt(txid) {
+                Ok(tx) => result.items.push((*txid, tx)),
+                Err(e) => result.errors.push((*txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Like [`Self::get_txs`], fetching up to `threads` transactions concurrently
+    pub fn get_txs_parallel(
+        &self,
+        txids: &[Txid],
+        threads: usize,
+    ) -> BatchResult<Txid, Transaction> {
+        let threads = self.concurrency(threads);
+        let mut result = BatchResult::default();
+        for (txid, tx) in txids.iter().zip(parallel_map(txids, threads, |txid| {
+            self.get_tx_no_opt(txid)
+        })) {
+            match tx {
+                Ok(tx) => result.items.push((*txid, tx)),
+                Err(e) => result.errors.push((*txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Fetch the Liquid transactions seen in `response` and find their peg-ins and peg-outs,
+    /// see [`crate::pegs`]. Transactions without pegs aren't in the items.
+    pub fn find_pegs(&self, response: &WaterfallResponse) -> BatchResult<Txid, Vec<Peg>> {
+        self.parse_raw_txs(response, crate::pegs::find_pegs)
+    }
+
+    /// Fetch the Liquid transactions seen in `response` and find their asset issuances and
+    /// reissuances, see [`crate::issuance`]. Transactions without issuances aren't in the items.
+    pub fn find_issuances(&self, response: &WaterfallResponse) -> BatchResult<Txid, Vec<Issuance>> {
+        self.parse_raw_txs(response, crate::issuance::find_issuances)
+    }
+
+    /// Fetch the raw transactions seen in `response` and `parse` them, skipping the ones
+    /// without items
+    fn parse_raw_txs<T>(
+        &self,
+        response: &WaterfallResponse,
+        parse: fn(&[u8]) -> Result<Vec<T>, Error>,
+    ) -> BatchResult<Txid, Vec<T>> {
+        let mut result = BatchResult::default();
+        for txid in response.txids() {
+            let items = self.get_tx_raw(&txid).and_then(|raw| match raw {
+                Some(raw) => parse(&raw),
+                None => Err(Error::TransactionNotFound(txid)),
+            });
+            match items {
+                Ok(items) if items.is_empty() => {}
+                Ok(items) => result.items.push((txid, items)),
+                Err(e) => result.errors.push((txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Fetch every transaction seen in `response`, see [`Self::get_txs`]
+    pub fn hydrate(&self, response: &WaterfallResponse) -> BatchResult<Txid, Transaction> {
+        self.get_txs(&response.txids())
+    }
+
+    /// Scan `descriptor`, fetch its transactions and compute the [`WalletSummary`], see
+    /// [`WaterfallResponse::summary`]
+    pub fn wallet_summary(&self, descriptor: &str) -> Result<WalletSummary, Error> {
+        let response = self.waterfalls(descriptor)?;
+        let txs = self.hydrate(&response).into_result()?;
+        Ok(response.summary(&txs.into_iter().collect()))
+    }
+
+    /// Scan the descriptor of `watcher` with [`Self::waterfalls_registered`], fetch the
+    /// transactions it lacks and return the events of the poll, see [`crate::watcher`]
+    #[cfg(feature = "watcher")]
+    pub fn poll_watcher(&self, watcher: &mut Watcher) -> Result<Vec<WatchEvent>, Error> {
+        let response = self.waterfalls_registered(watcher.descriptor())?;
+        let txs = self
+            .get_txs(&watcher.missing_txids(&response))
+            .into_result()?;
+        watcher.insert_txs(txs.into_iter().map(|(_, tx)| tx));
+        Ok(watcher.observe(&response))
+    }
+
+    /// Scan `descriptor`, recording the response in the [`OfflineCache`] of the client if
+    /// any. In offline mode the last recorded scan is returned instead, marked stale, see
+    /// [`crate::offline`].
+    pub fn waterfalls_cached(&self, descriptor: &str) -> Result<Cached<WaterfallResponse>, Error> {
+        match &self.offline_cache {
+            Some(cache) if cache.is_offline() => cache.scan(descriptor).ok_or(Error::Offline),
+            cache => {
+                let response = self.waterfalls(descriptor)?;
+                if let Some(cache) = cache {
+                    cache.insert_scan(descriptor, &response);
+                }
+                Ok(Cached::fresh(response))
+            }
+        }
+    }
+
+    /// Like [`Self::wallet_summary`], recording the scan and its transactions in the
+    /// [`OfflineCache`] of the client if any. In offline mode the summary is computed from the
+    /// cache instead, marked stale, see [`crate::offline`].
+    pub fn wallet_summary_cached(&self, descriptor: &str) -> Result<Cached<WalletSummary>, Error> {
+        match &self.offline_cache {
+            Some(cache) if cache.is_offline() => cache.summary(descriptor).ok_or(Error::Offline),
+            cache => {
+                let response = self.waterfalls(descriptor)?;
+                let txs = self.hydrate(&response).into_result()?;
+                let txs: BTreeMap<_, _> = txs.into_iter().collect();
+                if let Some(cache) = cache {
+                    cache.insert_scan(descriptor, &response);
+                    cache.insert_txs(txs.values().cloned());
+                }
+                Ok(Cached::fresh(response.summary(&txs)))
+            }
+        }
+    }
+
+    /// Scan `descriptor`, fetch its transactions and collect the [`CosignerData`] another
+    /// signer of the wallet needs to build a PSBT, see [`crate::cosigner`]
+    pub fn cosigner_data(&self, descriptor: &str) -> Result<CosignerData, Error> {
+        let response = self.waterfalls(descriptor)?;
+        let txs = self.hydrate(&response).into_result()?;
+        let txs = txs.into_iter().collect();
+        Ok(CosignerData::new(&response.summary(&txs).utxos, &txs))
+    }
+
+    /// Scan `descriptor`, fetch its transactions and collect the [`SyncPayload`] an air-gapped
+    /// signer of the wallet needs, see [`crate::payload`]
+    pub fn sync_payload(&self, descriptor: &str) -> Result<SyncPayload, Error> {
+        let response = self.waterfalls(descriptor)?;
+        let txs = self.hydrate(&response).into_result()?;
+        let txs = txs.into_iter().collect();
+        Ok(SyncPayload::new(&response.summary(&txs).utxos, &txs))
+    }
+
+    /// Get the verbose [`Tx`] of `txid`, including the outputs spent by its inputs, or `None`
+    /// if the server doesn't have it
+    pub fn get_tx_info(&self, txid: &Txid) -> Result<Option<Tx>, Error> {
+        match self.get_response_json_with_query(&format!("/tx/{txid}"), &[]) {
+            Ok(tx) => Ok(Some(tx)),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the [`MempoolEntry`] of the unconfirmed transaction `txid`, or `None` if it isn't in
+    /// the mempool or the server doesn't expose mempool entries
+    ///
+    /// `/mempool/tx/{txid}` isn't an Esplora or Waterfalls endpoint, the server must expose it as
+    /// an extension. Against a Bitcoin Core node use `CoreRpcClient::get_mempool_entry` instead.
+    #[cfg(feature = "server-extensions")]
+    pub fn get_mempool_entry(&self, txid: &Txid) -> Result<Option<MempoolEntry>, Error> {
+        match self.get_response_json_with_query(&format!("/mempool/tx/{txid}"), &[]) {
+            Ok(entry) => Ok(Some(entry)),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the [`RelayPolicy`] of the node behind the server, with every limit unknown if the
+    /// server doesn't expose it
+    ///
+    /// `/v1/relay_policy` isn't an Esplora or Waterfalls endpoint, the server must expose it as an
+    /// extension. Against a Bitcoin Core node use `CoreRpcClient::relay_policy` instead.
+    #[cfg(feature = "server-extensions")]
+    pub fn relay_policy(&self) -> Result<RelayPolicy, Error> {
+        match self.get_response_json_with_query("/v1/relay_policy", &[]) {
+            Ok(policy) => Ok(policy),
+            Err(Error::HttpResponse { status: 404, .. }) => Ok(RelayPolicy::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check that `transaction` paying `fee` would be relayed under the [`RelayPolicy`] of
+    /// the server before broadcasting it, the Bitcoin Core defaults without the
+    /// `server-extensions` feature
+    pub fn check_broadcast(&self, transaction: &Transaction, fee: Amount) -> Result<(), Error> {
+        #[cfg(feature = "server-extensions")]
+        let policy = self.relay_policy()?;
+        #[cfg(not(feature = "server-extensions"))]
+        let policy = RelayPolicy::default();
+        Ok(policy.check(transaction, fee)?)
+    }
+
+    /// Fetch every transaction seen in `response` with the outputs spent by its inputs, using
+    /// the verbose or the raw transactions according to `strategy`
+    pub fn hydrate_with_prevouts(
+        &self,
+        response: &WaterfallResponse,
+        strategy: HydrationStrategy,
+    ) -> BatchResult<Txid, HydratedTx> {
+        let mut result = BatchResult::default();
+        let mut raw_txs = HashMap::new();
+        let mut verbose = strategy != HydrationStrategy::RawFirst;
+        for txid in response.txids() {
+            let verbose_tx = match verbose {
+                true => self.get_tx_info(&txid),
+                false => Ok(None),
+            };
+            let hydrated = match verbose_tx {
+                Ok(Some(tx)) => Ok(HydratedTx::from(tx)),
+                Ok(None) => {
+                    // The server doesn't return verbose transactions
+                    verbose &= strategy != HydrationStrategy::Auto;
+                    self.hydrate_raw(&txid, &mut raw_txs)
+                }
+                Err(e) => Err(e),
+            };
+            match hydrated {
+                Ok(tx) => result.items.push((txid, tx)),
+                Err(e) => result.errors.push((txid, e)),
+            }
+        }
+        result
+    }
+
+    /// Fetch the raw transaction `txid` and the parents of its inputs, reusing those in
+    /// `raw_txs`
+    fn hydrate_raw(
+        &self,
+        txid: &Txid,
+        raw_txs: &mut HashMap<Txid, Transaction>,
+    ) -> Result<HydratedTx, Error> {
+        let tx = match raw_txs.get(txid) {
+            Some(tx) => tx.clone(),
+            None => self.get_tx_no_opt(txid)?,
+        };
+        raw_txs.insert(*txid, tx.clone());
+        let mut prevouts = Vec::with_capacity(tx.input.len());
+        for input in &tx.input {
+            let previous_output = input.previous_output;
+            if previous_output.is_null() {
+                prevouts.push(None);
+                continue;
+            }
+            if let Entry::Vacant(entry) = raw_txs.entry(previous_output.txid) {
+                entry.insert(self.get_tx_no_opt(&previous_output.txid)?);
+            }
+            let parent = &raw_txs[&previous_output.txid];
+            prevouts.push(parent.output.get(previous_output.vout as usize).cloned());
+        }
+        Ok(HydratedTx { tx, prevouts })
+    }
+
+    /// Query the waterfalls endpoint with a descriptor and/or addresses, merging the results
+    /// into a single [`WaterfallResponse`].
+    ///
+    /// One request is made for the descriptor and one for the addresses, unless the addresses
+    /// exceed the server limit and must be split, see [`Self::waterfalls_addresses_chunked`].
+    /// An empty query returns an empty response without contacting the server.
+    pub fn waterfalls_query(&self, query: &WaterfallsQuery) -> Result<WaterfallResponse, Error> {
+        let mut response = match &query.descriptor {
+            Some(descriptor) => self.waterfalls(descriptor)?,
+            None => WaterfallResponse::default(),
+        };
+        if !query.addresses.is_empty() {
+            let addresses = &query.addresses;
+            response.merge(self.waterfalls_addresses_chunked(addresses, addresses.len())?);
+        }
+        Ok(response)
     }
 
     /// Query waterfalls with version-specific parameters
@@ -227,9 +1314,175 @@ impl BlockingClient {
         self.get_response_json_with_query(&path, &query_refs)
     }
 
+    /// Query the waterfalls endpoint with a descriptor, following pages until the server has
+    /// no more, and merge them into a single [`WaterfallResponse`].
+    ///
+    /// `page_size` is the number of scripts per page returned by the server, see
+    /// [`WaterfallResponse::pagination`].
+    /// Use [`Self::waterfalls_resume`] with a [`ScanCursor`] to keep the pages received before a
+    /// failure.
+    pub fn waterfalls_all_pages(
+        &self,
+        descriptor: &str,
+        page_size: usize,
+    ) -> Result<WaterfallResponse, Error> {
+        let mut cursor = ScanCursor::new(descriptor, page_size);
+        self.waterfalls_resume(&mut cursor)?;
+        Ok(cursor.response)
+    }
+
+    /// Continue the scan of `cursor` from its next page until the last one.
+    ///
+    /// On failure the pages received so far are kept in `cursor`, so the scan can be resumed
+    /// by calling this again later.
+    pub fn waterfalls_resume(&self, cursor: &mut ScanCursor) -> Result<(), Error> {
+        while !cursor.complete {
+            let page = Some(cursor.next_page);
+            let page_response =
+                self.waterfalls_version(&cursor.descriptor, 4, page, None, false)?;
+            cursor.advance(page_response);
+        }
+        Ok(())
+    }
+
     /// Get a [`BlockHeader`] given a particular block hash.
+    ///
+    /// If the client has a [`HeaderCache`] it is looked up first and filled on miss.
     pub fn get_header_by_hash(&self, block_hash: &BlockHash) -> Result<BlockHeader, Error> {
-        self.get_response_hex(&format!("/block/{block_hash}/header"))
+        if let Some(header) = self
+            .header_cache
+            .as_ref()
+            .and_then(|cache| cache.header_by_hash(block_hash))
+        {
+            return Ok(header);
+        }
+        let header: BlockHeader = self.get_response_hex(&format!("/block/{block_hash}/header"))?;
+        if let Some(cache) = &self.header_cache {
+            cache.insert(None, header);
+        }
+        Ok(header)
+    }
+
+    /// Get the header of the block `block_hash` of an Elements chain such as Liquid, with its
+    /// dynafed fields, see [`ElementsHeader`].
+    ///
+    /// Fails with [`Error::InvalidResponse`] if the header doesn't hash to `block_hash`.
+    pub fn get_elements_header(&self, block_hash: &BlockHash) -> Result<ElementsHeader, Error> {
+        let hex = self.get_response_str(&format!("/block/{block_hash}/header"))?;
+        let header = parse_elements_header(&Vec::from_hex(hex.trim())?)?;
+        if header.block_hash() != *block_hash {
+            return Err(Error::InvalidResponse);
+        }
+        Ok(header)
+    }
+
+    /// Get the txids of the block `block_hash`, in block order.
+    ///
+    /// The txids are checked against the merkle root of the block header, fetched with
+    /// [`Self::get_header_by_hash`], see [`crate::verify_block_txids`].
+    pub fn get_block_txids(&self, block_hash: &BlockHash) -> Result<Vec<Txid>, Error> {
+        let path = format!("/block/{block_hash}/txids");
+        let txids: Vec<Txid> = self.get_response_json_with_query(&path, &[])?;
+        let header = self.get_header_by_hash(block_hash)?;
+        crate::verify_block_txids(&header, &txids).map_err(Error::HeaderValidation)?;
+        Ok(txids)
+    }
+
+    /// Drop the cached headers and unconfirm the cached scan entries above `fork_height`, after
+    /// the blocks above it have been reorged out.
+    ///
+    /// Called automatically when a scan shows a [`crate::Reorg`] of the [`HeaderCache`].
+    pub fn invalidate_above(&self, fork_height: u32) {
+        if let Some(cache) = &self.header_cache {
+            cache.remove_above(fork_height);
+        }
+        if let Some(cache) = &self.block_hash_cache {
+            cache.remove_above(fork_height);
+        }
+        if let Some(cache) = &self.offline_cache {
+            cache.rollback(fork_height);
+        }
+    }
+
+    /// The number of concurrent requests of a batched call asking for `requested`, one in
+    /// deterministic mode
+    fn concurrency(&self, requested: usize) -> usize {
+        if self.deterministic {
+            1
+        } else {
+            self.sync_profile.concurrency(requested)
+        }
+    }
+
+    /// Invalidate the caches if `response` shows a reorg, and record its tip in the block hash
+    /// cache
+    fn check_reorg(&self, response: WaterfallResponse) -> WaterfallResponse {
+        let reorg = self
+            .header_cache
+            .as_ref()
+            .and_then(|cache| cache.find_reorg(&response));
+        if let Some(reorg) = reorg {
+            info!(
+                "block {} at height {} reorged out, invalidating the caches",
+                reorg.cached, reorg.height
+            );
+            self.invalidate_above(reorg.fork_height());
+        }
+        if let (Some(cache), Some(meta)) = (&self.block_hash_cache, &response.tip_meta) {
+            cache.observe_tip(meta.h.to_u32());
+        }
+        response
+    }
+
+    /// Create a [`HeaderChain`] for `network` anchored at the highest checkpoint at or below
+    /// `start_height`, using the checkpoints and assume valid height of the [`Builder`].
+    pub fn header_chain(&self, network: Network, start_height: u32) -> HeaderChain {
+        let checkpoints = self
+            .checkpoints
+            .clone()
+            .unwrap_or_else(|| Checkpoint::defaults(network));
+        HeaderChain::new(network, checkpoints, start_height)
+            .with_assume_valid_height(self.assume_valid_height)
+    }
+
+    /// Fetch the headers from the tip of `chain` up to `to_height`, validating and connecting
+    /// them to `chain`.
+    ///
+    /// A chain anchored above genesis is first seeded with the headers from the start of the
+    /// difficulty period of its anchor, see [`HeaderChain::seed`].
+    ///
+    /// Validated headers are added to the [`HeaderCache`], if any, indexed by height.
+    /// If the sync fails, `chain` keeps the headers connected so far and can be
+    /// synced again later.
+    pub fn sync_headers(&self, chain: &mut HeaderChain, to_height: u32) -> Result<(), Error> {
+        if !chain.is_seeded(to_height) {
+            let hashes = self.get_block_hashes(
+                chain.seed_height()..=chain.anchor().height,
+                self.sync_profile.header_sync_concurrency(),
+            )?;
+            let headers = hashes
+                .iter()
+                .map(|hash| self.get_header_by_hash(hash))
+                .collect::<Result<Vec<_>, _>>()?;
+            chain.seed(headers)?;
+        }
+        while chain.tip_height() < to_height {
+            let from = chain.tip_height() + 1;
+            let to = to_height.min(from.saturating_add(HEADER_SYNC_BATCH - 1));
+            let hashes =
+                self.get_block_hashes(from..=to, self.sync_profile.header_sync_concurrency())?;
+            for (height, hash) in (from..=to).zip(hashes) {
+                let header = self.get_header_by_hash(&hash)?;
+                if header.block_hash() != hash {
+                    return Err(Error::InvalidResponse);
+                }
+                chain.connect(header)?;
+                if let Some(cache) = &self.header_cache {
+                    cache.insert(Some(height), header);
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Get the server's public key for encryption
@@ -249,15 +1502,15 @@ impl BlockingClient {
 
     /// Broadcast a [`Transaction`] to Waterfalls
     pub fn broadcast(&self, transaction: &Transaction) -> Result<(), Error> {
-        let mut request = minreq::post(format!("{}/tx", self.url)).with_body(
-            serialize(transaction)
-                .to_lower_hex_string()
-                .as_bytes()
-                .to_vec(),
-        );
-
-        if let Some(proxy) = &self.proxy {
-            let proxy = Proxy::new(proxy.as_str())?;
+        let url = format!("{}/tx", self.url);
+        let body = serialize(transaction)
+            .to_lower_hex_string()
+            .as_bytes()
+            .to_vec();
+        let request = self.sign(minreq::post(&url), "POST", &url, &body);
+        let mut request = request.with_body(body.clone());
+
+        if let Some(proxy) = self.request_proxy()? {
             request = request.with_proxy(proxy);
         }
 
@@ -265,27 +1518,237 @@ impl BlockingClient {
             request = request.with_timeout(*timeout);
         }
 
-        match request.send() {
+        match self.send(request, "POST", &url, &body) {
             Ok(resp) if !is_status_ok(resp.status_code) => {
                 let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
                 let message = resp.as_str().unwrap_or_default().to_string();
                 Err(Error::HttpResponse { status, message })
             }
             Ok(_resp) => Ok(()),
-            Err(e) => Err(Error::Minreq(e)),
+            Err(e) => Err(e),
         }
     }
 
+    /// Broadcast the transactions of `queue` in order, removing those the server accepted,
+    /// already knows or rejected.
+    ///
+    /// The flush stops at the first connection or server error, keeping the remaining
+    /// transactions queued so that a later flush retries them.
+    pub fn flush_broadcast_queue(&self, queue: &BroadcastQueue) -> FlushReport {
+        let mut report = FlushReport::default();
+        for tx in queue.pending() {
+            let result = self.broadcast(&tx);
+            if !queue.record(&mut report, tx.compute_txid(), result) {
+                break;
+            }
+        }
+        report
+    }
+
+    /// Seconds elapsed since the timestamp of the tip block, measured on the server clock
+    /// when a [`ClockOffset`] estimate is available.
+    ///
+    /// Block timestamps may be ahead of the current time, in which case the age is zero.
+    pub fn tip_age(&self) -> Result<u64, Error> {
+        let tip = self.get_tip_hash()?;
+        let header = self.get_header_by_hash(&tip)?;
+        let offset = self.clock_offset.clone().unwrap_or_default();
+        Ok(offset.elapsed_since(u64::from(header.time)))
+    }
+
+    /// Returns true if the tip block is at most `max_age` seconds old, allowing for the
+    /// configured clock skew tolerance.
+    pub fn is_tip_fresh(&self, max_age: u64) -> Result<bool, Error> {
+        let age = self.tip_age()?;
+        Ok(age <= max_age.saturating_add(self.clock_skew_tolerance))
+    }
+
     /// Get the [`BlockHash`] of the current blockchain tip.
     pub fn get_tip_hash(&self) -> Result<BlockHash, Error> {
         self.get_response_str("/blocks/tip/hash")
             .map(|s| BlockHash::from_str(s.as_str()).map_err(Error::HexToArray))?
     }
 
-    /// Get the [`BlockHash`] of a specific block height
+    /// Measure the tip, the time since the last block and the latency of the server, see
+    /// [`crate::selector`]
+    pub fn probe(&self) -> Result<ServerProbe, Error> {
+        let started = Instant::now();
+        let tip = self.get_tip_hash()?;
+        let seconds_since_block = parse_seconds_since_block(&self.time_since_last_block()?);
+        Ok(ServerProbe {
+            tip,
+            seconds_since_block,
+            latency: started.elapsed() / 2,
+        })
+    }
+
+    /// Probe every server of `selector` concurrently and promote the best one as primary,
+    /// returning its index, see [`crate::selector`]
+    pub fn score_servers(selector: &ServerSelector<Self>) -> usize {
+        let servers = selector.servers();
+        selector.record(parallel_map(servers, servers.len(), Self::probe))
+    }
+
+    /// Query the tip of every server in `clients` concurrently and return the one at least
+    /// `threshold` of them agree on, see [`crate::quorum`]
+    pub fn tip_quorum(clients: &[Self], threshold: usize) -> Result<TipQuorum, Error> {
+        let tips = parallel_map(clients, clients.len(), |client| client.get_tip_hash());
+        TipQuorum::from_tips(tips, threshold)
+    }
+
+    /// Identify the network of the server from its genesis block, see [`NetworkInfo`].
+    ///
+    /// Elements servers are recognized by their genesis header, which isn't a Bitcoin header.
+    /// The result is cached and shared with the clones of the client.
+    pub fn network_info(&self) -> Result<NetworkInfo, Error> {
+        let cached = *self.network_info.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(info) = cached {
+            return Ok(info);
+        }
+        let genesis_hash = self.get_block_hash(0)?;
+        let family = match self.get_header_by_hash(&genesis_hash) {
+            Ok(_) => ChainFamily::Bitcoin,
+            Err(Error::BitcoinEncoding(_)) => ChainFamily::Elements,
+            Err(e) => return Err(e),
+        };
+        let info = NetworkInfo::new(genesis_hash, family);
+        *self.network_info.lock().unwrap_or_else(|e| e.into_inner()) = Some(info);
+        Ok(info)
+    }
+
+    /// Get the [`BlockHash`] of a specific block height, from the [`BlockHashCache`] if set
     pub fn get_block_hash(&self, block_height: u32) -> Result<BlockHash, Error> {
-        self.get_response_str(&format!("/block-height/{block_height}"))
-            .map(|s| BlockHash::from_str(s.as_str()).map_err(Error::HexToArray))?
+        if let Some(hash) = self
+            .block_hash_cache
+            .as_ref()
+            .and_then(|c| c.get(block_height))
+        {
+            return Ok(hash);
+        }
+        let hash = self
+            .get_response_str(&format!("/block-height/{block_height}"))
+            .map(|s| BlockHash::from_str(s.as_str()).map_err(Error::HexToArray))??;
+        if let Some(cache) = &self.block_hash_cache {
+            cache.insert(block_height, hash);
+        }
+        Ok(hash)
+    }
+
+    /// Get the [`BlockHash`] of every height in `heights`, in order, requesting up to
+    /// `threads` of them concurrently
+    pub fn get_block_hashes(
+        &self,
+        heights: RangeInclusive<u32>,
+        threads: usize,
+    ) -> Result<Vec<BlockHash>, Error> {
+        let threads = self.concurrency(threads);
+        let heights: Vec<u32> = heights.collect();
+        parallel_map(&heights, threads, |height| self.get_block_hash(*height))
+            .into_iter()
+            .collect()
+    }
+
+    /// Get the fee estimates in sat/vB indexed by confirmation target in blocks.
+    ///
+    /// Requested from the Esplora fallback if set, see [`Builder::esplora_fallback`].
+    pub fn get_fee_estimates(&self) -> Result<HashMap<u16, f64>, Error> {
+        let resp = self.get_with_retry("/fee-estimates")?;
+        if !is_status_ok(resp.status_code) {
+            let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
+            let message = resp.as_str().unwrap_or_default().to_string();
+            return Err(Error::HttpResponse { status, message });
+        }
+        let estimates: HashMap<String, f64> = resp.json()?;
+        estimates
+            .into_iter()
+            .map(|(target, rate)| Ok((target.parse()?, rate)))
+            .collect()
+    }
+
+    /// Get the state of the mempool, with its fee histogram.
+    ///
+    /// Requested from the Esplora fallback if set, see [`Builder::esplora_fallback`].
+    pub fn get_mempool_info(&self) -> Result<MempoolInfo, Error> {
+        let resp = self.get_with_retry("/mempool")?;
+        if !is_status_ok(resp.status_code) {
+            let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
+            let message = resp.as_str().unwrap_or_default().to_string();
+            return Err(Error::HttpResponse { status, message });
+        }
+        resp.json()
+    }
+
+    /// The mean interval of the last [`ETA_INTERVAL_BLOCKS`] blocks, from their headers
+    pub fn recent_block_interval(&self) -> Result<Option<Duration>, Error> {
+        let mut hash = self.get_tip_hash()?;
+        let mut times = vec![];
+        for _ in 0..=ETA_INTERVAL_BLOCKS {
+            let header = self.get_header_by_hash(&hash)?;
+            times.push(header.time);
+            if header.prev_blockhash == BlockHash::all_zeros() {
+                break;
+            }
+            hash = header.prev_blockhash;
+        }
+        times.reverse();
+        Ok(mean_block_interval(&times))
+    }
+
+    /// Estimate when a transaction paying `fee_rate` sat/vB would confirm, from the mempool
+    /// fee histogram and the recent block interval, see [`crate::eta`]
+    pub fn estimate_confirmation_eta(&self, fee_rate: f64) -> Result<ConfirmationEta, Error> {
+        let mempool = self.get_mempool_info()?;
+        let interval = self.recent_block_interval()?;
+        Ok(estimate_eta(
+            &mempool.fee_histogram,
+            fee_rate,
+            interval.unwrap_or(DEFAULT_BLOCK_INTERVAL),
+        ))
+    }
+
+    /// Get the spending status of the output `index` of `txid`, or `None` if the server
+    /// doesn't know the transaction.
+    ///
+    /// Requested from the Esplora fallback if set, see [`Builder::esplora_fallback`].
+    pub fn get_output_status(
+        &self,
+        txid: &Txid,
+        index: u32,
+    ) -> Result<Option<OutputStatus>, Error> {
+        let resp = self.get_with_retry(&format!("/tx/{txid}/outspend/{index}"))?;
+        if is_status_not_found(resp.status_code) {
+            return Ok(None);
+        }
+        if !is_status_ok(resp.status_code) {
+            let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
+            let message = resp.as_str().unwrap_or_default().to_string();
+            return Err(Error::HttpResponse { status, message });
+        }
+        Ok(Some(resp.json()?))
+    }
+
+    /// Find the input spending each of `outpoints`, `None` if unspent, with up to `threads`
+    /// concurrent requests, see [`Self::get_output_status`]
+    pub fn find_spends(
+        &self,
+        outpoints: &[OutPoint],
+        threads: usize,
+    ) -> BatchResult<OutPoint, Option<Spend>> {
+        let threads = self.concurrency(threads);
+        let mut result = BatchResult::default();
+        let statuses = parallel_map(outpoints, threads, |outpoint| {
+            self.get_output_status(&outpoint.txid, outpoint.vout)
+        });
+        for (outpoint, status) in outpoints.iter().zip(statuses) {
+            match status {
+                Ok(Some(status)) => result.items.push((*outpoint, status.spend())),
+                Ok(None) => result
+                    .errors
+                    .push((*outpoint, Error::TransactionNotFound(outpoint.txid))),
+                Err(e) => result.errors.push((*outpoint, e)),
+            }
+        }
+        result
     }
 
     /// Get transaction history for the specified address in Esplora-compatible format
@@ -294,18 +1757,90 @@ impl BlockingClient {
         self.get_response_str(&path)
     }
 
+    /// Get the confirmed transactions of `address`, newest first and 25 per page. Pass the
+    /// last txid of a page as `last_seen` to get the following one.
+    pub fn get_address_txs_chain(
+        &self,
+        address: &Address,
+        last_seen: Option<&Txid>,
+    ) -> Result<Vec<Tx>, Error> {
+        let path = match last_seen {
+            Some(last_seen) => format!("/address/{address}/txs/chain/{last_seen}"),
+            None => format!("/address/{address}/txs/chain"),
+        };
+        self.get_response_json_with_query(&path, &[])
+    }
+
+    /// Get a page of the transactions of `address`, newest first, as Esplora pages them:
+    /// without `last_seen` the unconfirmed ones and the first 25 confirmed ones, with
+    /// `last_seen` the 25 confirmed ones following it.
+    ///
+    /// Pass the last confirmed txid of a page to get the next one, an empty page ends the
+    /// history.
+    pub fn get_address_txs_after(
+        &self,
+        address: &Address,
+        last_seen: Option<Txid>,
+    ) -> Result<Vec<Tx>, Error> {
+        match last_seen {
+            Some(last_seen) => self.get_address_txs_chain(address, Some(&last_seen)),
+            None => {
+                let path = format!("/address/{address}/txs");
+                self.get_response_json_with_query(&path, &[])
+            }
+        }
+    }
+
+    /// Get the unconfirmed transactions of `address`, newest first and at most 50
+    pub fn get_address_txs_mempool(&self, address: &Address) -> Result<Vec<Tx>, Error> {
+        let path = format!("/address/{address}/txs/mempool");
+        self.get_response_json_with_query(&path, &[])
+    }
+
+    /// Get the funded and spent outputs and the transaction count of `address`, on chain and
+    /// in the mempool
+    pub fn get_address_stats(&self, address: &Address) -> Result<AddressStats, Error> {
+        let path = format!("/address/{address}");
+        self.get_response_json_with_query(&path, &[])
+    }
+
     /// Sends a GET request to the given `url`, retrying failed attempts
-    /// for retryable error codes until max retries hit.
-    fn get_with_retry(&self, url: &str) -> Result<Response, Error> {
-        let mut delay = BASE_BACKOFF_MILLIS;
+    /// for retryable error codes until max retries hit or the deadline would be exceeded.
+    fn get_with_retry(&self, path: &str) -> Result<Reply, Error> {
+        let mut delay = self.base_backoff;
         let mut attempts = 0;
+        let deadline = self
+            .deadline
+            .map(|seconds| Instant::now() + Duration::from_secs(seconds));
 
         loop {
-            match self.get_request(url)?.send()? {
+            let (mut request, url) = self.get_request_to(path)?;
+            if let Some(deadline) = deadline {
+                // minreq timeouts are in whole seconds
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+                let timeout = self.timeout.map_or(seconds, |timeout| timeout.min(seconds));
+                request = request.with_timeout(timeout.max(1));
+            }
+            let resp = self
+                .send(request, "GET", &url, &[])
+                .map_err(|e| e.at_attempt(attempts + 1).past_deadline(deadline))?;
+            match resp {
                 resp if attempts < self.max_retries && is_status_retryable(resp.status_code) => {
-                    thread::sleep(delay);
+                    let retry_after = resp.headers.get("retry-after").and_then(|value| {
+                        let date = resp.headers.get("date").map(String::as_str);
+                        retry_after_delay(value, date, self.clock_offset.as_ref())
+                    });
+                    let wait = retry_after.unwrap_or(delay);
+                    if deadline.map_or(false, |deadline| Instant::now() + wait >= deadline) {
+                        return Err(Error::DeadlineExceeded {
+                            endpoint: endpoint(&url),
+                            attempt: attempts + 1,
+                        });
+                    }
+                    self.sleep(wait)?;
                     attempts += 1;
-                    delay *= 2;
+                    delay = next_backoff(delay, self.max_backoff);
                 }
                 resp => return Ok(resp),
             }
@@ -313,6 +1848,102 @@ impl BlockingClient {
     }
 }
 
+/// A response of the server, or the canned response of a dry run
+#[derive(Debug)]
+struct Reply {
+    status_code: i32,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn as_str(&self) -> Result<&str, Error> {
+        std::str::from_utf8(&self.body).map_err(Error::InvalidUtf8)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    fn json<T: DeserializeOwned>(self) -> Result<T, Error> {
+        crate::decode_json(self.body)
+    }
+}
+
+impl From<Response> for Reply {
+    fn from(mut resp: Response) -> Self {
+        Reply {
+            status_code: resp.status_code,
+            headers: std::mem::take(&mut resp.headers),
+            body: resp.into_bytes(),
+        }
+    }
+}
+
+/// Apply `f` to every item with at most `threads` scoped threads, returning the results in the
+/// order of `items`
+fn parallel_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1).min(items.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::SeqCst);
+                        match items.get(index) {
+                            Some(item) => results.push((index, f(item))),
+                            None => return results,
+                        }
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Returns true if `e` is a timeout, minreq reports its own as [`std::io::ErrorKind::TimedOut`]
+fn is_timeout(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+    )
+}
+
+/// The path of `url` reported by timeout errors, without the query which may hold a descriptor
+fn endpoint(url: &str) -> String {
+    let target = request_target(url);
+    target.split('?').next().unwrap_or(target).to_string()
+}
+
+fn is_connect_failure(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::TimedOut
+            | std::io::ErrorKind::AddrNotAvailable
+    )
+}
+
 fn is_status_ok(status: i32) -> bool {
     status == 200
 }
@@ -0,0 +1,242 @@
+//! Queue of transactions to broadcast once the server is reachable.
+//!
+//! A [`BroadcastQueue`] accepts transactions while offline and keeps them, optionally in a file,
+//! until a flush with [`crate::BlockingClient::flush_broadcast_queue`] or its async counterpart
+//! sends them. Transactions are sent in the order they were queued, so a child is never
+//! broadcast before its parent.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::{Transaction, Txid};
+
+use crate::Error;
+
+/// Reject reasons of Bitcoin Core for transactions already in the mempool or in a block
+const ALREADY_KNOWN: [&str; 3] = [
+    "txn-already-in-mempool",
+    "txn-already-known",
+    "already in block chain",
+];
+
+/// Reject reasons of Bitcoin Core for transactions spending outputs already spent by another
+const CONFLICT: [&str; 4] = [
+    "txn-mempool-conflict",
+    "bad-txns-inputs-missingorspent",
+    "missing-inputs",
+    "insufficient fee",
+];
+
+/// The result of broadcasting a queued transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastOutcome {
+    /// The server accepted the transaction
+    Broadcast,
+    /// The transaction was already in the mempool or in a block
+    AlreadyKnown,
+    /// The transaction conflicts with another one spending the same outputs, with the server
+    /// message
+    Conflict(String),
+    /// The transaction was rejected for another reason, with the server message
+    Rejected(String),
+}
+
+impl BroadcastOutcome {
+    /// Classify a response with the given `status` to a broadcast, `None` if the transaction
+    /// should stay queued and be retried later
+    pub fn from_response(status: u16, message: &str) -> Option<Self> {
+        let contains = |reasons: &[&str]| reasons.iter().any(|reason| message.contains(reason));
+        match status {
+            200..=299 => Some(BroadcastOutcome::Broadcast),
+            400..=499 if contains(&ALREADY_KNOWN) => Some(BroadcastOutcome::AlreadyKnown),
+            400..=499 if contains(&CONFLICT) => Some(BroadcastOutcome::Conflict(message.into())),
+            // Too many requests is transient
+            429 => None,
+            400..=499 => Some(BroadcastOutcome::Rejected(message.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// The result of a flush of a [`BroadcastQueue`].
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    /// The transactions removed from the queue and why
+    pub outcomes: Vec<(Txid, BroadcastOutcome)>,
+    /// The error which stopped the flush, the remaining transactions are still queued
+    pub error: Option<Error>,
+}
+
+impl FlushReport {
+    /// Returns true if every queued transaction was processed
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueInner {
+    txs: Vec<Transaction>,
+    path: Option<PathBuf>,
+}
+
+/// Transactions waiting to be broadcast, see the [module documentation](self).
+///
+/// Cloning a [`BroadcastQueue`] returns a handle to the same queue.
+#[derive(Debug, Clone, Default)]
+pub struct BroadcastQueue {
+    inner: Arc<Mutex<QueueInner>>,
+}
+
+impl BroadcastQueue {
+    /// Create an empty queue kept in memory only
+    pub fn new() -> Self {
+        BroadcastQueue::default()
+    }
+
+    /// Open the queue persisted at `path`, creating it if the file doesn't exist.
+    ///
+    /// The file is rewritten after every change of the queue.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let queue = BroadcastQueue::new();
+        if path.exists() {
+            queue.read_from(BufReader::new(File::open(&path)?))?;
+        }
+        queue.lock().path = Some(path);
+        Ok(queue)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `transaction`, returning the txids of the queued transactions it replaces because
+    /// they spend some of the same outputs
+    pub fn push(&self, transaction: Transaction) -> Result<Vec<Txid>, Error> {
+        let mut inner = self.lock();
+        let txid = transaction.compute_txid();
+        let mut replaced = vec![];
+        inner.txs.retain(|queued| {
+            let conflicts = queued.compute_txid() == txid
+                || queued.input.iter().any(|queued| {
+                    transaction
+                        .input
+                        .iter()
+                        .any(|input| input.previous_output == queued.previous_output)
+                });
+            if conflicts && queued.compute_txid() != txid {
+                replaced.push(queued.compute_txid());
+            }
+            !conflicts
+        });
+        inner.txs.push(transaction);
+        persist(&inner)?;
+        Ok(replaced)
+    }
+
+    /// Remove the transaction with `txid` from the queue, returning it if it was queued
+    pub fn remove(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let mut inner = self.lock();
+        let index = inner.txs.iter().position(|tx| tx.compute_txid() == *txid);
+        let removed = index.map(|index| inner.txs.remove(index));
+        if removed.is_some() {
+            persist(&inner)?;
+        }
+        Ok(removed)
+    }
+
+    /// The queued transactions, in broadcast order
+    pub fn pending(&self) -> Vec<Transaction> {
+        self.lock().txs.clone()
+    }
+
+    /// Number of queued transactions
+    pub fn len(&self) -> usize {
+        self.lock().txs.len()
+    }
+
+    /// Returns true if no transaction is queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the queued transactions, one hex encoded transaction per line
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        write_txs(&self.lock().txs, writer)
+    }
+
+    /// Queue the transactions read from `reader` in the format of [`Self::write_to`],
+    /// returning the number of transactions read
+    pub fn read_from<R: BufRead>(&self, reader: R) -> Result<usize, Error> {
+        let mut count = 0;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.push(deserialize(&Vec::<u8>::from_hex(line)?)?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Process the response to the broadcast of `txid`, returning false if the flush must stop
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn record(
+        &self,
+        report: &mut FlushReport,
+        txid: Txid,
+        result: Result<(), Error>,
+    ) -> bool {
+        let outcome = match result {
+            Ok(()) => Some(BroadcastOutcome::Broadcast),
+            Err(Error::HttpResponse { status, message }) => {
+                match BroadcastOutcome::from_response(status, &message) {
+                    Some(outcome) => Some(outcome),
+                    None => {
+                        report.error = Some(Error::HttpResponse { status, message });
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                report.error = Some(e);
+                None
+            }
+        };
+        match outcome {
+            Some(outcome) => {
+                if let Err(e) = self.remove(&txid) {
+                    report.error = Some(e);
+                }
+                report.outcomes.push((txid, outcome));
+                report.error.is_none()
+            }
+            None => false,
+        }
+    }
+}
+
+fn write_txs<W: Write>(txs: &[Transaction], mut writer: W) -> Result<(), Error> {
+    for tx in txs {
+        writeln!(writer, "{}", serialize(tx).to_lower_hex_string())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Rewrite the file of the queue, if any, through a temporary file so a crash doesn't lose it
+fn persist(inner: &QueueInner) -> Result<(), Error> {
+    if let Some(path) = &inner.path {
+        let tmp = path.with_extension("tmp");
+        write_txs(&inner.txs, BufWriter::new(File::create(&tmp)?))?;
+        std::fs::rename(&tmp, path)?;
+    }
+    Ok(())
+}
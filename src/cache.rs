@@ -0,0 +1,477 @@
+//! Caches shared across client calls.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use bitcoin::consensus::deserialize;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::hex::FromHex;
+use bitcoin::{block::Header as BlockHeader, BlockHash, Transaction, Txid};
+
+use crate::{Error, WaterfallResponse};
+
+/// A block of the [`HeaderCache`] replaced on the server by another one at the same height.
+///
+/// The clients look for reorgs in every scan and, when one is found, drop the cached headers and
+/// unconfirm the cached scan entries above [`Reorg::fork_height`], see
+/// [`crate::BlockingClient::invalidate_above`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reorg {
+    /// The height of the replaced block
+    pub height: u32,
+    /// The hash of the block in the cache
+    pub cached: BlockHash,
+    /// The hash of the block seen on the server
+    pub seen: BlockHash,
+}
+
+impl Reorg {
+    /// The highest height possibly still shared by the two chains
+    pub fn fork_height(&self) -> u32 {
+        self.height.saturating_sub(1)
+    }
+}
+
+/// Default number of headers kept by a [`HeaderCache`].
+pub const DEFAULT_HEADER_CACHE_CAPACITY: usize = 10_000;
+
+#[derive(Debug)]
+struct HeaderCacheInner {
+    capacity: usize,
+    headers: HashMap<BlockHash, BlockHeader>,
+    heights: BTreeMap<u32, BlockHash>,
+    /// Insertion order of `headers`, used for eviction
+    order: VecDeque<BlockHash>,
+}
+
+/// A cache of block headers indexed by hash and by height.
+///
+/// Cloning a [`HeaderCache`] returns a handle to the same cache, so it can be shared between
+/// calls and clients. Once `capacity` headers are stored, the oldest inserted ones are evicted
+/// first.
+#[derive(Debug, Clone)]
+pub struct HeaderCache {
+    inner: Arc<Mutex<HeaderCacheInner>>,
+}
+
+impl Default for HeaderCache {
+    fn default() -> Self {
+        HeaderCache::new(DEFAULT_HEADER_CACHE_CAPACITY)
+    }
+}
+
+impl HeaderCache {
+    /// Create an empty cache holding at most `capacity` headers
+    pub fn new(capacity: usize) -> Self {
+        HeaderCache {
+            inner: Arc::new(Mutex::new(HeaderCacheInner {
+                capacity,
+                headers: HashMap::new(),
+                heights: BTreeMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HeaderCacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Insert a header, indexing it also by `height` when known
+    pub fn insert(&self, height: Option<u32>, header: BlockHeader) {
+        let hash = header.block_hash();
+        let mut inner = self.lock();
+        if inner.capacity == 0 {
+            return;
+        }
+        if inner.headers.insert(hash, header).is_none() {
+            inner.order.push_back(hash);
+        }
+        if let Some(height) = height {
+            inner.heights.insert(height, hash);
+        }
+        while inner.headers.len() > inner.capacity {
+            let evicted = match inner.order.pop_front() {
+                Some(evicted) => evicted,
+                None => break,
+            };
+            inner.headers.remove(&evicted);
+            inner.heights.retain(|_, hash| *hash != evicted);
+        }
+    }
+
+    /// Get a header given its hash
+    pub fn header_by_hash(&self, hash: &BlockHash) -> Option<BlockHeader> {
+        self.lock().headers.get(hash).copied()
+    }
+
+    /// Get the header at the given height
+    pub fn header_at_height(&self, height: u32) -> Option<BlockHeader> {
+        let inner = self.lock();
+        let hash = inner.heights.get(&height)?;
+        inner.headers.get(hash).copied()
+    }
+
+    /// Get the hash of the header at the given height
+    pub fn hash_at_height(&self, height: u32) -> Option<BlockHash> {
+        self.lock().heights.get(&height).copied()
+    }
+
+    /// Remove every header indexed at a height greater than `height`, e.g. after a reorg
+    pub fn remove_above(&self, height: u32) {
+        let above = match height.checked_add(1) {
+            Some(above) => above,
+            None => return,
+        };
+        let mut inner = self.lock();
+        let removed = inner.heights.split_off(&above);
+        for hash in removed.values() {
+            inner.headers.remove(hash);
+        }
+        inner
+            .order
+            .retain(|hash| !removed.values().any(|h| h == hash));
+    }
+
+    /// The lowest height at which the blocks of `response`, its tip and the blocks confirming
+    /// its transactions, differ from the cached ones
+    pub fn find_reorg(&self, response: &WaterfallResponse) -> Option<Reorg> {
+        let inner = self.lock();
+        let tip = response.tip_meta.iter().map(|meta| (meta.h, meta.b));
+        let confirmed = response
+            .txs_seen
+            .values()
+            .flatten()
+            .flatten()
+            .filter_map(|tx| Some((tx.height, tx.block_hash?)));
+        tip.chain(confirmed)
+            .filter_map(|(height, seen)| {
+                let height = height.to_u32();
+                let cached = *inner.heights.get(&height)?;
+                (cached != seen).then_some(Reorg {
+                    height,
+                    cached,
+                    seen,
+                })
+            })
+            .min_by_key(|reorg| reorg.height)
+    }
+
+    /// Number of headers in the cache
+    pub fn len(&self) -> usize {
+        self.lock().headers.len()
+    }
+
+    /// Returns true if the cache holds no headers
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pre-seed the cache from a checkpoint file, returning the number of headers inserted.
+    ///
+    /// Every non-empty line not starting with `#` must contain a height and the hex encoded
+    /// header separated by whitespace.
+    pub fn seed_from_reader<R: BufRead>(&self, reader: R) -> Result<usize, Error> {
+        let mut count = 0;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let (height, header) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(height), Some(header), None) => (height, header),
+                _ => return Err(Error::InvalidCheckpoint(line.to_string())),
+            };
+            let height = u32::from_str(height)?;
+            let header: BlockHeader = deserialize(&Vec::from_hex(header)?)?;
+            self.insert(Some(height), header);
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Number of blocks below the tip after which a block hash is cached indefinitely by a
+/// [`BlockHashCache`].
+pub const IMMUTABLE_BLOCK_DEPTH: u32 = 100;
+
+/// Default time a [`BlockHashCache`] keeps the hashes of the blocks near the tip.
+pub const DEFAULT_VOLATILE_HASH_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct BlockHashCacheInner {
+    volatile_ttl: Duration,
+    tip: Option<u32>,
+    /// Hashes more than [`IMMUTABLE_BLOCK_DEPTH`] blocks below the tip when fetched
+    immutable: BTreeMap<u32, BlockHash>,
+    /// Hashes near the tip, with the time they were fetched
+    volatile: BTreeMap<u32, (BlockHash, Instant)>,
+}
+
+/// A cache of the block hashes at given heights, as returned by `/block-height/:height`.
+///
+/// A block more than [`IMMUTABLE_BLOCK_DEPTH`] blocks below the highest tip seen is considered
+/// final and its hash is kept indefinitely. The hashes of the blocks near the tip may be
+/// replaced by a reorg and are kept only for the volatile TTL, or until
+/// [`BlockHashCache::remove_above`] drops them.
+///
+/// The tip is taken from the scans of the clients using the cache, or set with
+/// [`BlockHashCache::observe_tip`]. Until a tip is known every hash is volatile.
+///
+/// Cloning a [`BlockHashCache`] returns a handle to the same cache.
+#[derive(Debug, Clone)]
+pub struct BlockHashCache {
+    inner: Arc<Mutex<BlockHashCacheInner>>,
+}
+
+impl Default for BlockHashCache {
+    fn default() -> Self {
+        BlockHashCache::new(DEFAULT_VOLATILE_HASH_TTL)
+    }
+}
+
+impl BlockHashCache {
+    /// Create an empty cache keeping the hashes near the tip for `volatile_ttl`, zero to not
+    /// cache them
+    pub fn new(volatile_ttl: Duration) -> Self {
+        BlockHashCache {
+            inner: Arc::new(Mutex::new(BlockHashCacheInner {
+                volatile_ttl,
+                tip: None,
+                immutable: BTreeMap::new(),
+                volatile: BTreeMap::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BlockHashCacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a tip at `height`, the highest tip seen decides which hashes are immutable
+    pub fn observe_tip(&self, height: u32) {
+        let mut inner = self.lock();
+        inner.tip = Some(inner.tip.map_or(height, |tip| tip.max(height)));
+    }
+
+    /// The height of the highest tip seen
+    pub fn tip(&self) -> Option<u32> {
+        self.lock().tip
+    }
+
+    /// Returns true if the hash at `height` is cached indefinitely once fetched
+    pub fn is_immutable(&self, height: u32) -> bool {
+        is_immutable(self.lock().tip, height)
+    }
+
+    /// Insert the hash of the block at `height`
+    pub fn insert(&self, height: u32, hash: BlockHash) {
+        let mut inner = self.lock();
+        if is_immutable(inner.tip, height) {
+            inner.volatile.remove(&height);
+            inner.immutable.insert(height, hash);
+        } else if !inner.volatile_ttl.is_zero() {
+            inner.volatile.insert(height, (hash, Instant::now()));
+        }
+    }
+
+    /// Get the hash of the block at `height`, `None` if missing or expired
+    pub fn get(&self, height: u32) -> Option<BlockHash> {
+        let mut inner = self.lock();
+        if let Some(hash) = inner.immutable.get(&height) {
+            return Some(*hash);
+        }
+        let (hash, fetched_at) = *inner.volatile.get(&height)?;
+        if fetched_at.elapsed() < inner.volatile_ttl {
+            Some(hash)
+        } else {
+            inner.volatile.remove(&height);
+            None
+        }
+    }
+
+    /// Remove every hash at a height greater than `height`, e.g. after a reorg, and lower the
+    /// tip to `height`
+    pub fn remove_above(&self, height: u32) {
+        let above = match height.checked_add(1) {
+            Some(above) => above,
+            None => return,
+        };
+        let mut inner = self.lock();
+        inner.immutable.split_off(&above);
+        inner.volatile.split_off(&above);
+        inner.tip = inner.tip.map(|tip| tip.min(height));
+    }
+
+    /// Number of hashes in the cache, expired ones included
+    pub fn len(&self) -> usize {
+        let inner = self.lock();
+        inner.immutable.len() + inner.volatile.len()
+    }
+
+    /// Returns true if the cache holds no hashes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns true if the block at `height` is more than [`IMMUTABLE_BLOCK_DEPTH`] blocks below
+/// `tip`
+fn is_immutable(tip: Option<u32>, height: u32) -> bool {
+    tip.map_or(false, |tip| {
+        height < tip.saturating_sub(IMMUTABLE_BLOCK_DEPTH)
+    })
+}
+
+/// Default number of transactions kept by a [`TxCache`].
+pub const DEFAULT_TX_CACHE_CAPACITY: usize = 10_000;
+
+#[derive(Debug)]
+struct TxCacheInner {
+    capacity: usize,
+    txs: HashMap<Txid, Transaction>,
+    /// Insertion order of `txs`, used for eviction
+    order: VecDeque<Txid>,
+}
+
+/// A cache of transactions indexed by txid.
+///
+/// Cloning a [`TxCache`] returns a handle to the same cache. Once `capacity` transactions are
+/// stored, the oldest inserted ones are evicted first.
+#[derive(Debug, Clone)]
+pub struct TxCache {
+    inner: Arc<Mutex<TxCacheInner>>,
+}
+
+impl Default for TxCache {
+    fn default() -> Self {
+        TxCache::new(DEFAULT_TX_CACHE_CAPACITY)
+    }
+}
+
+impl TxCache {
+    /// Create an empty cache holding at most `capacity` transactions
+    pub fn new(capacity: usize) -> Self {
+        TxCache {
+            inner: Arc::new(Mutex::new(TxCacheInner {
+                capacity,
+                txs: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TxCacheInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Insert a transaction
+    pub fn insert(&self, tx: Transaction) {
+        let txid = tx.compute_txid();
+        let mut inner = self.lock();
+        if inner.capacity == 0 {
+            return;
+        }
+        if inner.txs.insert(txid, tx).is_none() {
+            inner.order.push_back(txid);
+        }
+        while inner.txs.len() > inner.capacity {
+            let evicted = match inner.order.pop_front() {
+                Some(evicted) => evicted,
+                None => break,
+            };
+            inner.txs.remove(&evicted);
+        }
+    }
+
+    /// Get a transaction given its txid
+    pub fn get(&self, txid: &Txid) -> Option<Transaction> {
+        self.lock().txs.get(txid).cloned()
+    }
+
+    /// Returns true if the transaction `txid` is in the cache
+    pub fn contains(&self, txid: &Txid) -> bool {
+        self.lock().txs.contains_key(txid)
+    }
+
+    /// Number of transactions in the cache
+    pub fn len(&self) -> usize {
+        self.lock().txs.len()
+    }
+
+    /// Returns true if the cache holds no transactions
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Header carrying the token of the derived scripts of a descriptor in waterfalls responses.
+pub const SCRIPTS_TOKEN_HEADER: &str = "X-Waterfalls-Scripts-Token";
+
+/// A stable key of `descriptor` for caches, the digest of its normalized form.
+///
+/// The checksum, whitespace and the `'` or `h` hardened markers don't change the fingerprint,
+/// so cosmetic variants of the same descriptor share their cache entries.
+pub fn descriptor_fingerprint(descriptor: &str) -> sha256::Hash {
+    let without_checksum = descriptor.split('#').next().unwrap_or_default();
+    let normalized: String = without_checksum
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '\'' { 'h' } else { c })
+        .collect();
+    sha256::Hash::hash(normalized.as_bytes())
+}
+
+/// Tokens issued by the server for the descriptors it already derived, indexed by
+/// [`descriptor_fingerprint`].
+///
+/// The token is sent back in the `scripts` query parameter of later requests for the same
+/// descriptor, so the server can skip deriving its scripts again. Cloning a [`ScriptsTokens`]
+/// returns a handle to the same tokens; use [`ScriptsTokens::tokens`] and
+/// [`ScriptsTokens::insert`] to persist them between sessions.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptsTokens {
+    /// The descriptor last stored and its token, by fingerprint
+    inner: Arc<Mutex<HashMap<sha256::Hash, (String, String)>>>,
+}
+
+impl ScriptsTokens {
+    /// Create an empty set of tokens
+    pub fn new() -> Self {
+        ScriptsTokens::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<sha256::Hash, (String, String)>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The token of `descriptor` or of a cosmetic variant of it, if any
+    pub fn get(&self, descriptor: &str) -> Option<String> {
+        self.lock()
+            .get(&descriptor_fingerprint(descriptor))
+            .map(|(_, token)| token.clone())
+    }
+
+    /// Store the `token` of `descriptor`, replacing the previous one
+    pub fn insert(&self, descriptor: &str, token: &str) {
+        self.lock().insert(
+            descriptor_fingerprint(descriptor),
+            (descriptor.to_string(), token.to_string()),
+        );
+    }
+
+    /// Forget the token of `descriptor`, e.g. after the server rejected it
+    pub fn remove(&self, descriptor: &str) {
+        self.lock().remove(&descriptor_fingerprint(descriptor));
+    }
+
+    /// Every `(descriptor, token)` pair, with the descriptor as last stored
+    pub fn tokens(&self) -> Vec<(String, String)> {
+        self.lock().values().cloned().collect()
+    }
+}
@@ -0,0 +1,132 @@
+//! Cancellation of in-flight operations.
+
+use std::collections::HashMap;
+#[cfg(feature = "async")]
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll};
+
+#[derive(Debug, Default)]
+struct CancellationInner {
+    cancelled: AtomicBool,
+    /// The key of the next cancellable operation
+    #[cfg(feature = "async")]
+    next_key: AtomicU64,
+    /// Tasks waiting on a cancellable operation, by key of the operation
+    wakers: Mutex<HashMap<u64, Waker>>,
+}
+
+/// A token to cancel the operations of a client.
+///
+/// Cloning a [`CancellationToken`] returns a handle to the same token. Once cancelled, pending
+/// and future requests of the clients holding it fail with [`crate::Error::Cancelled`].
+///
+/// The blocking client can't interrupt a request already sent, it checks the token before
+/// every request and while sleeping between retries.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationInner>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Cancel the operations using this token
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut *self.lock_wakers());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Returns true if [`CancellationToken::cancel`] was called
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn lock_wakers(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Waker>> {
+        self.inner.wakers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register the `waker` of the operation at `key`, assigning it a key the first time
+    #[cfg(feature = "async")]
+    fn register(&self, key: &mut Option<u64>, waker: &Waker) {
+        let key = *key.get_or_insert_with(|| self.inner.next_key.fetch_add(1, Ordering::Relaxed));
+        let mut wakers = self.lock_wakers();
+        match wakers.get_mut(&key) {
+            Some(registered) if registered.will_wake(waker) => {}
+            Some(registered) => *registered = waker.clone(),
+            None => {
+                wakers.insert(key, waker.clone());
+            }
+        }
+    }
+
+    /// Forget the waker of the operation at `key`, once done or dropped
+    #[cfg(feature = "async")]
+    fn deregister(&self, key: u64) {
+        self.lock_wakers().remove(&key);
+    }
+}
+
+/// A future resolving to `None` as soon as its token is cancelled
+#[cfg(feature = "async")]
+pub(crate) struct Cancellable<'a, F> {
+    token: &'a CancellationToken,
+    future: Pin<Box<F>>,
+    /// The key of the waker registered in the token, if any
+    key: Option<u64>,
+}
+
+#[cfg(feature = "async")]
+impl<'a, F: Future> Cancellable<'a, F> {
+    pub(crate) fn new(token: &'a CancellationToken, future: F) -> Self {
+        Cancellable {
+            token,
+            future: Box::pin(future),
+            key: None,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F: Future> Future for Cancellable<'_, F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        let this = &mut *self;
+        this.token.register(&mut this.key, cx.waker());
+        // The token may have been cancelled before the waker was registered
+        if self.token.is_cancelled() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "async")]
+impl<F> Drop for Cancellable<'_, F> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.deregister(key);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Clock skew handling between the client and the Waterfalls server.
+//!
+//! Devices with a wrong clock would misjudge the age of blocks and the `Retry-After` dates sent
+//! by the server. A [`ClockOffset`] estimates how far the server clock is from the local one
+//! using the `Date` header of the responses, so times can be compared on the server clock.
+
+use std::sync::{Arc, Mutex};
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current unix time in seconds according to the local clock
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Estimated offset of the server clock relative to the local clock.
+///
+/// Cloning a [`ClockOffset`] returns a handle to the same estimate, so it can be shared between
+/// clients talking to the same server.
+#[derive(Debug, Clone, Default)]
+pub struct ClockOffset {
+    inner: Arc<Mutex<Option<i64>>>,
+}
+
+impl ClockOffset {
+    /// Create a handle with no estimate yet
+    pub fn new() -> Self {
+        ClockOffset::default()
+    }
+
+    /// Seconds the server clock is ahead of the local clock, negative if behind
+    pub fn offset(&self) -> Option<i64> {
+        *self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the offset, e.g. from an estimate persisted by a previous session
+    pub fn set(&self, offset: i64) {
+        *self.inner.lock().unwrap_or_else(|e| e.into_inner()) = Some(offset);
+    }
+
+    /// Update the estimate with the `Date` header of a response to a request sent at local
+    /// time `sent_at` and received at `received_at`.
+    ///
+    /// As in NTP, the server time is assumed to be taken halfway through the round trip.
+    /// Returns false if `date` couldn't be parsed.
+    pub fn observe_date(&self, date: &str, sent_at: u64, received_at: u64) -> bool {
+        match parse_http_date(date) {
+            Some(server) => {
+                let local = (sent_at + received_at.max(sent_at)) / 2;
+                self.set(server as i64 - local as i64);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The current unix time on the server clock, or on the local clock without an estimate
+    pub fn server_now(&self) -> u64 {
+        let now = unix_now() as i64 + self.offset().unwrap_or_default();
+        now.max(0) as u64
+    }
+
+    /// Seconds elapsed on the server clock since `timestamp`, zero if `timestamp` is in the
+    /// future
+    pub fn elapsed_since(&self, timestamp: u64) -> u64 {
+        self.server_now().saturating_sub(timestamp)
+    }
+}
+
+/// Parse an HTTP date in the IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+/// returning the unix time in seconds
+pub fn parse_http_date(date: &str) -> Option<u64> {
+    let mut fields = date.split_whitespace();
+    let _weekday = fields.next()?;
+    let day: u64 = fields.next()?.parse().ok()?;
+    let month = match fields.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: u64 = fields.next()?.parse().ok()?;
+    let mut time = fields.next()?.split(':');
+    let hour: u64 = time.next()?.parse().ok()?;
+    let minute: u64 = time.next()?.parse().ok()?;
+    let second: u64 = time.next()?.parse().ok()?;
+    if fields.next()? != "GMT" || year < 1970 || day == 0 || day > 31 || hour > 23 {
+        return None;
+    }
+    if minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days since the unix epoch of a proleptic gregorian date
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era).checked_sub(719468)?;
+
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// The delay requested by a `Retry-After` header value.
+///
+/// Dates are compared with the response `date` when present, which is on the same clock as the
+/// retry date, otherwise with the server time estimated by `offset`.
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn retry_after_delay(
+    value: &str,
+    date: Option<&str>,
+    offset: Option<&ClockOffset>,
+) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at = parse_http_date(value)?;
+    let now = match date.and_then(parse_http_date) {
+        Some(now) => now,
+        None => offset.map_or_else(unix_now, |o| o.server_now()),
+    };
+    Some(Duration::from_secs(retry_at.saturating_sub(now)))
+}
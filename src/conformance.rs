@@ -0,0 +1,123 @@
+//! Conformance checks for implementations of [`WaterfallsApi`], with the `test-utils` feature.
+//!
+//! [`check_conformance`] runs a standard battery of requests against a backend, such as an
+//! alternative server, a mock or a replay client, and reports every behavior differing from the
+//! Waterfalls server: found and missing items, first page of a scan and repeatable responses.
+
+use bitcoin::hashes::Hash;
+use bitcoin::{BlockHash, Txid};
+
+use crate::{Error, WaterfallResponse, WaterfallsApi};
+
+/// The data the backend under test is expected to know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    /// The height of a block in the best chain
+    pub block_height: u32,
+    /// The hash of the block at `block_height`
+    pub block_hash: BlockHash,
+    /// A transaction known to the backend
+    pub txid: Txid,
+    /// A descriptor with history
+    pub descriptor: String,
+    /// Transactions the scan of `descriptor` must return
+    pub descriptor_txids: Vec<Txid>,
+}
+
+/// A behavior of the backend differing from the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    /// The name of the failed check, e.g. `missing_tx`
+    pub check: &'static str,
+    /// What went wrong
+    pub message: String,
+}
+
+/// A height no chain will reach for a long time
+const MISSING_HEIGHT: u32 = 100_000_000;
+
+/// Run every check against `api`, returning the failures, empty if the backend conforms.
+pub async fn check_conformance(
+    api: &dyn WaterfallsApi,
+    fixture: &Fixture,
+) -> Vec<ConformanceFailure> {
+    let mut failures = vec![];
+    let mut fail =
+        |check: &'static str, message: String| failures.push(ConformanceFailure { check, message });
+
+    match api.get_tip_hash().await {
+        Ok(tip) => match api.get_header_by_hash(&tip).await {
+            Ok(header) if header.block_hash() == tip => {}
+            Ok(header) => fail(
+                "tip",
+                format!("header of {tip} hashes to {}", header.block_hash()),
+            ),
+            Err(e) => fail("tip", format!("header of the tip {tip}: {e}")),
+        },
+        Err(e) => fail("tip", e.to_string()),
+    }
+
+    match api.get_block_hash(fixture.block_height).await {
+        Ok(block_hash) if block_hash == fixture.block_hash => {}
+        Ok(block_hash) => fail("block_hash", format!("unexpected hash {block_hash}")),
+        Err(e) => fail("block_hash", e.to_string()),
+    }
+    match api.get_header_by_hash(&fixture.block_hash).await {
+        Ok(header) if header.block_hash() == fixture.block_hash => {}
+        Ok(header) => fail(
+            "header",
+            format!("header hashes to {}", header.block_hash()),
+        ),
+        Err(e) => fail("header", e.to_string()),
+    }
+
+    // Missing blocks are errors, missing transactions are `None`
+    if let Ok(block_hash) = api.get_block_hash(MISSING_HEIGHT).await {
+        fail("missing_block_hash", format!("returned {block_hash}"));
+    }
+    let missing = BlockHash::all_zeros();
+    if api.get_header_by_hash(&missing).await.is_ok() {
+        fail("missing_header", format!("returned a header for {missing}"));
+    }
+    match api.get_tx(&fixture.txid).await {
+        Ok(Some(tx)) if tx.compute_txid() == fixture.txid => {}
+        Ok(Some(tx)) => fail("tx", format!("unexpected txid {}", tx.compute_txid())),
+        Ok(None) => fail("tx", format!("{} not found", fixture.txid)),
+        Err(e) => fail("tx", e.to_string()),
+    }
+    match api.get_tx(&Txid::all_zeros()).await {
+        Ok(None) => {}
+        Ok(Some(_)) => fail("missing_tx", "returned a transaction".to_string()),
+        Err(e) => fail("missing_tx", format!("expected None, got {e}")),
+    }
+
+    match scan_twice(api, &fixture.descriptor).await {
+        Ok((first, second)) => {
+            if first.page != 0 {
+                fail("scan_first_page", format!("page {} returned", first.page));
+            }
+            let txids = first.txids();
+            for txid in &fixture.descriptor_txids {
+                if !txids.contains(txid) {
+                    fail("scan_history", format!("{txid} missing"));
+                }
+            }
+            if first.content_hash() != second.content_hash() {
+                fail("scan_repeatable", "two scans differ".to_string());
+            }
+        }
+        Err(e) => fail("scan", e.to_string()),
+    }
+
+    failures
+}
+
+async fn scan_twice(
+    api: &dyn WaterfallsApi,
+    descriptor: &str,
+) -> Result<(WaterfallResponse, WaterfallResponse), Error> {
+    Ok((
+        api.waterfalls(descriptor).await?,
+        api.waterfalls(descriptor).await?,
+    ))
+}
@@ -0,0 +1,133 @@
+//! Consolidation of small unspent outputs.
+//!
+//! Spending many small outputs later costs a fee for each of their inputs. Spending them now
+//! into one output, when fees are low, costs one transaction but saves the fees of all the
+//! inputs but one when the funds are spent. [`find_consolidations`] groups the small spendable
+//! outputs of a [`crate::WalletSummary`] by keychain and projects, for each group, the fee of
+//! the consolidation at the current feerate and the fee saved at the expected future feerate.
+//!
+//! Only the script types with a known input size are considered: P2PKH, P2WPKH and key path
+//! P2TR. Consolidating links the outputs together on chain, which hurts privacy.
+
+use std::collections::BTreeMap;
+
+use bitcoin::{Amount, FeeRate, Script, Weight};
+
+use crate::api::fee_for;
+use crate::{Height, Keychain, Utxo};
+
+/// Minimum number of outputs worth consolidating
+pub const MIN_CONSOLIDATION_INPUTS: usize = 2;
+
+/// Confirmations needed to spend a coinbase output
+const COINBASE_MATURITY: u32 = 100;
+
+/// Weight of the version, locktime and counts of a transaction with one input and one output
+const TX_OVERHEAD: Weight = Weight::from_wu(40);
+
+/// Weight of the segwit marker and flag
+const SEGWIT_OVERHEAD: Weight = Weight::from_wu(2);
+
+/// The projected cost and savings of consolidating outputs of a keychain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consolidation {
+    /// The keychain of the outputs, `None` for address scans and non standard paths
+    pub keychain: Option<Keychain>,
+    /// The outputs to consolidate, smallest first
+    pub utxos: Vec<Utxo>,
+    /// The value of the outputs
+    pub total: Amount,
+    /// The weight of the consolidation transaction
+    pub weight: Weight,
+    /// The fee of the consolidation at the current feerate
+    pub fee: Amount,
+    /// The fee saved spending one output instead of all of them at the future feerate
+    pub savings: Amount,
+}
+
+impl Consolidation {
+    /// Returns true if the fee saved later exceeds the fee of the consolidation
+    pub fn is_worth_it(&self) -> bool {
+        self.savings > self.fee
+    }
+}
+
+/// The weight of an input spending `script`, `None` for script types with unknown size
+pub fn input_weight(script: &Script) -> Option<Weight> {
+    // Outpoint, sequence and script length, then the script or the witness
+    let base = Weight::from_non_witness_data_size(32 + 4 + 4 + 1);
+    if script.is_p2pkh() {
+        Some(base + Weight::from_non_witness_data_size(107))
+    } else if script.is_p2wpkh() {
+        Some(base + Weight::from_witness_data_size(108))
+    } else if script.is_p2tr() {
+        Some(base + Weight::from_witness_data_size(66))
+    } else {
+        None
+    }
+}
+
+/// Find, for each keychain, the spendable outputs below `max_value` worth more than the fee
+/// of their input at `feerate`, and project their consolidation into one output at `feerate`
+/// against spending them at `future_feerate`.
+///
+/// Groups with fewer than [`MIN_CONSOLIDATION_INPUTS`] outputs are skipped, the others are
+/// returned by decreasing net savings.
+pub fn find_consolidations(
+    utxos: &[Utxo],
+    feerate: FeeRate,
+    future_feerate: FeeRate,
+    max_value: Amount,
+) -> Vec<Consolidation> {
+    let mut groups: BTreeMap<Option<Keychain>, Vec<(Utxo, Weight)>> = BTreeMap::new();
+    for utxo in utxos {
+        let spendable = utxo.height != Height::ZERO
+            && (!utxo.is_coinbase || utxo.confirmations.to_u32() >= COINBASE_MATURITY);
+        let weight = match input_weight(&utxo.txout.script_pubkey) {
+            Some(weight) if spendable && utxo.txout.value < max_value => weight,
+            _ => continue,
+        };
+        if utxo.txout.value <= fee_for(feerate, weight) {
+            continue;
+        }
+        groups
+            .entry(utxo.keychain)
+            .or_default()
+            .push((utxo.clone(), weight));
+    }
+
+    let mut consolidations: Vec<Consolidation> = groups
+        .into_iter()
+        .filter(|(_, group)| group.len() >= MIN_CONSOLIDATION_INPUTS)
+        .map(|(keychain, mut group)| {
+            group.sort_by_key(|(utxo, _)| utxo.txout.value);
+            let inputs: Weight = group.iter().map(|(_, weight)| *weight).sum();
+            let largest = group
+                .iter()
+                .map(|(_, weight)| *weight)
+                .max()
+                .unwrap_or(Weight::ZERO);
+            let output = group[0].0.txout.weight();
+            let segwit = group
+                .iter()
+                .any(|(utxo, _)| !utxo.txout.script_pubkey.is_p2pkh());
+            let mut weight = TX_OVERHEAD + inputs + output;
+            if segwit {
+                weight += SEGWIT_OVERHEAD;
+            }
+            let utxos: Vec<Utxo> = group.into_iter().map(|(utxo, _)| utxo).collect();
+            Consolidation {
+                keychain,
+                total: utxos.iter().map(|utxo| utxo.txout.value).sum(),
+                utxos,
+                weight,
+                fee: fee_for(feerate, weight),
+                savings: fee_for(future_feerate, inputs - largest),
+            }
+        })
+        .collect();
+    consolidations.sort_by_key(|c| {
+        std::cmp::Reverse(i128::from(c.savings.to_sat()) - i128::from(c.fee.to_sat()))
+    });
+    consolidations
+}
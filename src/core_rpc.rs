@@ -0,0 +1,243 @@
+//! Bitcoin Core RPC bridge for the generic endpoints.
+//!
+//! A [`CoreRpcClient`] serves transactions, headers, the tip and broadcasts from a local
+//! Bitcoin Core node with the same signatures as [`crate::BlockingClient`], so that a
+//! Waterfalls server is only needed for descriptor scans.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::{block::Header as BlockHeader, Amount, BlockHash, FeeRate, Transaction, Txid};
+use log::trace;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{BasicAuth, Error, MempoolEntry, RelayPolicy};
+
+/// Error code of Bitcoin Core for unknown transactions, blocks and addresses
+const RPC_INVALID_ADDRESS_OR_KEY: i64 = -5;
+/// Error code of Bitcoin Core for out of range parameters, such as a height above the tip
+const RPC_INVALID_PARAMETER: i64 = -8;
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcMempoolFees {
+    base: f64,
+    ancestor: f64,
+    descendant: f64,
+}
+
+#[derive(Deserialize)]
+struct RpcMempoolEntry {
+    weight: u64,
+    ancestorcount: u64,
+    ancestorsize: u64,
+    descendantcount: u64,
+    descendantsize: u64,
+    fees: RpcMempoolFees,
+}
+
+#[derive(Deserialize)]
+struct RpcNetworkInfo {
+    relayfee: f64,
+    incrementalfee: f64,
+}
+
+/// A blocking client for the JSON-RPC interface of Bitcoin Core.
+#[derive(Debug)]
+pub struct CoreRpcClient {
+    url: String,
+    auth: BasicAuth,
+    timeout: Option<u64>,
+    next_id: AtomicU64,
+}
+
+impl CoreRpcClient {
+    /// Create a client for the node at `url`, e.g. `http://127.0.0.1:8332`, authenticating
+    /// with the `rpcuser` and `rpcpassword` credentials
+    pub fn new(url: &str, auth: BasicAuth) -> Self {
+        CoreRpcClient {
+            url: url.to_string(),
+            auth,
+            timeout: None,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a client authenticating with the cookie file written by the node in its data
+    /// directory
+    pub fn with_cookie_file(url: &str, path: &Path) -> Result<Self, Error> {
+        let cookie = std::fs::read_to_string(path)?;
+        let (username, password) = cookie.trim().split_once(':').ok_or_else(|| {
+            Error::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid cookie file",
+            ))
+        })?;
+        Ok(Self::new(url, BasicAuth::new(username, password)))
+    }
+
+    /// Set the socket timeout in seconds
+    pub fn timeout(mut self, timeout: u64) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let body = json!({"jsonrpc": "1.0", "id": id, "method": method, "params": params});
+        trace!("core rpc request {body}");
+
+        let mut request = minreq::post(&self.url)
+            .with_header("Authorization", self.auth.header_value())
+            .with_header("Content-Type", "application/json")
+            .with_body(body.to_string());
+        if let Some(timeout) = self.timeout {
+            request = request.with_timeout(timeout);
+        }
+        let resp = request.send()?;
+
+        // Bitcoin Core answers RPC errors with a JSON body and a non 200 status
+        let response: RpcResponse = match resp.json() {
+            Ok(response) => response,
+            Err(_) => {
+                let status = u16::try_from(resp.status_code).map_err(Error::StatusCode)?;
+                let message = resp.as_str().unwrap_or_default().to_string();
+                return Err(Error::HttpResponse { status, message });
+            }
+        };
+        if let Some(error) = response.error {
+            return Err(Error::CoreRpc {
+                code: error.code,
+                message: error.message,
+            });
+        }
+        serde_json::from_value(response.result.unwrap_or(Value::Null))
+            .map_err(|_| Error::InvalidResponse)
+    }
+
+    /// Get a [`Transaction`] option given its [`Txid`].
+    ///
+    /// Unconfirmed transactions and those in blocks are found only if the node has them in
+    /// its mempool or runs with `txindex=1`.
+    pub fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        match self.call::<String>("getrawtransaction", json!([txid, false])) {
+            Ok(hex) => Ok(Some(deserialize(&Vec::<u8>::from_hex(&hex)?)?)),
+            Err(Error::CoreRpc {
+                code: RPC_INVALID_ADDRESS_OR_KEY,
+                ..
+            }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get a [`Transaction`] given its [`Txid`].
+    pub fn get_tx_no_opt(&self, txid: &Txid) -> Result<Transaction, Error> {
+        match self.get_tx(txid) {
+            Ok(Some(tx)) => Ok(tx),
+            Ok(None) => Err(Error::TransactionNotFound(*txid)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get a [`BlockHeader`] given a particular block hash.
+    pub fn get_header_by_hash(&self, block_hash: &BlockHash) -> Result<BlockHeader, Error> {
+        match self.call::<String>("getblockheader", json!([block_hash, false])) {
+            Ok(hex) => Ok(deserialize(&Vec::<u8>::from_hex(&hex)?)?),
+            Err(Error::CoreRpc {
+                code: RPC_INVALID_ADDRESS_OR_KEY,
+                ..
+            }) => Err(Error::HeaderHashNotFound(*block_hash)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the [`BlockHash`] of the current blockchain tip.
+    pub fn get_tip_hash(&self) -> Result<BlockHash, Error> {
+        let hash: String = self.call("getbestblockhash", json!([]))?;
+        Ok(BlockHash::from_str(&hash)?)
+    }
+
+    /// Get the height of the current blockchain tip.
+    pub fn get_height(&self) -> Result<u32, Error> {
+        self.call("getblockcount", json!([]))
+    }
+
+    /// Get the [`BlockHash`] of a specific block height
+    pub fn get_block_hash(&self, block_height: u32) -> Result<BlockHash, Error> {
+        match self.call::<String>("getblockhash", json!([block_height])) {
+            Ok(hash) => Ok(BlockHash::from_str(&hash)?),
+            Err(Error::CoreRpc {
+                code: RPC_INVALID_PARAMETER,
+                ..
+            }) => Err(Error::HeaderHeightNotFound(block_height)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get the [`MempoolEntry`] of `txid`, or `None` if it isn't in the mempool of the node
+    pub fn get_mempool_entry(&self, txid: &Txid) -> Result<Option<MempoolEntry>, Error> {
+        let entry: RpcMempoolEntry = match self.call("getmempoolentry", json!([txid])) {
+            Ok(entry) => entry,
+            Err(Error::CoreRpc {
+                code: RPC_INVALID_ADDRESS_OR_KEY,
+                ..
+            }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let sat = |btc: f64| {
+            Amount::from_btc(btc)
+                .map(Amount::to_sat)
+                .map_err(|_| Error::InvalidResponse)
+        };
+        Ok(Some(MempoolEntry {
+            weight: entry.weight,
+            fee: sat(entry.fees.base)?,
+            ancestor_count: entry.ancestorcount,
+            ancestor_size: entry.ancestorsize,
+            ancestor_fees: sat(entry.fees.ancestor)?,
+            descendant_count: entry.descendantcount,
+            descendant_size: entry.descendantsize,
+            descendant_fees: sat(entry.fees.descendant)?,
+        }))
+    }
+
+    /// Get the [`RelayPolicy`] of the node, the maximum standard weight isn't exposed by the
+    /// RPC interface and is left unknown
+    pub fn relay_policy(&self) -> Result<RelayPolicy, Error> {
+        let info: RpcNetworkInfo = self.call("getnetworkinfo", json!([]))?;
+        // Bitcoin Core reports feerates in BTC/kvB, a kvB is 4 kwu
+        let feerate = |btc_per_kvb: f64| {
+            Amount::from_btc(btc_per_kvb)
+                .map(|per_kvb| FeeRate::from_sat_per_kwu(per_kvb.to_sat() / 4))
+                .map_err(|_| Error::InvalidResponse)
+        };
+        Ok(RelayPolicy {
+            min_relay_feerate: Some(feerate(info.relayfee)?),
+            incremental_relay_feerate: Some(feerate(info.incrementalfee)?),
+            max_standard_weight: None,
+        })
+    }
+
+    /// Broadcast a [`Transaction`] through the node
+    pub fn broadcast(&self, transaction: &Transaction) -> Result<(), Error> {
+        let hex = serialize(transaction).to_lower_hex_string();
+        self.call::<Txid>("sendrawtransaction", json!([hex]))?;
+        Ok(())
+    }
+}
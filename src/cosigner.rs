@@ -0,0 +1,140 @@
+//! Data exchanged between the cosigners of a multi-signature wallet.
+//!
+//! To build a PSBT on another device, a cosigner needs the unspent outputs of the wallet, the
+//! derivation path of their scripts and the transactions creating them, which signers check
+//! before signing non segwit inputs and to verify the amounts. [`CosignerData`] holds them in
+//! a compact JSON document, with every transaction in consensus hex once even if it creates
+//! several outputs of the wallet.
+//!
+//! [`crate::BlockingClient::cosigner_data`] and its async counterpart build it from a scan.
+
+use std::collections::BTreeMap;
+
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use bitcoin::consensus::encode::{deserialize_hex, serialize_hex};
+use bitcoin::{Amount, OutPoint, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Keychain, Utxo};
+
+/// Version of the [`CosignerData`] format written by this crate
+pub const COSIGNER_DATA_VERSION: u8 = 1;
+
+/// An unspent output of a [`CosignerData`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosignerUtxo {
+    /// The output
+    #[serde(rename = "o")]
+    pub outpoint: OutPoint,
+    /// The value of the output
+    #[serde(rename = "a")]
+    pub value: Amount,
+    /// The path of the script relative to the keys of the descriptor, e.g. `1/4` for the
+    /// fifth change address
+    #[serde(rename = "p")]
+    pub path: DerivationPath,
+}
+
+/// The unspent outputs of a wallet with their transactions, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosignerData {
+    /// The format version, [`COSIGNER_DATA_VERSION`]
+    #[serde(rename = "v")]
+    pub version: u8,
+    /// The unspent outputs
+    #[serde(rename = "u")]
+    pub utxos: Vec<CosignerUtxo>,
+    /// The transactions creating the outputs, in consensus hex
+    #[serde(rename = "t")]
+    pub txs: BTreeMap<Txid, String>,
+}
+
+impl CosignerData {
+    /// The data of `utxos`, created by the transactions in `txs`. Outputs whose transaction
+    /// is missing are skipped.
+    pub fn new(utxos: &[Utxo], txs: &BTreeMap<Txid, Transaction>) -> Self {
+        let mut data = CosignerData {
+            version: COSIGNER_DATA_VERSION,
+            utxos: vec![],
+            txs: BTreeMap::new(),
+        };
+        for utxo in utxos {
+            let tx = match txs.get(&utxo.outpoint.txid) {
+                Some(tx) => tx,
+                None => continue,
+            };
+            let mut path = vec![];
+            match utxo.keychain {
+                Some(Keychain::External) => path.push(ChildNumber::Normal { index: 0 }),
+                Some(Keychain::Internal) => path.push(ChildNumber::Normal { index: 1 }),
+                None => {}
+            }
+            path.push(ChildNumber::Normal { index: utxo.index });
+            data.utxos.push(CosignerUtxo {
+                outpoint: utxo.outpoint,
+                value: utxo.txout.value,
+                path: DerivationPath::from(path),
+            });
+            data.txs
+                .entry(utxo.outpoint.txid)
+                .or_insert_with(|| serialize_hex(tx));
+        }
+        data
+    }
+
+    /// The transaction `txid`, `None` if it isn't in the data
+    pub fn tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        self.txs
+            .get(txid)
+            .map(|hex| deserialize_hex(hex).map_err(|e| invalid(format!("{txid}: {e}"))))
+            .transpose()
+    }
+
+    /// Check the version, that every transaction matches its txid and that every output is
+    /// in its transaction with the stated value
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.version != COSIGNER_DATA_VERSION {
+            return Err(invalid(format!("unsupported version {}", self.version)));
+        }
+        let mut txs = BTreeMap::new();
+        for (txid, hex) in &self.txs {
+            let tx: Transaction =
+                deserialize_hex(hex).map_err(|e| invalid(format!("{txid}: {e}")))?;
+            if tx.compute_txid() != *txid {
+                return Err(invalid(format!("{txid}: txid mismatch")));
+            }
+            txs.insert(*txid, tx);
+        }
+        for utxo in &self.utxos {
+            let outpoint = utxo.outpoint;
+            let txout = txs
+                .get(&outpoint.txid)
+                .and_then(|tx| tx.output.get(outpoint.vout as usize));
+            match txout {
+                Some(txout) if txout.value == utxo.value => {}
+                Some(_) => return Err(invalid(format!("{outpoint}: value mismatch"))),
+                None => return Err(invalid(format!("{outpoint}: missing transaction"))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode the data as compact JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("serializing to a string doesn't fail")
+    }
+
+    /// Decode and [validate](Self::validate) data encoded with [`Self::to_json`]
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let data: CosignerData = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        data.validate()?;
+        Ok(data)
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidCosignerData(message)
+}
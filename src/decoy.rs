@@ -0,0 +1,203 @@
+//! Decoy addresses mixed into address queries.
+//!
+//! A client built with [`crate::Builder::decoy_pool`] adds to every
+//! [`crate::BlockingClient::waterfalls_addresses`] query, and its async counterpart, `ratio`
+//! decoys per real address taken from a [`DecoyPool`], shuffles them with the real ones and
+//! removes their histories from the response, so the caller sees only its own addresses. The
+//! pool is filled with addresses used on chain, with [`DecoyPool::insert_from_txs`] or with
+//! [`crate::BlockingClient::refresh_decoys`] from the recent blocks of a server providing the
+//! Esplora block endpoints.
+//!
+//! Decoys make it harder for the server to tell which addresses of a query belong to the
+//! wallet, not impossible:
+//!
+//! - the real addresses come back in every query while the decoys change, intersecting a few
+//!   queries of the same wallet reveals them
+//! - decoys are drawn from recent chain activity and may have a history unlike the wallet ones,
+//!   e.g. exchange addresses with thousands of transactions
+//! - the timing of the queries, the IP address and the transactions broadcast by the wallet are
+//!   unaffected
+//! - the server does `ratio` times more work and the responses grow accordingly
+//!
+//! Descriptor queries are not affected, they reveal the whole wallet anyway.
+
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bitcoin::{Address, Network, Transaction};
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::{Error, LimitKind};
+use crate::{WaterfallResponse, ADDRESSES_KEY};
+
+/// Default maximum number of addresses in a [`DecoyPool`]
+pub const DEFAULT_DECOY_POOL_CAPACITY: usize = 10_000;
+
+/// Maximum number of transactions of each block used by
+/// [`crate::BlockingClient::refresh_decoys`]
+pub const DECOY_TXS_PER_BLOCK: usize = 10;
+
+#[derive(Debug)]
+struct DecoyInner {
+    addresses: VecDeque<Address>,
+    capacity: usize,
+}
+
+/// Addresses seen on chain used as decoys, see the [module documentation](self).
+///
+/// Cloning a [`DecoyPool`] returns a handle to the same pool.
+#[derive(Debug, Clone)]
+pub struct DecoyPool {
+    inner: Arc<Mutex<DecoyInner>>,
+    ratio: usize,
+}
+
+impl DecoyPool {
+    /// Create an empty pool adding `ratio` decoys per real address
+    pub fn new(ratio: usize) -> Self {
+        DecoyPool {
+            inner: Arc::new(Mutex::new(DecoyInner {
+                addresses: VecDeque::new(),
+                capacity: DEFAULT_DECOY_POOL_CAPACITY,
+            })),
+            ratio,
+        }
+    }
+
+    /// Set the maximum number of addresses kept, the oldest are dropped first
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.lock().capacity = capacity;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DecoyInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The number of decoys added per real address
+    pub fn ratio(&self) -> usize {
+        self.ratio
+    }
+
+    /// The number of addresses in the pool
+    pub fn len(&self) -> usize {
+        self.lock().addresses.len()
+    }
+
+    /// Returns true if the pool has no addresses
+    pub fn is_empty(&self) -> bool {
+        self.lock().addresses.is_empty()
+    }
+
+    /// Add addresses to the pool, skipping the ones already in it
+    pub fn insert(&self, addresses: impl IntoIterator<Item = Address>) {
+        let mut inner = self.lock();
+        for address in addresses {
+            if !inner.addresses.contains(&address) {
+                inner.addresses.push_back(address);
+            }
+        }
+        let excess = inner.addresses.len().saturating_sub(inner.capacity);
+        inner.addresses.drain(..excess);
+    }
+
+    /// Add the addresses of the outputs of `txs` on `network` to the pool
+    pub fn insert_from_txs<'a>(
+        &self,
+        txs: impl IntoIterator<Item = &'a Transaction>,
+        network: Network,
+    ) {
+        self.insert(txs.into_iter().flat_map(|tx| {
+            tx.output
+                .iter()
+                .filter_map(move |output| Address::from_script(&output.script_pubkey, network).ok())
+        }));
+    }
+
+    /// Mix decoys into `addresses`, returning the addresses to query and the position of each
+    /// real address among them, see [`remove_decoys`]
+    pub fn mix(&self, addresses: &[Address]) -> (Vec<Address>, Vec<usize>) {
+        let inner = self.lock();
+        let mut candidates: Vec<&Address> = inner
+            .addresses
+            .iter()
+            .filter(|address| !addresses.contains(address))
+            .collect();
+        let wanted = addresses.len().saturating_mul(self.ratio);
+        let decoys = choose(&mut candidates, wanted).iter().map(|a| (*a).clone());
+
+        // Tag each address with its origin, `None` for decoys, then shuffle them together
+        let mut mixed: Vec<(Option<usize>, Address)> = addresses
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(index, address)| (Some(index), address))
+            .chain(decoys.map(|address| (None, address)))
+            .collect();
+        shuffle(&mut mixed);
+        let mut positions = vec![0; addresses.len()];
+        for (position, (index, _)) in mixed.iter().enumerate() {
+            if let Some(index) = index {
+                positions[*index] = position;
+            }
+        }
+        (
+            mixed.into_iter().map(|(_, address)| address).collect(),
+            positions,
+        )
+    }
+}
+
+/// Keep in the `response` to a query built by [`DecoyPool::mix`] only the histories of the
+/// real addresses, at `positions` in the query, in their original order
+pub fn remove_decoys(mut response: WaterfallResponse, positions: &[usize]) -> WaterfallResponse {
+    if let Some(histories) = response.txs_seen.remove(ADDRESSES_KEY) {
+        let real = positions
+            .iter()
+            .map(|position| histories.get(*position).cloned().unwrap_or_default())
+            .collect();
+        response.txs_seen.insert(ADDRESSES_KEY.to_string(), real);
+    }
+    response
+}
+
+/// Move `count` random items, or all of them if fewer, to the start of `items` and return
+/// them, with a partial Fisher-Yates shuffle
+fn choose<T>(items: &mut [T], count: usize) -> &[T] {
+    let count = count.min(items.len());
+    for i in 0..count {
+        let j = i + (random_u64() % (items.len() - i) as u64) as usize;
+        items.swap(i, j);
+    }
+    &items[..count]
+}
+
+/// Shuffle `items` with the Fisher-Yates algorithm
+fn shuffle<T>(items: &mut [T]) {
+    choose(items, items.len());
+}
+
+/// The error of a query with decoys, with the limits of the server converted to real addresses
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn without_decoys(error: Error, ratio: usize, actual: usize) -> Error {
+    match error {
+        Error::LimitExceeded {
+            kind: LimitKind::Addresses,
+            limit,
+            ..
+        } => Error::LimitExceeded {
+            kind: LimitKind::Addresses,
+            limit: limit / ratio.saturating_add(1),
+            actual,
+        },
+        e => e,
+    }
+}
+
+/// A random number from the randomly keyed hasher of the standard library, which is enough
+/// to pick decoys without another dependency
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
@@ -0,0 +1,56 @@
+//! Derivation of the addresses of a descriptor.
+//!
+//! [`derive_addresses`] derives the same scripts the server derives for a descriptor scan, so
+//! a wallet can show its next receive address without depending on `miniscript` itself.
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use bitcoin::{Address, Network};
+use miniscript::{Descriptor, DescriptorPublicKey};
+
+use crate::{Error, Keychain};
+
+/// Derive the addresses of `keychain` at the indexes in `range` from `descriptor`.
+///
+/// A multipath descriptor such as `wpkh(xpub/<0;1>/*)` is split into its receive and change
+/// branches, a single path descriptor is used as is unless it's clearly of the other keychain.
+pub fn derive_addresses(
+    descriptor: &str,
+    keychain: Keychain,
+    range: Range<u32>,
+    network: Network,
+) -> Result<Vec<Address>, Error> {
+    let invalid = |e: &dyn std::fmt::Display| Error::InvalidDescriptor(e.to_string());
+    let descriptor =
+        Descriptor::<DescriptorPublicKey>::from_str(descriptor).map_err(|e| invalid(&e))?;
+    let mut branches = descriptor
+        .into_single_descriptors()
+        .map_err(|e| invalid(&e))?;
+    let branch = if branches.len() == 1 {
+        let branch = branches.remove(0);
+        match Keychain::from_key(&branch.to_string()) {
+            Some(other) if other != keychain => {
+                return Err(Error::InvalidDescriptor(format!(
+                    "the descriptor derives the {other:?} keychain"
+                )))
+            }
+            _ => branch,
+        }
+    } else {
+        let index = match keychain {
+            Keychain::External => 0,
+            Keychain::Internal => 1,
+        };
+        match branches.into_iter().nth(index) {
+            Some(branch) => branch,
+            None => return Err(Error::InvalidDescriptor("missing keychain".to_string())),
+        }
+    };
+    range
+        .map(|index| {
+            let derived = branch.at_derivation_index(index).map_err(|e| invalid(&e))?;
+            derived.address(network).map_err(|e| invalid(&e))
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+//! Recording of the requests a client would send, without sending them.
+//!
+//! A client built with [`crate::Builder::dry_run`] doesn't touch the network: every request is
+//! recorded in the [`DryRun`], before any signing, and answered with a canned response so that
+//! calls made of several requests run to completion:
+//!
+//! - scans return an empty [`crate::WaterfallResponse`]
+//! - address histories and block transaction lists are empty
+//! - fee estimates are empty and the mempool is empty
+//! - the tip and the block hashes are the all-zeros hash
+//! - broadcasts succeed
+//!
+//! Other requests are answered with an empty `404 Not Found`, so lookups like
+//! [`crate::BlockingClient::get_tx`] return `None`. The recorded URLs and bodies show exactly
+//! what a wallet would reveal to the server.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::EndpointClass;
+
+/// The placeholder of redacted values
+pub const REDACTED: &str = "REDACTED";
+
+/// A request recorded by a [`DryRun`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    /// The HTTP method, e.g. `GET`
+    pub method: String,
+    /// The full URL, with the values of the redacted query parameters replaced by [`REDACTED`]
+    pub url: String,
+    /// The body, if not empty, replaced by [`REDACTED`] if bodies are redacted
+    pub body: Option<String>,
+}
+
+/// The requests recorded by clients in dry-run mode, see the [module documentation](self).
+///
+/// Cloning a [`DryRun`] returns a handle to the same records.
+#[derive(Debug, Clone, Default)]
+pub struct DryRun {
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    redacted_params: Vec<String>,
+    redact_body: bool,
+}
+
+impl DryRun {
+    /// Create an empty record, without redaction
+    pub fn new() -> Self {
+        DryRun::default()
+    }
+
+    /// Redact the value of the query parameter `key`, e.g. `descriptor`
+    pub fn redact_param(mut self, key: &str) -> Self {
+        self.redacted_params.push(key.to_string());
+        self
+    }
+
+    /// Redact the request bodies, such as broadcast transactions
+    pub fn redact_body(mut self) -> Self {
+        self.redact_body = true;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<RecordedRequest>> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The requests recorded so far, in order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().clone()
+    }
+
+    /// Forget the recorded requests
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn record(&self, method: &str, url: &str, body: &[u8]) {
+        let url = match url.split_once('?') {
+            Some((base, query)) if !self.redacted_params.is_empty() => {
+                let params: Vec<String> = query
+                    .split('&')
+                    .map(|param| match param.split_once('=') {
+                        Some((key, _)) if self.redacted_params.iter().any(|k| k == key) => {
+                            format!("{key}={REDACTED}")
+                        }
+                        _ => param.to_string(),
+                    })
+                    .collect();
+                format!("{base}?{}", params.join("&"))
+            }
+            _ => url.to_string(),
+        };
+        let body = match body {
+            [] => None,
+            _ if self.redact_body => Some(REDACTED.to_string()),
+            body => Some(String::from_utf8_lossy(body).into_owned()),
+        };
+        self.lock().push(RecordedRequest {
+            method: method.to_string(),
+            url,
+            body,
+        });
+    }
+}
+
+/// The status and body of the canned response to a request in dry-run mode, see the
+/// [module documentation](self)
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn canned_response(method: &str, url: &str) -> (u16, &'static str) {
+    const ZERO_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+    let path = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest.find('/').map_or("", |i| &rest[i..]));
+    let path = path.split('?').next().unwrap_or_default();
+    match EndpointClass::classify(method, url) {
+        EndpointClass::Waterfalls => (200, r#"{"txs_seen":{},"page":0}"#),
+        EndpointClass::Broadcast => (200, ""),
+        EndpointClass::FeeEstimates => (200, "{}"),
+        EndpointClass::Address if path.contains("/txs") => (200, "[]"),
+        EndpointClass::Block if path.ends_with("/txids") => (200, "[]"),
+        EndpointClass::Block
+            if path.ends_with("/blocks/tip/hash") || path.contains("/block-height/") =>
+        {
+            (200, ZERO_HASH)
+        }
+        EndpointClass::Other if path.ends_with("/mempool") => (
+            200,
+            r#"{"count":0,"vsize":0,"total_fee":0,"fee_histogram":[]}"#,
+        ),
+        _ => (404, ""),
+    }
+}
@@ -0,0 +1,138 @@
+//! Object safe interface over the clients.
+//!
+//! [`WaterfallsApi`] is implemented by [`crate::BlockingClient`] and [`crate::AsyncClient`]
+//! whatever its [`crate::Sleeper`], so an application can keep a [`DynWaterfallsClient`] in a
+//! non-generic struct and swap the real client for a mock or a simulation at runtime.
+//!
+//! Every method returns a boxed future. The futures of the blocking client do the request when
+//! polled for the first time, blocking the executor thread meanwhile.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use bitcoin::{block::Header as BlockHeader, Address, BlockHash, Transaction, Txid};
+
+use crate::{Error, WaterfallResponse};
+
+/// A boxed future returned by the methods of [`WaterfallsApi`]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A client boxed as a trait object
+pub type DynWaterfallsClient = Box<dyn WaterfallsApi>;
+
+/// The endpoints of a Waterfalls server, see the [module documentation](self).
+pub trait WaterfallsApi: Send + Sync {
+    /// Scan the scripts derived from `descriptor`
+    fn waterfalls<'a>(
+        &'a self,
+        descriptor: &'a str,
+    ) -> BoxFuture<'a, Result<WaterfallResponse, Error>>;
+
+    /// Scan the given `addresses`
+    fn waterfalls_addresses<'a>(
+        &'a self,
+        addresses: &'a [Address],
+    ) -> BoxFuture<'a, Result<WaterfallResponse, Error>>;
+
+    /// Get a [`Transaction`] option given its [`Txid`]
+    fn get_tx<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<Option<Transaction>, Error>>;
+
+    /// Get a [`BlockHeader`] given its [`BlockHash`]
+    fn get_header_by_hash<'a>(
+        &'a self,
+        block_hash: &'a BlockHash,
+    ) -> BoxFuture<'a, Result<BlockHeader, Error>>;
+
+    /// Get the [`BlockHash`] of the current blockchain tip
+    fn get_tip_hash(&self) -> BoxFuture<'_, Result<BlockHash, Error>>;
+
+    /// Get the [`BlockHash`] of a specific block height
+    fn get_block_hash(&self, block_height: u32) -> BoxFuture<'_, Result<BlockHash, Error>>;
+
+    /// Broadcast a [`Transaction`]
+    fn broadcast<'a>(&'a self, transaction: &'a Transaction) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+#[cfg(feature = "blocking")]
+impl WaterfallsApi for crate::BlockingClient {
+    fn waterfalls<'a>(
+        &'a self,
+        descriptor: &'a str,
+    ) -> BoxFuture<'a, Result<WaterfallResponse, Error>> {
+        Box::pin(async move { crate::BlockingClient::waterfalls(self, descriptor) })
+    }
+
+    fn waterfalls_addresses<'a>(
+        &'a self,
+        addresses: &'a [Address],
+    ) -> BoxFuture<'a, Result<WaterfallResponse, Error>> {
+        Box::pin(async move { crate::BlockingClient::waterfalls_addresses(self, addresses) })
+    }
+
+    fn get_tx<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<Option<Transaction>, Error>> {
+        Box::pin(async move { crate::BlockingClient::get_tx(self, txid) })
+    }
+
+    fn get_header_by_hash<'a>(
+        &'a self,
+        block_hash: &'a BlockHash,
+    ) -> BoxFuture<'a, Result<BlockHeader, Error>> {
+        Box::pin(async move { crate::BlockingClient::get_header_by_hash(self, block_hash) })
+    }
+
+    fn get_tip_hash(&self) -> BoxFuture<'_, Result<BlockHash, Error>> {
+        Box::pin(async move { crate::BlockingClient::get_tip_hash(self) })
+    }
+
+    fn get_block_hash(&self, block_height: u32) -> BoxFuture<'_, Result<BlockHash, Error>> {
+        Box::pin(async move { crate::BlockingClient::get_block_hash(self, block_height) })
+    }
+
+    fn broadcast<'a>(&'a self, transaction: &'a Transaction) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move { crate::BlockingClient::broadcast(self, transaction) })
+    }
+}
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+impl<S> WaterfallsApi for crate::AsyncClient<S>
+where
+    S: crate::Sleeper + Send + Sync,
+    S::Sleep: Send,
+{
+    fn waterfalls<'a>(
+        &'a self,
+        descriptor: &'a str,
+    ) -> BoxFuture<'a, Result<WaterfallResponse, Error>> {
+        Box::pin(crate::AsyncClient::waterfalls(self, descriptor))
+    }
+
+    fn waterfalls_addresses<'a>(
+        &'a self,
+        addresses: &'a [Address],
+    ) -> BoxFuture<'a, Result<WaterfallResponse, Error>> {
+        Box::pin(crate::AsyncClient::waterfalls_addresses(self, addresses))
+    }
+
+    fn get_tx<'a>(&'a self, txid: &'a Txid) -> BoxFuture<'a, Result<Option<Transaction>, Error>> {
+        Box::pin(crate::AsyncClient::get_tx(self, txid))
+    }
+
+    fn get_header_by_hash<'a>(
+        &'a self,
+        block_hash: &'a BlockHash,
+    ) -> BoxFuture<'a, Result<BlockHeader, Error>> {
+        Box::pin(crate::AsyncClient::get_header_by_hash(self, block_hash))
+    }
+
+    fn get_tip_hash(&self) -> BoxFuture<'_, Result<BlockHash, Error>> {
+        Box::pin(crate::AsyncClient::get_tip_hash(self))
+    }
+
+    fn get_block_hash(&self, block_height: u32) -> BoxFuture<'_, Result<BlockHash, Error>> {
+        Box::pin(crate::AsyncClient::get_block_hash(self, block_height))
+    }
+
+    fn broadcast<'a>(&'a self, transaction: &'a Transaction) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(crate::AsyncClient::broadcast(self, transaction))
+    }
+}
@@ -0,0 +1,208 @@
+//! Fallback backend speaking the Electrum protocol.
+//!
+//! When no Waterfalls server is reachable, an [`ElectrumClient`] can serve the address history
+//! subset of the API, returning the same types as [`crate::BlockingClient`] so the calling code
+//! doesn't change. Descriptors can't be scanned since Electrum servers only index scripts.
+//!
+//! Only plaintext TCP connections are supported.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bitcoin::consensus::{deserialize, serialize};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::hex::{DisplayHex, FromHex};
+use bitcoin::{block::Header as BlockHeader, Address, BlockHash, Script, Transaction, Txid};
+use log::trace;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{BlockMeta, Error, Height, TxSeen, WaterfallResponse, V};
+
+pub use crate::ADDRESSES_KEY;
+
+#[derive(Deserialize)]
+struct HistoryEntry {
+    tx_hash: Txid,
+    height: i32,
+}
+
+#[derive(Deserialize)]
+struct HeaderNotification {
+    height: u32,
+    hex: String,
+}
+
+/// A blocking client for an Electrum server.
+#[derive(Debug)]
+pub struct ElectrumClient {
+    stream: Mutex<BufReader<TcpStream>>,
+    next_id: AtomicU64,
+}
+
+impl ElectrumClient {
+    /// Connect to the Electrum server at `addr`, e.g. `electrum.example.com:50001`
+    pub fn new<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        Self::with_timeout(addr, None)
+    }
+
+    /// Connect with a `timeout` in seconds for connecting and reading responses
+    pub fn with_timeout<A: ToSocketAddrs>(addr: A, timeout: Option<u64>) -> Result<Self, Error> {
+        let stream = match timeout {
+            Some(timeout) => {
+                let timeout = Duration::from_secs(timeout);
+                let addr = addr
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or(Error::InvalidResponse)?;
+                let stream = TcpStream::connect_timeout(&addr, timeout)?;
+                stream.set_read_timeout(Some(timeout))?;
+                stream
+            }
+            None => TcpStream::connect(addr)?,
+        };
+        Ok(ElectrumClient {
+            stream: Mutex::new(BufReader::new(stream)),
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        trace!("electrum request {request}");
+
+        let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        let mut line = request.to_string();
+        line.push('\n');
+        stream.get_mut().write_all(line.as_bytes())?;
+
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line)? == 0 {
+                return Err(Error::InvalidResponse);
+            }
+            let mut response: Value =
+                serde_json::from_str(&line).map_err(|e| Error::Electrum(e.to_string()))?;
+            // Skip notifications of previous subscriptions
+            if response.get("id").and_then(Value::as_u64) != Some(id) {
+                continue;
+            }
+            if let Some(error) = response.get("error").filter(|e| !e.is_null()) {
+                return Err(Error::Electrum(error.to_string()));
+            }
+            return Ok(response["result"].take());
+        }
+    }
+
+    fn call_into<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, Error> {
+        let result = self.call(method, params)?;
+        serde_json::from_value(result).map_err(|e| Error::Electrum(e.to_string()))
+    }
+
+    /// The history of every address, in the format of the waterfalls endpoint.
+    ///
+    /// Electrum servers don't return block hashes, timestamps and inputs or outputs index, so
+    /// the [`TxSeen`] only have the txid and height.
+    pub fn waterfalls_addresses(&self, addresses: &[Address]) -> Result<WaterfallResponse, Error> {
+        let mut histories = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let history: Vec<HistoryEntry> = self.call_into(
+                "blockchain.scripthash.get_history",
+                json!([script_hash(&address.script_pubkey())]),
+            )?;
+            histories.push(
+                history
+                    .into_iter()
+                    .map(|entry| TxSeen {
+                        txid: entry.tx_hash,
+                        height: Height(entry.height.max(0) as u32),
+                        block_hash: None,
+                        block_timestamp: None,
+                        v: V::Undefined,
+                    })
+                    .collect(),
+            );
+        }
+        let mut response = WaterfallResponse {
+            txs_seen: BTreeMap::from([(ADDRESSES_KEY.to_string(), histories)]),
+            tip_meta: Some(self.tip_meta()?),
+            ..Default::default()
+        };
+        response.normalize();
+        Ok(response)
+    }
+
+    /// Get a [`Transaction`] option given its [`Txid`]
+    pub fn get_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        match self.get_tx_no_opt(txid) {
+            Ok(tx) => Ok(Some(tx)),
+            Err(Error::TransactionNotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get a [`Transaction`] given its [`Txid`].
+    ///
+    /// Any error reported by the server is returned as [`Error::TransactionNotFound`].
+    pub fn get_tx_no_opt(&self, txid: &Txid) -> Result<Transaction, Error> {
+        let hex: String = match self.call_into("blockchain.transaction.get", json!([txid])) {
+            Ok(hex) => hex,
+            Err(Error::Electrum(_)) => return Err(Error::TransactionNotFound(*txid)),
+            Err(e) => return Err(e),
+        };
+        Ok(deserialize(&Vec::<u8>::from_hex(&hex)?)?)
+    }
+
+    /// Broadcast a [`Transaction`]
+    pub fn broadcast(&self, transaction: &Transaction) -> Result<(), Error> {
+        let hex = serialize(transaction).to_lower_hex_string();
+        self.call("blockchain.transaction.broadcast", json!([hex]))?;
+        Ok(())
+    }
+
+    /// Get the [`BlockHeader`] at the given height
+    pub fn get_header_by_height(&self, block_height: u32) -> Result<BlockHeader, Error> {
+        let hex: String = match self.call_into("blockchain.block.header", json!([block_height])) {
+            Ok(hex) => hex,
+            Err(Error::Electrum(_)) => return Err(Error::HeaderHeightNotFound(block_height)),
+            Err(e) => return Err(e),
+        };
+        Ok(deserialize(&Vec::<u8>::from_hex(&hex)?)?)
+    }
+
+    /// Get the [`BlockHash`] of a specific block height
+    pub fn get_block_hash(&self, block_height: u32) -> Result<BlockHash, Error> {
+        Ok(self.get_header_by_height(block_height)?.block_hash())
+    }
+
+    /// Get the [`BlockHash`] of the current blockchain tip
+    pub fn get_tip_hash(&self) -> Result<BlockHash, Error> {
+        Ok(self.tip_meta()?.b)
+    }
+
+    fn tip_meta(&self) -> Result<BlockMeta, Error> {
+        let tip: HeaderNotification = self.call_into("blockchain.headers.subscribe", json!([]))?;
+        let header: BlockHeader = deserialize(&Vec::<u8>::from_hex(&tip.hex)?)?;
+        Ok(BlockMeta {
+            b: header.block_hash(),
+            t: header.time.into(),
+            h: Height(tip.height),
+        })
+    }
+}
+
+/// The Electrum script hash of `script`: its sha256 with the bytes reversed, hex encoded
+pub fn script_hash(script: &Script) -> String {
+    let mut hash = sha256::Hash::hash(script.as_bytes()).to_byte_array();
+    hash.reverse();
+    hash.to_lower_hex_string()
+}
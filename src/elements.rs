@@ -0,0 +1,219 @@
+//! Minimal parsing of raw Elements transactions.
+//!
+//! The `elements` crate isn't a dependency, the Liquid helpers such as [`crate::pegs`] and
+//! [`crate::issuance`] read the few fields they need with [`parse_tx`], and
+//! [`crate::elements_header`] reads block headers with [`Reader`]. Range proofs, surjection
+//! proofs and the script witnesses are skipped.
+
+use bitcoin::consensus::encode::Error as EncodeError;
+use bitcoin::hashes::Hash;
+use bitcoin::{OutPoint, Script, Txid};
+
+use crate::Error;
+
+/// Flag of the previous output index of an input claiming a peg-in
+const PEGIN_FLAG: u32 = 1 << 30;
+
+/// Flag of the previous output index of an input issuing an asset
+const ISSUANCE_FLAG: u32 = 1 << 31;
+
+pub(crate) fn parse_failed(message: &'static str) -> Error {
+    Error::BitcoinEncoding(EncodeError::ParseFailed(message))
+}
+
+/// A confidential value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Value {
+    /// No value
+    Null,
+    /// An explicit amount
+    Explicit(u64),
+    /// A blinded amount
+    Confidential,
+}
+
+impl Value {
+    /// The amount if explicit
+    pub(crate) fn explicit(self) -> Option<u64> {
+        match self {
+            Value::Explicit(value) => Some(value),
+            Value::Null | Value::Confidential => None,
+        }
+    }
+}
+
+/// The asset issuance of an input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AssetIssuance {
+    /// Zero for a new issuance, the blinding factor of the reissuance token otherwise
+    pub(crate) blinding_nonce: [u8; 32],
+    /// The contract hash for a new issuance, the entropy of the asset for a reissuance
+    pub(crate) asset_entropy: [u8; 32],
+    pub(crate) amount: Value,
+    pub(crate) inflation_keys: Value,
+}
+
+/// An input of an Elements transaction
+#[derive(Debug, Clone)]
+pub(crate) struct Input<'a> {
+    /// The spent output, with the flags removed from the index
+    pub(crate) previous_output: OutPoint,
+    pub(crate) is_pegin: bool,
+    pub(crate) issuance: Option<AssetIssuance>,
+    /// Empty if the transaction has no witness
+    pub(crate) pegin_witness: Vec<&'a [u8]>,
+}
+
+/// An output of an Elements transaction
+#[derive(Debug, Clone)]
+pub(crate) struct Output<'a> {
+    pub(crate) value: Value,
+    pub(crate) script_pubkey: &'a Script,
+}
+
+/// The fields of an Elements transaction read by the Liquid helpers
+#[derive(Debug, Clone)]
+pub(crate) struct ElementsTx<'a> {
+    pub(crate) inputs: Vec<Input<'a>>,
+    pub(crate) outputs: Vec<Output<'a>>,
+}
+
+/// A cursor over the bytes of a transaction or block header
+pub(crate) struct Reader<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(parse_failed("truncated elements data"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    pub(crate) fn array(&mut self) -> Result<[u8; 32], Error> {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(self.take(32)?);
+        Ok(bytes)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, Error> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub(crate) fn var_int(&mut self) -> Result<usize, Error> {
+        let value = match self.u8()? {
+            0xfd => u64::from(u16::from_le_bytes([self.u8()?, self.u8()?])),
+            0xfe => u64::from(self.u32()?),
+            0xff => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                u64::from_le_bytes(bytes)
+            }
+            n => u64::from(n),
+        };
+        usize::try_from(value).map_err(|_| parse_failed("length overflow"))
+    }
+
+    pub(crate) fn var_bytes(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.var_int()?;
+        self.take(len)
+    }
+
+    pub(crate) fn stack(&mut self) -> Result<Vec<&'a [u8]>, Error> {
+        let count = self.var_int()?;
+        (0..count).map(|_| self.var_bytes()).collect()
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.u8()? {
+            0 => Ok(Value::Null),
+            1 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                Ok(Value::Explicit(u64::from_be_bytes(bytes)))
+            }
+            8 | 9 => self.take(32).map(|_| Value::Confidential),
+            _ => Err(parse_failed("invalid confidential value")),
+        }
+    }
+
+    /// A confidential asset or nonce
+    fn commitment(&mut self) -> Result<(), Error> {
+        match self.u8()? {
+            0 => Ok(()),
+            1..=3 | 0x0a | 0x0b => self.take(32).map(|_| ()),
+            _ => Err(parse_failed("invalid confidential commitment")),
+        }
+    }
+}
+
+/// Parse the raw Elements transaction `raw`
+pub(crate) fn parse_tx(raw: &[u8]) -> Result<ElementsTx<'_>, Error> {
+    let mut reader = Reader(raw);
+    reader.u32()?; // version
+    let has_witness = match reader.u8()? {
+        0 => false,
+        1 => true,
+        _ => return Err(parse_failed("invalid elements transaction flag")),
+    };
+
+    let mut inputs = vec![];
+    for _ in 0..reader.var_int()? {
+        let txid = Txid::from_byte_array(reader.array()?);
+        let vout = reader.u32()?;
+        reader.var_bytes()?; // script sig
+        reader.u32()?; // sequence
+
+        // The null prevout of coinbase inputs has every flag set
+        let (index, flags) = match vout {
+            u32::MAX => (vout, 0),
+            _ => (vout & !(PEGIN_FLAG | ISSUANCE_FLAG), vout),
+        };
+        let issuance = if flags & ISSUANCE_FLAG != 0 {
+            Some(AssetIssuance {
+                blinding_nonce: reader.array()?,
+                asset_entropy: reader.array()?,
+                amount: reader.value()?,
+                inflation_keys: reader.value()?,
+            })
+        } else {
+            None
+        };
+        inputs.push(Input {
+            previous_output: OutPoint::new(txid, index),
+            is_pegin: flags & PEGIN_FLAG != 0,
+            issuance,
+            pegin_witness: vec![],
+        });
+    }
+
+    let mut outputs = vec![];
+    for _ in 0..reader.var_int()? {
+        reader.commitment()?; // asset
+        let value = reader.value()?;
+        reader.commitment()?; // nonce
+        let script_pubkey = Script::from_bytes(reader.var_bytes()?);
+        outputs.push(Output {
+            value,
+            script_pubkey,
+        });
+    }
+    reader.u32()?; // lock time
+
+    if has_witness {
+        for input in inputs.iter_mut() {
+            reader.var_bytes()?; // issuance amount range proof
+            reader.var_bytes()?; // inflation keys range proof
+            reader.stack()?; // script witness
+            input.pegin_witness = reader.stack()?;
+        }
+    }
+    Ok(ElementsTx { inputs, outputs })
+}
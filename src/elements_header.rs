@@ -0,0 +1,317 @@
+//! Block headers of Elements chains such as Liquid.
+//!
+//! Elements headers aren't Bitcoin headers: they carry the block height and, instead of a
+//! proof of work, either a signed block proof or, after the dynamic federation (dynafed)
+//! activation, the federation parameters and the witness signing the block. [`ElementsHeader`]
+//! holds them as typed fields, [`parse_elements_header`] reads them from the bytes returned by
+//! the `/block/:hash/header` endpoint of a Liquid server.
+//!
+//! [`ElementsHeader::connects_to`] checks the link between consecutive headers, and
+//! [`ElementsHeader::federation_change`] reports the blocks where the script signing the blocks
+//! changes or a new federation is proposed, to monitor federation changes.
+
+use bitcoin::consensus::encode::{serialize, VarInt};
+use bitcoin::hashes::{sha256d, Hash};
+use bitcoin::{BlockHash, Script, ScriptBuf, TxMerkleNode};
+
+use crate::elements::{parse_failed, Reader};
+use crate::Error;
+
+/// Bit of the serialized version marking a dynafed header
+const DYNAFED_VERSION_BIT: u32 = 1 << 31;
+
+/// The parameters of a dynamic federation, as committed in a dynafed header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DynafedParams {
+    /// No parameters, e.g. no proposal
+    Null,
+    /// The parameters needed to validate the block, the others are committed by `elided_root`
+    Compact {
+        /// The script the block witness must satisfy
+        signblockscript: ScriptBuf,
+        /// The maximum size of the block witness
+        signblock_witness_limit: u32,
+        /// The root of the elided parameters
+        elided_root: [u8; 32],
+    },
+    /// Every parameter, present at the start of each epoch
+    Full {
+        /// The script the block witness must satisfy
+        signblockscript: ScriptBuf,
+        /// The maximum size of the block witness
+        signblock_witness_limit: u32,
+        /// The script of the peg-in addresses on the main chain
+        fedpeg_program: ScriptBuf,
+        /// The script of the federation holding the main chain funds
+        fedpegscript: Vec<u8>,
+        /// The keys allowed to sign peg-out authorizations
+        extension_space: Vec<Vec<u8>>,
+    },
+}
+
+impl DynafedParams {
+    /// Returns true for [`DynafedParams::Null`]
+    pub fn is_null(&self) -> bool {
+        matches!(self, DynafedParams::Null)
+    }
+
+    /// The script the block witness must satisfy, `None` for null parameters
+    pub fn signblockscript(&self) -> Option<&Script> {
+        match self {
+            DynafedParams::Null => None,
+            DynafedParams::Compact {
+                signblockscript, ..
+            }
+            | DynafedParams::Full {
+                signblockscript, ..
+            } => Some(signblockscript),
+        }
+    }
+
+    fn read(reader: &mut Reader) -> Result<Self, Error> {
+        match reader.u8()? {
+            0 => Ok(DynafedParams::Null),
+            1 => Ok(DynafedParams::Compact {
+                signblockscript: ScriptBuf::from_bytes(reader.var_bytes()?.to_vec()),
+                signblock_witness_limit: reader.u32()?,
+                elided_root: reader.array()?,
+            }),
+            2 => Ok(DynafedParams::Full {
+                signblockscript: ScriptBuf::from_bytes(reader.var_bytes()?.to_vec()),
+                signblock_witness_limit: reader.u32()?,
+                fedpeg_program: ScriptBuf::from_bytes(reader.var_bytes()?.to_vec()),
+                fedpegscript: reader.var_bytes()?.to_vec(),
+                extension_space: to_vecs(reader.stack()?),
+            }),
+            _ => Err(parse_failed("invalid dynafed parameters type")),
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            DynafedParams::Null => out.push(0),
+            DynafedParams::Compact {
+                signblockscript,
+                signblock_witness_limit,
+                elided_root,
+            } => {
+                out.push(1);
+                write_var_bytes(out, signblockscript.as_bytes());
+                out.extend(signblock_witness_limit.to_le_bytes());
+                out.extend(elided_root);
+            }
+            DynafedParams::Full {
+                signblockscript,
+                signblock_witness_limit,
+                fedpeg_program,
+                fedpegscript,
+                extension_space,
+            } => {
+                out.push(2);
+                write_var_bytes(out, signblockscript.as_bytes());
+                out.extend(signblock_witness_limit.to_le_bytes());
+                write_var_bytes(out, fedpeg_program.as_bytes());
+                write_var_bytes(out, fedpegscript);
+                write_stack(out, extension_space);
+            }
+        }
+    }
+}
+
+/// The part of an [`ElementsHeader`] proving the block was signed by the federation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderExt {
+    /// A header from before dynafed
+    Proof {
+        /// The script the solution must satisfy
+        challenge: ScriptBuf,
+        /// The signatures of the block, not committed by the block hash
+        solution: ScriptBuf,
+    },
+    /// A dynafed header
+    Dynafed {
+        /// The parameters of the current epoch
+        current: DynafedParams,
+        /// The parameters proposed by the signer of the block, null if none
+        proposed: DynafedParams,
+        /// The witness satisfying the signblockscript, not committed by the block hash
+        signblock_witness: Vec<Vec<u8>>,
+    },
+}
+
+/// A block header of an Elements chain, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementsHeader {
+    /// The version, without the dynafed bit
+    pub version: u32,
+    /// The hash of the previous block
+    pub prev_blockhash: BlockHash,
+    /// The merkle root of the transactions of the block
+    pub merkle_root: TxMerkleNode,
+    /// The timestamp of the block
+    pub time: u32,
+    /// The height of the block
+    pub height: u32,
+    /// The signed block proof or the dynafed fields
+    pub ext: HeaderExt,
+}
+
+impl ElementsHeader {
+    /// Returns true if the header has the dynafed fields
+    pub fn is_dynafed(&self) -> bool {
+        matches!(self.ext, HeaderExt::Dynafed { .. })
+    }
+
+    /// The script signing this block: the challenge of a proof header, the signblockscript of
+    /// the current parameters of a dynafed one
+    pub fn signblockscript(&self) -> Option<&Script> {
+        match &self.ext {
+            HeaderExt::Proof { challenge, .. } => Some(challenge),
+            HeaderExt::Dynafed { current, .. } => current.signblockscript(),
+        }
+    }
+
+    /// The parameters proposed by the signer of the block, `None` if it proposes none
+    pub fn proposed(&self) -> Option<&DynafedParams> {
+        match &self.ext {
+            HeaderExt::Dynafed { proposed, .. } if !proposed.is_null() => Some(proposed),
+            _ => None,
+        }
+    }
+
+    /// The hash of the block, committing to every field but the solution or the signblock
+    /// witness
+    pub fn block_hash(&self) -> BlockHash {
+        BlockHash::from_raw_hash(sha256d::Hash::hash(&self.serialize(false)))
+    }
+
+    /// Returns true if this header follows `previous`: it builds on its hash at the next
+    /// height
+    pub fn connects_to(&self, previous: &ElementsHeader) -> bool {
+        self.prev_blockhash == previous.block_hash()
+            && previous.height.checked_add(1) == Some(self.height)
+    }
+
+    /// The change of federation at this header, following `previous`
+    pub fn federation_change(&self, previous: &ElementsHeader) -> Option<FederationChange> {
+        if self.signblockscript() != previous.signblockscript() {
+            return Some(FederationChange::Activated {
+                height: self.height,
+                previous: previous.signblockscript().map(Script::to_owned),
+                current: self.signblockscript().map(Script::to_owned),
+            });
+        }
+        let proposed = self.proposed()?;
+        (previous.proposed() != Some(proposed)).then(|| FederationChange::Proposed {
+            height: self.height,
+            params: proposed.clone(),
+        })
+    }
+
+    /// The header encoded as Elements does, with the solution or signblock witness if
+    /// `with_witness`
+    pub fn serialize(&self, with_witness: bool) -> Vec<u8> {
+        let version = match self.ext {
+            HeaderExt::Proof { .. } => self.version,
+            HeaderExt::Dynafed { .. } => self.version | DYNAFED_VERSION_BIT,
+        };
+        let mut out = version.to_le_bytes().to_vec();
+        out.extend(self.prev_blockhash.as_byte_array());
+        out.extend(self.merkle_root.as_byte_array());
+        out.extend(self.time.to_le_bytes());
+        out.extend(self.height.to_le_bytes());
+        match &self.ext {
+            HeaderExt::Proof {
+                challenge,
+                solution,
+            } => {
+                write_var_bytes(&mut out, challenge.as_bytes());
+                if with_witness {
+                    write_var_bytes(&mut out, solution.as_bytes());
+                }
+            }
+            HeaderExt::Dynafed {
+                current,
+                proposed,
+                signblock_witness,
+            } => {
+                current.write(&mut out);
+                proposed.write(&mut out);
+                if with_witness {
+                    write_stack(&mut out, signblock_witness);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A change of the federation of an Elements chain, see [`ElementsHeader::federation_change`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FederationChange {
+    /// The script signing the blocks changed at `height`
+    Activated {
+        /// The height of the first block signed with the new script
+        height: u32,
+        /// The script signing the previous block
+        previous: Option<ScriptBuf>,
+        /// The script signing this block
+        current: Option<ScriptBuf>,
+    },
+    /// The block at `height` proposes new parameters
+    Proposed {
+        /// The height of the proposing block
+        height: u32,
+        /// The proposed parameters
+        params: DynafedParams,
+    },
+}
+
+/// Parse the serialized Elements block header `raw`
+pub fn parse_elements_header(raw: &[u8]) -> Result<ElementsHeader, Error> {
+    let mut reader = Reader(raw);
+    let version = reader.u32()?;
+    let prev_blockhash = BlockHash::from_byte_array(reader.array()?);
+    let merkle_root = TxMerkleNode::from_byte_array(reader.array()?);
+    let time = reader.u32()?;
+    let height = reader.u32()?;
+    let ext = if version & DYNAFED_VERSION_BIT != 0 {
+        HeaderExt::Dynafed {
+            current: DynafedParams::read(&mut reader)?,
+            proposed: DynafedParams::read(&mut reader)?,
+            signblock_witness: to_vecs(reader.stack()?),
+        }
+    } else {
+        HeaderExt::Proof {
+            challenge: ScriptBuf::from_bytes(reader.var_bytes()?.to_vec()),
+            solution: ScriptBuf::from_bytes(reader.var_bytes()?.to_vec()),
+        }
+    };
+    if !reader.0.is_empty() {
+        return Err(parse_failed("trailing bytes after elements header"));
+    }
+    Ok(ElementsHeader {
+        version: version & !DYNAFED_VERSION_BIT,
+        prev_blockhash,
+        merkle_root,
+        time,
+        height,
+        ext,
+    })
+}
+
+fn to_vecs(stack: Vec<&[u8]>) -> Vec<Vec<u8>> {
+    stack.into_iter().map(<[u8]>::to_vec).collect()
+}
+
+fn write_var_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend(serialize(&VarInt(bytes.len() as u64)));
+    out.extend(bytes);
+}
+
+fn write_stack(out: &mut Vec<u8>, stack: &[Vec<u8>]) {
+    out.extend(serialize(&VarInt(stack.len() as u64)));
+    for item in stack {
+        write_var_bytes(out, item);
+    }
+}
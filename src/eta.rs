@@ -0,0 +1,69 @@
+//! Confirmation time estimates from the mempool fee histogram.
+//!
+//! [`crate::BlockingClient::estimate_confirmation_eta`] and its async counterpart combine the
+//! fee histogram of the Esplora `/mempool` endpoint with the mean interval of the last
+//! [`ETA_INTERVAL_BLOCKS`] blocks, for wallets showing e.g. "~30 min" next to each fee choice.
+//!
+//! The estimate assumes the transactions paying more are mined first, [`BLOCK_VSIZE`] per block,
+//! and ignores the transactions arriving later: it's optimistic when the mempool is growing.
+//! Blocks arrive at random, so the time range is one standard deviation around the expected
+//! time.
+
+use std::time::Duration;
+
+/// Virtual size of the transactions mined in a full block
+pub const BLOCK_VSIZE: u64 = 1_000_000;
+
+/// Number of recent blocks whose mean interval is used by
+/// [`crate::BlockingClient::estimate_confirmation_eta`]
+pub const ETA_INTERVAL_BLOCKS: u32 = 6;
+
+/// Block interval used when the recent blocks don't give one
+pub const DEFAULT_BLOCK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// When a transaction is expected to confirm, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmationEta {
+    /// The number of blocks until the one confirming the transaction, included
+    pub blocks: u32,
+    /// The expected time until the confirmation
+    pub expected: Duration,
+    /// The earliest time of the range
+    pub earliest: Duration,
+    /// The latest time of the range
+    pub latest: Duration,
+}
+
+/// Estimate when a transaction paying `fee_rate` sat/vB confirms, given a mempool
+/// `histogram` of `(sat/vB, vsize)` buckets and the mean `block_interval`
+pub fn estimate_eta(
+    histogram: &[(f64, u64)],
+    fee_rate: f64,
+    block_interval: Duration,
+) -> ConfirmationEta {
+    let ahead: u64 = histogram
+        .iter()
+        .filter(|(rate, _)| *rate > fee_rate)
+        .map(|(_, vsize)| *vsize)
+        .sum();
+    let blocks = u32::try_from(ahead / BLOCK_VSIZE)
+        .unwrap_or(u32::MAX)
+        .saturating_add(1);
+    let n = f64::from(blocks);
+    let interval = block_interval.as_secs_f64();
+    ConfirmationEta {
+        blocks,
+        expected: Duration::from_secs_f64(n * interval),
+        earliest: Duration::from_secs_f64((n - n.sqrt()) * interval),
+        latest: Duration::from_secs_f64((n + n.sqrt()) * interval),
+    }
+}
+
+/// The mean interval between consecutive blocks with timestamps `times`, oldest first,
+/// `None` with fewer than two blocks or if the last is not later than the first
+pub fn mean_block_interval(times: &[u32]) -> Option<Duration> {
+    let (first, last) = (times.first()?, times.last()?);
+    let span = last.checked_sub(*first).filter(|span| *span > 0)?;
+    let intervals = u32::try_from(times.len() - 1).ok()?;
+    Some(Duration::from_secs(u64::from(span)) / intervals)
+}
@@ -0,0 +1,58 @@
+//! Capture of sanitized fixtures from a live server, with the `test-utils` feature.
+//!
+//! A [`CapturedScan`] holds a real scan response whose descriptor or addresses are replaced by
+//! [`REDACTED`], so it can be committed as test data for mocks and replay backends and
+//! refreshed from the server when its output changes.
+
+use bitcoin::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::dry_run::REDACTED;
+use crate::{Keychain, WaterfallResponse};
+
+/// A sanitized scan of the waterfalls endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CapturedScan {
+    /// The query parameters, with values replaced by [`REDACTED`]
+    pub query: String,
+    /// The response, with the descriptor keys replaced by [`REDACTED`]
+    pub response: WaterfallResponse,
+}
+
+impl CapturedScan {
+    /// Sanitize the `response` of a descriptor scan
+    pub fn from_descriptor(response: WaterfallResponse) -> Self {
+        CapturedScan {
+            query: format!("descriptor={REDACTED}"),
+            response: sanitize(response),
+        }
+    }
+
+    /// Sanitize the `response` of a scan of `addresses`, keeping their number
+    pub fn from_addresses(addresses: &[Address], response: WaterfallResponse) -> Self {
+        let redacted = vec![REDACTED; addresses.len()];
+        CapturedScan {
+            query: format!("addresses={}", redacted.join(",")),
+            response: sanitize(response),
+        }
+    }
+}
+
+/// Replace the descriptors in the keys of `response`, keeping the keychain of each branch
+fn sanitize(mut response: WaterfallResponse) -> WaterfallResponse {
+    let txs_seen = std::mem::take(&mut response.txs_seen);
+    response.txs_seen = txs_seen
+        .into_iter()
+        .enumerate()
+        .map(|(i, (key, scripts))| {
+            let key = match Keychain::from_key(&key) {
+                _ if key == "addresses" => key,
+                Some(Keychain::External) => format!("{REDACTED}/0/*"),
+                Some(Keychain::Internal) => format!("{REDACTED}/1/*"),
+                None => format!("{REDACTED}#{i}"),
+            };
+            (key, scripts)
+        })
+        .collect();
+    response
+}
@@ -0,0 +1,476 @@
+//! Block header chain validation anchored at checkpoints.
+//!
+//! A [`HeaderChain`] starts from a trusted [`Checkpoint`] instead of genesis and validates every
+//! header connected on top of it, so SPV-style verification can begin close to the heights a
+//! wallet cares about.
+//!
+//! The difficulty of a header depends on the headers before it, so a chain anchored above
+//! genesis must be seeded with the headers from the start of the difficulty period of its
+//! anchor, see [`HeaderChain::seed`]. They are trusted only if they link up to the checkpoint
+//! hash. Headers whose difficulty can't be checked are rejected.
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::blockdata::constants::genesis_block;
+use bitcoin::hashes::Hash;
+use bitcoin::{block::Header as BlockHeader, merkle_tree, BlockHash, CompactTarget, Network};
+use bitcoin::{TxMerkleNode, Txid, Work};
+
+/// Checkpoints shipped with Bitcoin Core for mainnet.
+const MAINNET_CHECKPOINTS: [(u32, &str); 13] = [
+    (
+        11111,
+        "0000000069e244f73d78e8fd29ba2fd2ed618bd6fa2ee92559f542fdb26e7c1d",
+    ),
+    (
+        33333,
+        "000000002dd5588a74784eaa7ab0507a18ad16a236e7b1ce69f00d7ddfb5d0a6",
+    ),
+    (
+        74000,
+        "0000000000573993a3c9e41ce34471c079dcf5f52a0e824a81e7f953b8661a20",
+    ),
+    (
+        105000,
+        "00000000000291ce28027faea320c8d2b054b2e0fe44a773f3eefb151d6bdc97",
+    ),
+    (
+        134444,
+        "00000000000005b12ffd4cd315cd34ffd4a594f430ac814c91184a0d42d2b0fe",
+    ),
+    (
+        168000,
+        "000000000000099e61ea72015e79632f216fe6cb33d7899acb35b75c8303b763",
+    ),
+    (
+        193000,
+        "000000000000059f452a5f7340de6682a977387c17010ff6e6c3bd83ca8b1317",
+    ),
+    (
+        210000,
+        "000000000000048b95347e83192f69cf0366076336c639f9b7228e9ba171342e",
+    ),
+    (
+        216116,
+        "00000000000001b4f4b433e81ee46494af945cf96014816a4e2370f11b23df4e",
+    ),
+    (
+        225430,
+        "00000000000001c108384350f74090433e7fcf79a606b8e797f065b130575932",
+    ),
+    (
+        250000,
+        "000000000000003887df1f29024b06fc2200b55f8af8f35453d7be294df2d214",
+    ),
+    (
+        279000,
+        "0000000000000001ae8c72a0b0c301f67e3afca10e819efa9041e458e9bd7e40",
+    ),
+    (
+        295000,
+        "00000000000000004d9b4ef50f0f9d686fd69db2e03af35a100370c64632a983",
+    ),
+];
+
+/// A block known to be in the best chain, used as a trust anchor by [`HeaderChain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Checkpoint {
+    /// The block height
+    pub height: u32,
+    /// The block hash
+    pub hash: BlockHash,
+}
+
+impl Checkpoint {
+    /// Create a checkpoint
+    pub fn new(height: u32, hash: BlockHash) -> Self {
+        Checkpoint { height, hash }
+    }
+
+    /// The genesis block of `network`
+    pub fn genesis(network: Network) -> Self {
+        Checkpoint::new(0, genesis_block(network).block_hash())
+    }
+
+    /// The compiled-in checkpoints for `network`, sorted by height and always starting with
+    /// the genesis block.
+    pub fn defaults(network: Network) -> Vec<Checkpoint> {
+        let mut checkpoints = vec![Checkpoint::genesis(network)];
+        if network == Network::Bitcoin {
+            checkpoints.extend(MAINNET_CHECKPOINTS.iter().map(|(height, hash)| {
+                Checkpoint::new(
+                    *height,
+                    BlockHash::from_str(hash).expect("static checkpoint hash"),
+                )
+            }));
+        }
+        checkpoints
+    }
+}
+
+/// Reasons a header is rejected by a [`HeaderChain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderValidationError {
+    /// The header doesn't build on the chain tip
+    Disconnected {
+        height: u32,
+        expected_prev: BlockHash,
+        actual_prev: BlockHash,
+    },
+    /// The header doesn't match the checkpoint at its height
+    CheckpointMismatch {
+        height: u32,
+        expected: BlockHash,
+        actual: BlockHash,
+    },
+    /// The header target is easier than the network proof of work limit
+    TargetAboveLimit { height: u32 },
+    /// The header hash doesn't satisfy its target
+    BadProofOfWork { height: u32 },
+    /// The header `bits` can't be checked, the chain isn't seeded with the headers below its
+    /// anchor, see [`HeaderChain::seed`]
+    UnknownDifficulty { height: u32 },
+    /// The header `bits` don't follow the network difficulty adjustment rules
+    BadDifficulty {
+        height: u32,
+        expected: CompactTarget,
+        actual: CompactTarget,
+    },
+    /// The txids of the block don't hash to the merkle root of its header, `actual` is `None`
+    /// if there are no txids
+    MerkleRootMismatch {
+        block_hash: BlockHash,
+        expected: TxMerkleNode,
+        actual: Option<TxMerkleNode>,
+    },
+}
+
+impl fmt::Display for HeaderValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for HeaderValidationError {}
+
+/// Check that `txids`, in block order, hash to the merkle root of `header`.
+///
+/// Catches servers returning the txids of another block, or missing or reordered ones.
+pub fn verify_block_txids(
+    header: &BlockHeader,
+    txids: &[Txid],
+) -> Result<(), HeaderValidationError> {
+    let hashes = txids
+        .iter()
+        .map(|txid| TxMerkleNode::from_raw_hash(txid.to_raw_hash()));
+    let actual = merkle_tree::calculate_root(hashes);
+    if actual == Some(header.merkle_root) {
+        return Ok(());
+    }
+    Err(HeaderValidationError::MerkleRootMismatch {
+        block_hash: header.block_hash(),
+        expected: header.merkle_root,
+        actual,
+    })
+}
+
+/// The tip of a [`HeaderChain`] with the work accumulated on top of its anchor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainTip {
+    /// The tip height
+    pub height: u32,
+    /// The tip hash
+    pub hash: BlockHash,
+    /// Work of the headers above the common anchor height of the compared chains
+    pub work: Work,
+}
+
+/// Different tips seen by chains compared with [`HeaderChain::heaviest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainSplit {
+    /// The tip of every compared chain, in the order they were given
+    pub tips: Vec<ChainTip>,
+    /// Index in `tips` of the chain with the most work
+    pub heaviest: usize,
+}
+
+/// A chain of validated block headers built on top of a [`Checkpoint`].
+#[derive(Debug, Clone)]
+pub struct HeaderChain {
+    network: Network,
+    /// Checkpoints sorted by height
+    checkpoints: Vec<Checkpoint>,
+    /// Headers at or below this height are only checked for linkage and checkpoints
+    assume_valid_height: Option<u32>,
+    anchor: Checkpoint,
+    /// Headers up to the anchor, the last one at height `anchor.height`
+    seed: Vec<BlockHeader>,
+    /// `headers[i]` is at height `anchor.height + 1 + i`
+    headers: Vec<BlockHeader>,
+}
+
+impl HeaderChain {
+    /// Start a chain from the highest of `checkpoints` at or below `start_height`.
+    ///
+    /// The genesis block of `network` is always considered a checkpoint, so an empty list
+    /// starts the chain from genesis.
+    pub fn new(network: Network, checkpoints: Vec<Checkpoint>, start_height: u32) -> Self {
+        let mut checkpoints = checkpoints;
+        if !checkpoints.iter().any(|c| c.height == 0) {
+            checkpoints.push(Checkpoint::genesis(network));
+        }
+        checkpoints.sort_by_key(|c| c.height);
+        checkpoints.dedup_by_key(|c| c.height);
+        let anchor = *checkpoints
+            .iter()
+            .rev()
+            .find(|c| c.height <= start_height)
+            .expect("genesis checkpoint is always present");
+        HeaderChain {
+            network,
+            checkpoints,
+            assume_valid_height: None,
+            anchor,
+            seed: vec![],
+            headers: vec![],
+        }
+    }
+
+    /// Skip proof of work checks for headers at or below `height`, only checking that they
+    /// connect and match the checkpoints.
+    pub fn with_assume_valid_height(mut self, height: Option<u32>) -> Self {
+        self.assume_valid_height = height;
+        self
+    }
+
+    /// Height of the first header needed by [`Self::seed`]: the start of the difficulty
+    /// period of the anchor
+    pub fn seed_height(&self) -> u32 {
+        let interval = self.network.params().difficulty_adjustment_interval() as u32;
+        self.anchor.height - self.anchor.height % interval
+    }
+
+    /// Returns true if headers up to `to_height` can be connected without seeding the chain
+    /// first: it's anchored at genesis, already seeded, or the headers are assumed valid
+    pub fn is_seeded(&self, to_height: u32) -> bool {
+        self.anchor.height == 0
+            || self.seed.len() as u32 > self.anchor.height - self.seed_height()
+            || self.assume_valid_height.map_or(false, |h| to_height <= h)
+    }
+
+    /// Seed the chain with `headers`, the headers from [`Self::seed_height`] up to the anchor
+    /// in ascending order, so that the difficulty of the following headers can be checked.
+    ///
+    /// The headers are trusted only if each one links to the next and the last one hashes to
+    /// the anchor checkpoint.
+    pub fn seed(&mut self, headers: Vec<BlockHeader>) -> Result<(), HeaderValidationError> {
+        let start = (self.anchor.height + 1).saturating_sub(headers.len() as u32);
+        for (i, pair) in headers.windows(2).enumerate() {
+            let expected_prev = pair[0].block_hash();
+            if pair[1].prev_blockhash != expected_prev {
+                return Err(HeaderValidationError::Disconnected {
+                    height: start + i as u32 + 1,
+                    expected_prev,
+                    actual_prev: pair[1].prev_blockhash,
+                });
+            }
+        }
+        let actual = headers.last().map(|h| h.block_hash());
+        if actual != Some(self.anchor.hash) {
+            return Err(HeaderValidationError::CheckpointMismatch {
+                height: self.anchor.height,
+                expected: self.anchor.hash,
+                actual: actual.unwrap_or_else(BlockHash::all_zeros),
+            });
+        }
+        self.seed = headers;
+        Ok(())
+    }
+
+    /// The network of this chain
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// The checkpoint this chain is built on
+    pub fn anchor(&self) -> Checkpoint {
+        self.anchor
+    }
+
+    /// Height of the last connected header, or of the anchor if none is connected
+    pub fn tip_height(&self) -> u32 {
+        self.anchor.height + self.headers.len() as u32
+    }
+
+    /// Hash of the last connected header, or of the anchor if none is connected
+    pub fn tip_hash(&self) -> BlockHash {
+        self.headers
+            .last()
+            .map(|h| h.block_hash())
+            .unwrap_or(self.anchor.hash)
+    }
+
+    /// The connected header at `height`, if any
+    pub fn header_at_height(&self, height: u32) -> Option<BlockHeader> {
+        let index = height.checked_sub(self.anchor.height + 1)?;
+        self.headers.get(index as usize).copied()
+    }
+
+    /// The connected headers with their heights, in ascending order
+    pub fn headers(&self) -> impl Iterator<Item = (u32, &BlockHeader)> + '_ {
+        let start = self.anchor.height + 1;
+        self.headers
+            .iter()
+            .enumerate()
+            .map(move |(i, h)| (start + i as u32, h))
+    }
+
+    /// Cumulative work of the connected headers above `height`
+    pub fn work_above(&self, height: u32) -> Work {
+        self.headers()
+            .filter(|(h, _)| *h > height)
+            .fold(Work::from_be_bytes([0; 32]), |work, (_, header)| {
+                work + header.work()
+            })
+    }
+
+    /// Cumulative work of the connected headers above the anchor
+    pub fn work(&self) -> Work {
+        self.work_above(self.anchor.height)
+    }
+
+    /// Index of the chain with the most cumulative work among `chains`, and the competing tips
+    /// if not every chain ends at the same block.
+    ///
+    /// Work is compared above the highest anchor among `chains`, which are expected to agree on
+    /// the block at that height, as when built from the same checkpoints. Ties are won by the
+    /// first chain. Returns `None` if `chains` is empty.
+    pub fn heaviest(chains: &[&HeaderChain]) -> Option<(usize, Option<ChainSplit>)> {
+        let common = chains.iter().map(|c| c.anchor.height).max()?;
+        let tips: Vec<ChainTip> = chains
+            .iter()
+            .map(|c| ChainTip {
+                height: c.tip_height(),
+                hash: c.tip_hash(),
+                work: c.work_above(common),
+            })
+            .collect();
+        let mut heaviest = 0;
+        for (i, tip) in tips.iter().enumerate() {
+            if tip.work > tips[heaviest].work {
+                heaviest = i;
+            }
+        }
+        let split = if tips.iter().all(|t| t.hash == tips[0].hash) {
+            None
+        } else {
+            Some(ChainSplit { tips, heaviest })
+        };
+        Some((heaviest, split))
+    }
+
+    /// A known header at `height`: the genesis block, a seeded or a connected header
+    fn known_header(&self, height: u32) -> Option<BlockHeader> {
+        if height == 0 {
+            return Some(genesis_block(self.network).header);
+        }
+        if height <= self.anchor.height {
+            let first = (self.anchor.height + 1).saturating_sub(self.seed.len() as u32);
+            return self.seed.get(height.checked_sub(first)? as usize).copied();
+        }
+        self.header_at_height(height)
+    }
+
+    /// The `bits` required for `header` to be connected on top of the chain tip.
+    ///
+    /// Returns `None` when the headers needed to compute them aren't known, for example for the
+    /// first blocks after a checkpoint the chain isn't seeded below, see [`Self::seed`].
+    pub fn required_bits(&self, header: &BlockHeader) -> Option<CompactTarget> {
+        let params = self.network.params();
+        let height = self.tip_height() + 1;
+        let prev_height = height - 1;
+        let prev = self.known_header(prev_height)?;
+        let interval = params.difficulty_adjustment_interval() as u32;
+
+        if height % interval != 0 {
+            if !params.allow_min_difficulty_blocks {
+                return Some(prev.bits);
+            }
+            let pow_limit = params.max_attainable_target.to_compact_lossy();
+            // A block more than twice the target spacing after the previous one may use the
+            // minimum difficulty
+            if u64::from(header.time) > u64::from(prev.time) + params.pow_target_spacing * 2 {
+                return Some(pow_limit);
+            }
+            // Otherwise use the difficulty of the last block not mined at minimum difficulty
+            let mut last = prev;
+            let mut last_height = prev_height;
+            while last_height > 0 && last_height % interval != 0 && last.bits == pow_limit {
+                last_height -= 1;
+                last = self.known_header(last_height)?;
+            }
+            return Some(last.bits);
+        }
+
+        let first = self.known_header(height - interval)?;
+        let timespan = u64::from(prev.time.saturating_sub(first.time));
+        // BIP94: testnet4 retargets from the first block of the period, so that blocks at
+        // minimum difficulty at the end of a period don't carry over
+        let last_bits = if self.network == Network::Testnet4 {
+            first.bits
+        } else {
+            prev.bits
+        };
+        Some(CompactTarget::from_next_work_required(
+            last_bits, timespan, params,
+        ))
+    }
+
+    /// Validate `header` and append it on top of the chain tip.
+    pub fn connect(&mut self, header: BlockHeader) -> Result<(), HeaderValidationError> {
+        let height = self.tip_height() + 1;
+        let expected_prev = self.tip_hash();
+        if header.prev_blockhash != expected_prev {
+            return Err(HeaderValidationError::Disconnected {
+                height,
+                expected_prev,
+                actual_prev: header.prev_blockhash,
+            });
+        }
+
+        let hash = header.block_hash();
+        if let Some(checkpoint) = self.checkpoints.iter().find(|c| c.height == height) {
+            if checkpoint.hash != hash {
+                return Err(HeaderValidationError::CheckpointMismatch {
+                    height,
+                    expected: checkpoint.hash,
+                    actual: hash,
+                });
+            }
+        }
+
+        if self.assume_valid_height.map_or(true, |h| height > h) {
+            let target = header.target();
+            if target > self.network.params().max_attainable_target {
+                return Err(HeaderValidationError::TargetAboveLimit { height });
+            }
+            let expected = self
+                .required_bits(&header)
+                .ok_or(HeaderValidationError::UnknownDifficulty { height })?;
+            if header.bits != expected {
+                return Err(HeaderValidationError::BadDifficulty {
+                    height,
+                    expected,
+                    actual: header.bits,
+                });
+            }
+            if !target.is_met_by(hash) {
+                return Err(HeaderValidationError::BadProofOfWork { height });
+            }
+        }
+
+        self.headers.push(header);
+        Ok(())
+    }
+}
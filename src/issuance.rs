@@ -0,0 +1,124 @@
+//! Asset issuances and reissuances of Liquid transactions.
+//!
+//! [`find_issuances`] reads a raw Elements transaction, as returned by the `/tx/:txid/raw`
+//! endpoint of a Liquid server, and returns an [`Issuance`] for every input creating units of
+//! an asset, with the ids of the asset and of its reissuance token computed as Elements does.
+//!
+//! [`crate::BlockingClient::find_issuances`] and its async counterpart run it on every
+//! transaction of a scan.
+
+use std::fmt;
+
+use bitcoin::consensus::serialize;
+use bitcoin::hashes::{sha256, sha256d, Hash, HashEngine};
+use bitcoin::OutPoint;
+
+use crate::elements::{parse_tx, Value};
+use crate::Error;
+
+/// The id of a Liquid asset, displayed in reversed byte order like the Elements tools do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AssetId(pub [u8; 32]);
+
+impl AssetId {
+    /// The id of the asset issued with `entropy`
+    pub fn from_entropy(entropy: [u8; 32]) -> Self {
+        AssetId(midstate(&entropy, &[0; 32]))
+    }
+
+    /// The id of the reissuance token of the asset issued with `entropy`, which depends on
+    /// whether the issued amount was blinded
+    pub fn reissuance_token_from_entropy(entropy: [u8; 32], confidential: bool) -> Self {
+        let mut second = [0u8; 32];
+        second[0] = if confidential { 2 } else { 1 };
+        AssetId(midstate(&entropy, &second))
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().rev().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+/// Whether an [`Issuance`] creates a new asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssuanceKind {
+    /// The first issuance, creating the asset
+    Issuance,
+    /// More units of an existing asset, authorized by spending its reissuance token
+    Reissuance,
+}
+
+/// An input of a Liquid transaction issuing an asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issuance {
+    /// The index of the input
+    pub input: u32,
+    /// The output spent by the input
+    pub previous_output: OutPoint,
+    /// Issuance or reissuance
+    pub kind: IssuanceKind,
+    /// The entropy of the asset, from which its id is computed
+    pub entropy: [u8; 32],
+    /// The issued asset
+    pub asset: AssetId,
+    /// The reissuance token created with the asset, `None` for reissuances
+    pub token: Option<AssetId>,
+    /// The issued amount of the asset, `None` if blinded
+    pub amount: Option<u64>,
+    /// The issued amount of the reissuance token, `None` if blinded
+    pub inflation_keys: Option<u64>,
+}
+
+/// The issuances of the raw Elements transaction `raw`, see the [module documentation](self)
+pub fn find_issuances(raw: &[u8]) -> Result<Vec<Issuance>, Error> {
+    let tx = parse_tx(raw)?;
+    let mut issuances = vec![];
+    for (input, txin) in tx.inputs.iter().enumerate() {
+        let issuance = match &txin.issuance {
+            Some(issuance) => issuance,
+            None => continue,
+        };
+        let (kind, entropy, token) = if issuance.blinding_nonce == [0; 32] {
+            let entropy = issuance_entropy(&txin.previous_output, &issuance.asset_entropy);
+            let confidential = issuance.amount == Value::Confidential;
+            let token = AssetId::reissuance_token_from_entropy(entropy, confidential);
+            (IssuanceKind::Issuance, entropy, Some(token))
+        } else {
+            (IssuanceKind::Reissuance, issuance.asset_entropy, None)
+        };
+        issuances.push(Issuance {
+            input: input as u32,
+            previous_output: txin.previous_output,
+            kind,
+            entropy,
+            asset: AssetId::from_entropy(entropy),
+            token,
+            amount: explicit_or_zero(issuance.amount),
+            inflation_keys: explicit_or_zero(issuance.inflation_keys),
+        });
+    }
+    Ok(issuances)
+}
+
+/// The entropy of an asset issued by the input spending `prevout` with `contract_hash`
+fn issuance_entropy(prevout: &OutPoint, contract_hash: &[u8; 32]) -> [u8; 32] {
+    let prevout_hash = sha256d::Hash::hash(&serialize(prevout));
+    midstate(prevout_hash.as_byte_array(), contract_hash)
+}
+
+/// The SHA256 midstate of `left` followed by `right`, without padding
+fn midstate(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut engine = sha256::HashEngine::default();
+    engine.input(left);
+    engine.input(right);
+    engine.midstate().to_byte_array()
+}
+
+fn explicit_or_zero(value: Value) -> Option<u64> {
+    match value {
+        Value::Null => Some(0),
+        value => value.explicit(),
+    }
+}
@@ -0,0 +1,378 @@
+//! User labels of transactions, addresses and outputs.
+//!
+//! A [`Labels`] store keeps the labels a wallet attaches to what its scans discover, optionally
+//! in a file rewritten after every change like [`crate::BroadcastQueue`]. Labels are stored and
+//! exchanged in the [BIP329](https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki)
+//! format, one JSON object per line, so they can be moved to and from other wallets.
+//!
+//! [`Labels::import_bip329`] and [`Labels::export_bip329`] restrict the exchanged labels to a
+//! [`LabelScope`] built from the scan results of a wallet, so labels of unrelated transactions
+//! or addresses are neither imported nor leaked to another wallet.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bitcoin::{Address, OutPoint, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Utxo, WaterfallResponse};
+
+/// The kind of object a [`Label`] refers to, as defined by BIP329.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+    /// A transaction, referenced by txid
+    Tx,
+    /// An address
+    Addr,
+    /// A public key, hex encoded
+    Pubkey,
+    /// A transaction input, referenced by the outpoint it spends
+    Input,
+    /// A transaction output, referenced by outpoint
+    Output,
+    /// An extended public key
+    Xpub,
+}
+
+/// A BIP329 label record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label {
+    /// The kind of the referenced object
+    #[serde(rename = "type")]
+    pub label_type: LabelType,
+    /// The referenced object, e.g. the txid of a [`LabelType::Tx`] label
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// The label text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The descriptor the object belongs to, without checksum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Whether an output may be spent, for [`LabelType::Output`] labels only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable: Option<bool>,
+}
+
+impl Label {
+    /// A label with text `label` for the object of `label_type` referenced by `reference`
+    pub fn new(
+        label_type: LabelType,
+        reference: impl Into<String>,
+        label: impl Into<String>,
+    ) -> Self {
+        Label {
+            label_type,
+            reference: reference.into(),
+            label: Some(label.into()),
+            origin: None,
+            spendable: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LabelsInner {
+    labels: BTreeMap<(LabelType, String), Label>,
+    path: Option<PathBuf>,
+}
+
+/// Labels attached to wallet objects, see the [module documentation](self).
+///
+/// Cloning a [`Labels`] returns a handle to the same store.
+#[derive(Debug, Clone, Default)]
+pub struct Labels {
+    inner: Arc<Mutex<LabelsInner>>,
+}
+
+impl Labels {
+    /// Create an empty store kept in memory only
+    pub fn new() -> Self {
+        Labels::default()
+    }
+
+    /// Open the store persisted at `path`, creating it if the file doesn't exist.
+    ///
+    /// The file is rewritten after every change of the store.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let labels = Labels::new();
+        if path.exists() {
+            labels.read_from(BufReader::new(File::open(&path)?))?;
+        }
+        labels.lock().path = Some(path);
+        Ok(labels)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LabelsInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Store `label`, returning the label it replaces for the same object
+    pub fn set(&self, label: Label) -> Result<Option<Label>, Error> {
+        let mut inner = self.lock();
+        let key = (label.label_type, label.reference.clone());
+        let replaced = inner.labels.insert(key, label);
+        persist(&inner)?;
+        Ok(replaced)
+    }
+
+    /// The label of the object of `label_type` referenced by `reference`
+    pub fn get(&self, label_type: LabelType, reference: &str) -> Option<Label> {
+        self.lock()
+            .labels
+            .get(&(label_type, reference.to_string()))
+            .cloned()
+    }
+
+    /// Remove the label of the object of `label_type` referenced by `reference`
+    pub fn remove(&self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+        let mut inner = self.lock();
+        let removed = inner.labels.remove(&(label_type, reference.to_string()));
+        if removed.is_some() {
+            persist(&inner)?;
+        }
+        Ok(removed)
+    }
+
+    /// Label the transaction `txid`
+    pub fn label_tx(&self, txid: Txid, label: &str) -> Result<Option<Label>, Error> {
+        self.set(Label::new(LabelType::Tx, txid.to_string(), label))
+    }
+
+    /// Label `address`
+    pub fn label_address(&self, address: &Address, label: &str) -> Result<Option<Label>, Error> {
+        self.set(Label::new(LabelType::Addr, address.to_string(), label))
+    }
+
+    /// Label the output `outpoint`, keeping its spendable flag
+    pub fn label_output(&self, outpoint: OutPoint, label: &str) -> Result<Option<Label>, Error> {
+        let mut new = Label::new(LabelType::Output, outpoint.to_string(), label);
+        new.spendable = self
+            .get(LabelType::Output, &new.reference)
+            .and_then(|old| old.spendable);
+        self.set(new)
+    }
+
+    /// Mark the output `outpoint` as spendable or frozen, keeping its label
+    pub fn set_spendable(&self, outpoint: OutPoint, spendable: bool) -> Result<(), Error> {
+        let reference = outpoint.to_string();
+        let label = match self.get(LabelType::Output, &reference) {
+            Some(label) => Label {
+                spendable: Some(spendable),
+                ..label
+            },
+            None => Label {
+                label_type: LabelType::Output,
+                reference,
+                label: None,
+                origin: None,
+                spendable: Some(spendable),
+            },
+        };
+        self.set(label)?;
+        Ok(())
+    }
+
+    /// The label text of the transaction `txid`
+    pub fn tx_label(&self, txid: &Txid) -> Option<String> {
+        self.text(LabelType::Tx, &txid.to_string())
+    }
+
+    /// The label text of `address`
+    pub fn address_label(&self, address: &Address) -> Option<String> {
+        self.text(LabelType::Addr, &address.to_string())
+    }
+
+    /// The label text of the output `outpoint`
+    pub fn output_label(&self, outpoint: &OutPoint) -> Option<String> {
+        self.text(LabelType::Output, &outpoint.to_string())
+    }
+
+    /// The label text of `utxo`, falling back to the label of the transaction creating it
+    pub fn utxo_label(&self, utxo: &Utxo) -> Option<String> {
+        self.output_label(&utxo.outpoint)
+            .or_else(|| self.tx_label(&utxo.outpoint.txid))
+    }
+
+    /// Returns false if the output `outpoint` is marked as not spendable
+    pub fn is_spendable(&self, outpoint: &OutPoint) -> bool {
+        self.get(LabelType::Output, &outpoint.to_string())
+            .and_then(|label| label.spendable)
+            .unwrap_or(true)
+    }
+
+    fn text(&self, label_type: LabelType, reference: &str) -> Option<String> {
+        self.get(label_type, reference)
+            .and_then(|label| label.label)
+    }
+
+    /// Every label, ordered by type and reference
+    pub fn labels(&self) -> Vec<Label> {
+        self.lock().labels.values().cloned().collect()
+    }
+
+    /// Number of labels
+    pub fn len(&self) -> usize {
+        self.lock().labels.len()
+    }
+
+    /// Returns true if there are no labels
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the labels in the BIP329 format, one JSON object per line
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        write_labels(self.lock().labels.values(), writer)
+    }
+
+    /// Store the labels read from `reader` in the BIP329 format, returning the number of labels
+    /// read
+    pub fn read_from<R: BufRead>(&self, reader: R) -> Result<usize, Error> {
+        let mut count = 0;
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.set(parse_label(line)?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// The transactions and addresses of a wallet history, which labels may refer to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelScope {
+    txids: BTreeSet<Txid>,
+    addresses: BTreeSet<String>,
+}
+
+impl LabelScope {
+    /// The scope of the transactions seen in `response`
+    pub fn new(response: &WaterfallResponse) -> Self {
+        LabelScope {
+            txids: response.txids().into_iter().collect(),
+            addresses: BTreeSet::new(),
+        }
+    }
+
+    /// Add the transactions seen in `response`, e.g. another page of the scan
+    pub fn with_response(mut self, response: &WaterfallResponse) -> Self {
+        self.txids.extend(response.txids());
+        self
+    }
+
+    /// Add the addresses of the wallet, e.g. derived with `derive_addresses` or queried with
+    /// [`crate::WaterfallsQuery::with_addresses`]
+    pub fn with_addresses<'a>(mut self, addresses: impl IntoIterator<Item = &'a Address>) -> Self {
+        self.addresses
+            .extend(addresses.into_iter().map(ToString::to_string));
+        self
+    }
+
+    /// Returns true if `label` refers to an object of the wallet history.
+    ///
+    /// Outputs and inputs are in scope when the transaction creating the output is, public keys
+    /// and extended public keys are always in scope since scans don't return them.
+    pub fn contains(&self, label: &Label) -> bool {
+        match label.label_type {
+            LabelType::Tx => Txid::from_str(&label.reference)
+                .map(|txid| self.txids.contains(&txid))
+                .unwrap_or(false),
+            LabelType::Addr => self.addresses.contains(&label.reference),
+            LabelType::Input | LabelType::Output => OutPoint::from_str(&label.reference)
+                .map(|outpoint| self.txids.contains(&outpoint.txid))
+                .unwrap_or(false),
+            LabelType::Pubkey | LabelType::Xpub => true,
+        }
+    }
+}
+
+/// The result of [`Labels::import_bip329`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Number of labels stored
+    pub imported: usize,
+    /// The labels not stored because they refer to objects outside the [`LabelScope`]
+    pub out_of_scope: Vec<Label>,
+}
+
+impl Labels {
+    /// Store the labels read from the BIP329 `reader` which refer to objects in `scope`.
+    ///
+    /// An invalid line fails the import with [`Error::InvalidLabel`], the labels of the previous
+    /// lines are kept.
+    pub fn import_bip329<R: BufRead>(
+        &self,
+        reader: R,
+        scope: &LabelScope,
+    ) -> Result<ImportReport, Error> {
+        let mut report = ImportReport::default();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let label = parse_label(line)
+                .map_err(|e| Error::InvalidLabel(format!("line {}: {e}", number + 1)))?;
+            if scope.contains(&label) {
+                self.set(label)?;
+                report.imported += 1;
+            } else {
+                report.out_of_scope.push(label);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Write the labels referring to objects in `scope` in the BIP329 format, returning the
+    /// number of labels written
+    pub fn export_bip329<W: Write>(&self, writer: W, scope: &LabelScope) -> Result<usize, Error> {
+        let inner = self.lock();
+        let labels: Vec<_> = inner
+            .labels
+            .values()
+            .filter(|label| scope.contains(label))
+            .collect();
+        write_labels(labels.iter().copied(), writer)?;
+        Ok(labels.len())
+    }
+}
+
+/// Parse a BIP329 line, unknown fields are ignored
+fn parse_label(line: &str) -> Result<Label, Error> {
+    serde_json::from_str(line).map_err(|e| Error::InvalidLabel(e.to_string()))
+}
+
+fn write_labels<'a, W: Write>(
+    labels: impl IntoIterator<Item = &'a Label>,
+    mut writer: W,
+) -> Result<(), Error> {
+    for label in labels {
+        serde_json::to_writer(&mut writer, label)
+            .map_err(|e| Error::InvalidLabel(e.to_string()))?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Rewrite the file of the store, if any, through a temporary file so a crash doesn't lose it
+fn persist(inner: &LabelsInner) -> Result<(), Error> {
+    if let Some(path) = &inner.path {
+        let tmp = path.with_extension("tmp");
+        write_labels(inner.labels.values(), BufWriter::new(File::create(&tmp)?))?;
+        std::fs::rename(&tmp, path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Double-entry ledger view of a wallet history.
+//!
+//! [`WaterfallResponse::ledger`] turns a scan and its [`HydratedTx`] into one [`LedgerEntry`]
+//! per transaction, whose [`Posting`]s balance to zero, for accounting systems:
+//!
+//! - the wallet outputs spent by the transaction are credited to the wallet
+//! - the wallet outputs it creates are debited to the wallet, as change or as received funds
+//!   according to the [`ChangeHeuristic`]
+//! - when the wallet spends, the outputs to others are payments and, if every input is the
+//!   wallet's, the fee is an expense
+//! - the counterparty posting balances the entry, e.g. the sender of received funds
+//!
+//! The wallet inputs and outputs are known from the [`V`] of the entries, as sent by the v4
+//! endpoint.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bitcoin::{Amount, SignedAmount, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::{Height, HydratedTx, Keychain, Timestamp, WaterfallResponse, V};
+
+/// How [`WaterfallResponse::ledger`] tells change from received funds among the wallet outputs
+/// of a transaction spending wallet outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ChangeHeuristic {
+    /// Outputs to the internal keychain are change, the others are received
+    #[default]
+    InternalKeychain,
+    /// Every wallet output is change, e.g. for address scans without keychains
+    AnyOwned,
+    /// No output is change
+    Never,
+}
+
+impl ChangeHeuristic {
+    /// Whether a wallet output to `keychain` of a transaction spending wallet outputs is change
+    pub fn is_change(self, keychain: Option<Keychain>) -> bool {
+        match self {
+            ChangeHeuristic::InternalKeychain => keychain == Some(Keychain::Internal),
+            ChangeHeuristic::AnyOwned => true,
+            ChangeHeuristic::Never => false,
+        }
+    }
+}
+
+/// The account of a [`Posting`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Account {
+    /// The funds of the wallet
+    Wallet,
+    /// The funds of everyone else
+    External,
+    /// The fees paid by the wallet
+    Fees,
+}
+
+/// What a [`Posting`] records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PostingKind {
+    /// Wallet outputs spent by the transaction
+    Spent,
+    /// Wallet outputs created as change
+    Change,
+    /// Wallet outputs created as received funds
+    Received,
+    /// Outputs to others of a transaction spending wallet outputs
+    Payment,
+    /// The fee of a transaction whose inputs are all the wallet's
+    Fee,
+    /// The balance of the postings, funds from or to others
+    Counterparty,
+}
+
+impl PostingKind {
+    /// The account the posting belongs to
+    pub fn account(self) -> Account {
+        match self {
+            PostingKind::Spent | PostingKind::Change | PostingKind::Received => Account::Wallet,
+            PostingKind::Payment | PostingKind::Counterparty => Account::External,
+            PostingKind::Fee => Account::Fees,
+        }
+    }
+}
+
+/// An amount debited, if positive, or credited, if negative, to an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Posting {
+    /// What the posting records
+    pub kind: PostingKind,
+    /// The amount
+    #[serde(with = "bitcoin::amount::serde::as_sat")]
+    pub amount: SignedAmount,
+}
+
+/// The postings of a transaction of the wallet, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// The transaction
+    pub txid: Txid,
+    /// The height of the block confirming the transaction, zero if unconfirmed
+    pub height: Height,
+    /// The timestamp of the block confirming the transaction, if known
+    pub block_timestamp: Option<Timestamp>,
+    /// The non zero postings, summing to zero
+    pub postings: Vec<Posting>,
+}
+
+/// The wallet inputs and outputs of a transaction
+struct Owned {
+    height: Height,
+    block_timestamp: Option<Timestamp>,
+    inputs: BTreeSet<u32>,
+    outputs: BTreeMap<u32, Option<Keychain>>,
+}
+
+impl WaterfallResponse {
+    /// The [`LedgerEntry`] of every transaction in `txs`, e.g. the items of
+    /// `hydrate_with_prevouts`, confirmed ones first by height, telling change with `change`
+    pub fn ledger(
+        &self,
+        txs: &BTreeMap<Txid, HydratedTx>,
+        change: ChangeHeuristic,
+    ) -> Vec<LedgerEntry> {
+        let mut order = vec![];
+        let mut owned: BTreeMap<Txid, Owned> = BTreeMap::new();
+        for (key, scripts) in &self.txs_seen {
+            let keychain = Keychain::from_key(key);
+            for entry in scripts.iter().flatten() {
+                let tx = owned.entry(entry.txid).or_insert_with(|| {
+                    order.push(entry.txid);
+                    Owned {
+                        height: entry.height,
+                        block_timestamp: entry.block_timestamp,
+                        inputs: BTreeSet::new(),
+                        outputs: BTreeMap::new(),
+                    }
+                });
+                match entry.v {
+                    V::Vin(vin) => {
+                        tx.inputs.insert(vin);
+                    }
+                    V::Vout(vout) => {
+                        tx.outputs.insert(vout, keychain);
+                    }
+                    V::Undefined => {}
+                }
+            }
+        }
+
+        let mut entries: Vec<LedgerEntry> = order
+            .into_iter()
+            .filter_map(|txid| {
+                let hydrated = txs.get(&txid)?;
+                let owned = owned.remove(&txid)?;
+                Some(LedgerEntry {
+                    txid,
+                    height: owned.height,
+                    block_timestamp: owned.block_timestamp,
+                    postings: postings(hydrated, &owned, change),
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.height == Height::ZERO, entry.height));
+        entries
+    }
+}
+
+fn postings(hydrated: &HydratedTx, owned: &Owned, change: ChangeHeuristic) -> Vec<Posting> {
+    let spends = !owned.inputs.is_empty();
+    let spent: Amount = owned
+        .inputs
+        .iter()
+        .filter_map(|vin| hydrated.prevouts.get(*vin as usize)?.as_ref())
+        .map(|prevout| prevout.value)
+        .sum();
+    let (mut changed, mut received, mut paid) = (Amount::ZERO, Amount::ZERO, Amount::ZERO);
+    for (vout, output) in hydrated.tx.output.iter().enumerate() {
+        match owned.outputs.get(&(vout as u32)) {
+            Some(keychain) if spends && change.is_change(*keychain) => changed += output.value,
+            Some(_) => received += output.value,
+            None if spends => paid += output.value,
+            None => {}
+        }
+    }
+    let fee = match owned.inputs.len() == hydrated.tx.input.len() {
+        true => hydrated.fee().unwrap_or(Amount::ZERO),
+        false => Amount::ZERO,
+    };
+
+    let mut postings = vec![
+        (PostingKind::Spent, -signed(spent)),
+        (PostingKind::Change, signed(changed)),
+        (PostingKind::Received, signed(received)),
+        (PostingKind::Payment, signed(paid)),
+        (PostingKind::Fee, signed(fee)),
+    ];
+    let balance: SignedAmount = postings.iter().map(|(_, amount)| *amount).sum();
+    postings.push((PostingKind::Counterparty, -balance));
+    postings
+        .into_iter()
+        .filter(|(_, amount)| *amount != SignedAmount::ZERO)
+        .map(|(kind, amount)| Posting { kind, amount })
+        .collect()
+}
+
+fn signed(amount: Amount) -> SignedAmount {
+    amount.to_signed().unwrap_or(SignedAmount::MAX)
+}
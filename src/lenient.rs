@@ -0,0 +1,234 @@
+//! Lenient decoding of raw transactions.
+//!
+//! [`bitcoin::consensus::deserialize`] rejects some transactions found on chain or in the
+//! wild: a segwit flag without any witness, varints not in their shortest form, trailing bytes,
+//! witnesses over the `rust-bitcoin` allocation limit. [`decode_lenient`] reads every field it
+//! can and returns a [`PartialTx`], listing the [`Anomaly`]s met on the way and, if the decoding
+//! stopped early, where and why. It's meant to inspect unusual transactions, not to validate
+//! them.
+
+use bitcoin::blockdata::transaction::Version;
+use bitcoin::hashes::Hash;
+use bitcoin::{
+    absolute, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+/// Size above which `rust-bitcoin` refuses to decode a vector, such as a witness
+pub const MAX_DECODED_VEC_SIZE: usize = 4_000_000;
+
+/// Something unusual in a transaction which [`decode_lenient`] accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anomaly {
+    /// The segwit flag is set but every witness is empty
+    EmptyWitnesses,
+    /// The segwit flag has a value other than 1
+    UnknownSegwitFlag(u8),
+    /// The varint at `offset` isn't in its shortest form
+    NonMinimalVarInt { offset: usize },
+    /// The witness of `input` is `size` bytes, over [`MAX_DECODED_VEC_SIZE`]
+    OversizedWitness { input: usize, size: usize },
+    /// Bytes left after the lock time
+    TrailingBytes(usize),
+}
+
+/// Why [`decode_lenient`] stopped before the end of the transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeFailure {
+    /// The offset of the field which couldn't be read
+    pub offset: usize,
+    /// What was being read
+    pub message: &'static str,
+}
+
+/// The fields of a transaction read by [`decode_lenient`], see the
+/// [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartialTx {
+    /// The version, `None` if the transaction is shorter than 4 bytes
+    pub version: Option<Version>,
+    /// Whether the segwit marker and flag are present
+    pub segwit: bool,
+    /// The inputs read, with their witnesses if already read
+    pub inputs: Vec<TxIn>,
+    /// The outputs read
+    pub outputs: Vec<TxOut>,
+    /// The lock time, `None` if not reached
+    pub lock_time: Option<absolute::LockTime>,
+    /// The unusual encodings accepted
+    pub anomalies: Vec<Anomaly>,
+    /// Why the decoding stopped, `None` if the whole transaction was read
+    pub failure: Option<DecodeFailure>,
+}
+
+impl PartialTx {
+    /// Returns true if every field was read
+    pub fn is_complete(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// The decoded transaction, `None` if the decoding stopped early
+    pub fn transaction(&self) -> Option<Transaction> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(Transaction {
+            version: self.version?,
+            lock_time: self.lock_time?,
+            input: self.inputs.clone(),
+            output: self.outputs.clone(),
+        })
+    }
+}
+
+/// A cursor over the bytes of a transaction, tracking its offset
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize, message: &'static str) -> Result<&'a [u8], DecodeFailure> {
+        let rest = &self.bytes[self.offset..];
+        if rest.len() < len {
+            return Err(self.failure(message));
+        }
+        self.offset += len;
+        Ok(&rest[..len])
+    }
+
+    fn failure(&self, message: &'static str) -> DecodeFailure {
+        DecodeFailure {
+            offset: self.offset,
+            message,
+        }
+    }
+
+    fn peek(&self, index: usize) -> Option<u8> {
+        self.bytes.get(self.offset + index).copied()
+    }
+
+    fn u32(&mut self, message: &'static str) -> Result<u32, DecodeFailure> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.take(4, message)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn var_int(
+        &mut self,
+        anomalies: &mut Vec<Anomaly>,
+        message: &'static str,
+    ) -> Result<usize, DecodeFailure> {
+        let offset = self.offset;
+        let (value, min) = match self.take(1, message)?[0] {
+            0xfd => {
+                let bytes = self.take(2, message)?;
+                (u64::from(u16::from_le_bytes([bytes[0], bytes[1]])), 0xfd)
+            }
+            0xfe => (u64::from(self.u32(message)?), 0x1_0000),
+            0xff => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(self.take(8, message)?);
+                (u64::from_le_bytes(bytes), 0x1_0000_0000)
+            }
+            n => (u64::from(n), 0),
+        };
+        if value < min {
+            anomalies.push(Anomaly::NonMinimalVarInt { offset });
+        }
+        // A count larger than the remaining bytes can't be satisfied
+        match usize::try_from(value) {
+            Ok(value) if value <= self.bytes.len() - self.offset => Ok(value),
+            _ => Err(DecodeFailure { offset, message }),
+        }
+    }
+
+    fn var_bytes(
+        &mut self,
+        anomalies: &mut Vec<Anomaly>,
+        message: &'static str,
+    ) -> Result<&'a [u8], DecodeFailure> {
+        let len = self.var_int(anomalies, message)?;
+        self.take(len, message)
+    }
+}
+
+/// Decode the raw transaction `raw` as far as possible, see the [module documentation](self)
+pub fn decode_lenient(raw: &[u8]) -> PartialTx {
+    let mut tx = PartialTx::default();
+    let mut reader = Reader {
+        bytes: raw,
+        offset: 0,
+    };
+    if let Err(failure) = decode(&mut reader, &mut tx) {
+        tx.failure = Some(failure);
+    }
+    tx
+}
+
+fn decode(reader: &mut Reader, tx: &mut PartialTx) -> Result<(), DecodeFailure> {
+    tx.version = Some(Version(reader.u32("version")? as i32));
+    let flag = match (reader.peek(0), reader.peek(1)) {
+        (Some(0), Some(flag)) if flag != 0 => Some(flag),
+        _ => None,
+    };
+    if let Some(flag) = flag {
+        reader.take(2, "segwit marker")?;
+        tx.segwit = true;
+        if flag != 1 {
+            tx.anomalies.push(Anomaly::UnknownSegwitFlag(flag));
+        }
+    }
+
+    let count = reader.var_int(&mut tx.anomalies, "input count")?;
+    for _ in 0..count {
+        let mut txid = [0u8; 32];
+        txid.copy_from_slice(reader.take(32, "input previous txid")?);
+        let vout = reader.u32("input previous vout")?;
+        let script = reader.var_bytes(&mut tx.anomalies, "input script")?;
+        let sequence = reader.u32("input sequence")?;
+        tx.inputs.push(TxIn {
+            previous_output: OutPoint::new(Txid::from_byte_array(txid), vout),
+            script_sig: ScriptBuf::from_bytes(script.to_vec()),
+            sequence: Sequence(sequence),
+            witness: Witness::new(),
+        });
+    }
+
+    let count = reader.var_int(&mut tx.anomalies, "output count")?;
+    for _ in 0..count {
+        let mut value = [0u8; 8];
+        value.copy_from_slice(reader.take(8, "output value")?);
+        let script = reader.var_bytes(&mut tx.anomalies, "output script")?;
+        tx.outputs.push(TxOut {
+            value: Amount::from_sat(u64::from_le_bytes(value)),
+            script_pubkey: ScriptBuf::from_bytes(script.to_vec()),
+        });
+    }
+
+    if tx.segwit {
+        for input in 0..tx.inputs.len() {
+            let start = reader.offset;
+            let count = reader.var_int(&mut tx.anomalies, "witness element count")?;
+            let mut elements = Vec::with_capacity(count);
+            for _ in 0..count {
+                elements.push(reader.var_bytes(&mut tx.anomalies, "witness element")?);
+            }
+            let size = reader.offset - start;
+            if size > MAX_DECODED_VEC_SIZE {
+                tx.anomalies.push(Anomaly::OversizedWitness { input, size });
+            }
+            tx.inputs[input].witness = Witness::from_slice(&elements);
+        }
+        if tx.inputs.iter().all(|input| input.witness.is_empty()) {
+            tx.anomalies.push(Anomaly::EmptyWitnesses);
+        }
+    }
+
+    let lock_time = reader.u32("lock time")?;
+    tx.lock_time = Some(absolute::LockTime::from_consensus(lock_time));
+    let trailing = reader.bytes.len() - reader.offset;
+    if trailing > 0 {
+        tx.anomalies.push(Anomaly::TrailingBytes(trailing));
+    }
+    Ok(())
+}
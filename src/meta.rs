@@ -0,0 +1,110 @@
+//! Transfer metadata of the responses received by a call.
+//!
+//! [`crate::BlockingClient::measured`] and its async counterpart run a call, such as a scan
+//! following pages or a hydration fetching many transactions, and return with its result a
+//! [`Measured`] listing the [`ResponseMeta`] of every response received on the way: the bytes
+//! on the wire and once decoded, the time to the first byte and the time to the end of the
+//! body. Bandwidth-constrained users can compare the two sizes to quantify the savings of
+//! compressed encodings.
+//!
+//! Neither client negotiates compression today, so both sizes are equal unless the server
+//! announces a `Content-Length` differing from the body. The blocking client receives the
+//! whole response at once, its time to the first byte is unknown.
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+#[cfg(feature = "async")]
+use std::time::Instant;
+
+use crate::EndpointClass;
+
+/// The transfer metadata of one response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseMeta {
+    /// The kind of data requested
+    pub endpoint: EndpointClass,
+    /// The HTTP status
+    pub status: u16,
+    /// Bytes of the body as sent by the server, its `Content-Length` if announced
+    pub wire_bytes: u64,
+    /// Bytes of the body as handed to the client, after any decompression
+    pub decoded_bytes: u64,
+    /// Time from sending the request to receiving the response headers, `None` if the HTTP
+    /// backend doesn't expose it
+    pub time_to_first_byte: Option<Duration>,
+    /// Time from sending the request to receiving the whole body
+    pub duration: Duration,
+}
+
+/// The result of a call with the metadata of the responses it received, see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct Measured<T> {
+    /// The result
+    pub value: T,
+    /// The metadata of every response received, in order, retries included
+    pub responses: Vec<ResponseMeta>,
+    /// The time taken by the whole call
+    pub duration: Duration,
+}
+
+impl<T> Measured<T> {
+    /// Bytes of the bodies as sent by the server
+    pub fn wire_bytes(&self) -> u64 {
+        self.responses.iter().map(|meta| meta.wire_bytes).sum()
+    }
+
+    /// Bytes of the bodies as handed to the client
+    pub fn decoded_bytes(&self) -> u64 {
+        self.responses.iter().map(|meta| meta.decoded_bytes).sum()
+    }
+}
+
+/// The metadata recorded by a client, shared by its clones
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MetaLog {
+    inner: Arc<Mutex<Vec<ResponseMeta>>>,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl MetaLog {
+    fn lock(&self) -> MutexGuard<'_, Vec<ResponseMeta>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn record(&self, meta: ResponseMeta) {
+        self.lock().push(meta);
+    }
+
+    pub(crate) fn take(&self) -> Vec<ResponseMeta> {
+        std::mem::take(&mut *self.lock())
+    }
+}
+
+/// The metadata of an async response whose body isn't read yet, kept in its extensions
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub(crate) struct PendingMeta {
+    pub(crate) endpoint: EndpointClass,
+    pub(crate) status: u16,
+    pub(crate) content_length: Option<u64>,
+    pub(crate) started: Instant,
+    pub(crate) time_to_first_byte: Duration,
+}
+
+#[cfg(feature = "async")]
+impl PendingMeta {
+    /// The metadata once `decoded_bytes` of body are read
+    pub(crate) fn finish(self, decoded_bytes: u64) -> ResponseMeta {
+        ResponseMeta {
+            endpoint: self.endpoint,
+            status: self.status,
+            wire_bytes: self.content_length.unwrap_or(decoded_bytes),
+            decoded_bytes,
+            time_to_first_byte: Some(self.time_to_first_byte),
+            duration: self.started.elapsed(),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Delivery of events to an async callback with retries.
+//!
+//! A [`Notifier`] hands every event to a user provided async callback, such as a function
+//! enqueuing a job in an existing job system. Events are delivered at least once and in order:
+//! an event stays pending until the callback succeeds, and a failing callback is retried with
+//! exponential backoff.
+//!
+//! The crate has no monitoring subsystem producing events yet, the caller feeds the notifier
+//! with [`Notifier::notify`], e.g. with the transactions found by comparing two
+//! [`crate::WaterfallResponse`].
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::r#async::{DefaultSleeper, Sleeper};
+use crate::{next_backoff, DEFAULT_BASE_BACKOFF, DEFAULT_MAX_RETRIES};
+
+/// Delivers events of type `E` to an async callback `F`, see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct Notifier<E, F, S = DefaultSleeper> {
+    callback: F,
+    max_retries: usize,
+    pending: Mutex<VecDeque<E>>,
+    marker: PhantomData<S>,
+}
+
+impl<E, F, Fut, CE, S> Notifier<E, F, S>
+where
+    E: Clone,
+    F: Fn(E) -> Fut,
+    Fut: Future<Output = Result<(), CE>>,
+    S: Sleeper,
+{
+    /// Create a notifier invoking `callback` for every event
+    pub fn new(callback: F) -> Self {
+        Notifier {
+            callback,
+            max_retries: DEFAULT_MAX_RETRIES,
+            pending: Mutex::new(VecDeque::new()),
+            marker: PhantomData,
+        }
+    }
+
+    /// Set the maximum number of times a failing callback is retried for the same event
+    pub fn max_retries(mut self, count: usize) -> Self {
+        self.max_retries = count;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<E>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number of events not delivered yet
+    pub fn pending(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Queue `event` and deliver every pending event, see [`Self::deliver_pending`]
+    pub async fn notify(&self, event: E) -> Result<usize, CE> {
+        self.lock().push_back(event);
+        self.deliver_pending().await
+    }
+
+    /// Deliver the pending events in order, returning how many were delivered.
+    ///
+    /// When the callback still fails after the retries, its error is returned and the event
+    /// stays pending with the following ones, to be delivered by the next call. Concurrent
+    /// deliveries may invoke the callback more than once for the same event.
+    pub async fn deliver_pending(&self) -> Result<usize, CE> {
+        let mut delivered = 0;
+        loop {
+            let event = match self.lock().front() {
+                Some(event) => event.clone(),
+                None => return Ok(delivered),
+            };
+            let mut delay = DEFAULT_BASE_BACKOFF;
+            let mut attempts = 0;
+            loop {
+                match (self.callback)(event.clone()).await {
+                    Ok(()) => break,
+                    Err(_) if S::RETRIES && attempts < self.max_retries => {
+                        S::sleep(delay).await;
+                        attempts += 1;
+                        delay = next_backoff(delay, None);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            self.lock().pop_front();
+            delivered += 1;
+        }
+    }
+}
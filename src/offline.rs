@@ -0,0 +1,160 @@
+//! Answers from cached data when the device is offline.
+//!
+//! A client built with [`crate::Builder::offline_cache`] records in an [`OfflineCache`] the
+//! scans and transactions fetched by [`crate::BlockingClient::waterfalls_cached`] and
+//! [`crate::BlockingClient::wallet_summary_cached`], or their async counterparts. After
+//! [`OfflineCache::set_offline`] these calls answer from the cache instead of failing, with the
+//! result marked [stale](Cached::stale) and the time of its last refresh, so a wallet can still
+//! render its balance on airplane mode. Every other request fails at once with
+//! [`crate::Error::Offline`], without waiting for network timeouts.
+//!
+//! The cache can be saved with [`OfflineCache::write_to`] and restored at the next start with
+//! [`OfflineCache::read_from`].
+
+use std::collections::BTreeMap;
+#[cfg(feature = "json")]
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bitcoin::{Transaction, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::clock::unix_now;
+#[cfg(feature = "json")]
+use crate::Error;
+use crate::{Timestamp, WalletSummary, WaterfallResponse};
+
+/// A result which may come from an [`OfflineCache`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cached<T> {
+    /// The result
+    pub value: T,
+    /// When the data of the result was last fetched from the server
+    pub refreshed_at: Timestamp,
+    /// Whether the result comes from the cache instead of the server
+    pub stale: bool,
+}
+
+impl<T> Cached<T> {
+    /// A result just fetched from the server
+    pub fn fresh(value: T) -> Self {
+        Cached {
+            value,
+            refreshed_at: Timestamp(unix_now()),
+            stale: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedScan {
+    response: WaterfallResponse,
+    refreshed_at: Timestamp,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OfflineInner {
+    #[serde(skip)]
+    offline: bool,
+    scans: BTreeMap<String, CachedScan>,
+    txs: BTreeMap<Txid, Transaction>,
+}
+
+/// The scans and transactions answering calls in offline mode, see the
+/// [module documentation](self).
+///
+/// Cloning an [`OfflineCache`] returns a handle to the same cache.
+#[derive(Debug, Clone, Default)]
+pub struct OfflineCache {
+    inner: Arc<Mutex<OfflineInner>>,
+}
+
+impl OfflineCache {
+    /// Create an empty cache, online
+    pub fn new() -> Self {
+        OfflineCache::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, OfflineInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns true if the clients using the cache are in offline mode
+    pub fn is_offline(&self) -> bool {
+        self.lock().offline
+    }
+
+    /// Enter or leave offline mode, e.g. when the device loses or regains connectivity
+    pub fn set_offline(&self, offline: bool) {
+        self.lock().offline = offline;
+    }
+
+    /// Store `response`, the scan of `descriptor` just fetched from the server
+    pub fn insert_scan(&self, descriptor: &str, response: &WaterfallResponse) {
+        let scan = CachedScan {
+            response: response.clone(),
+            refreshed_at: Timestamp(unix_now()),
+        };
+        self.lock().scans.insert(descriptor.to_string(), scan);
+    }
+
+    /// The last scan of `descriptor`, marked stale
+    pub fn scan(&self, descriptor: &str) -> Option<Cached<WaterfallResponse>> {
+        let inner = self.lock();
+        let scan = inner.scans.get(descriptor)?;
+        Some(Cached {
+            value: scan.response.clone(),
+            refreshed_at: scan.refreshed_at,
+            stale: true,
+        })
+    }
+
+    /// Store transactions fetched from the server
+    pub fn insert_txs(&self, txs: impl IntoIterator<Item = Transaction>) {
+        let mut inner = self.lock();
+        for tx in txs {
+            inner.txs.insert(tx.compute_txid(), tx);
+        }
+    }
+
+    /// The cached transaction `txid`
+    pub fn tx(&self, txid: &Txid) -> Option<Transaction> {
+        self.lock().txs.get(txid).cloned()
+    }
+
+    /// The [`WalletSummary`] of the last scan of `descriptor` computed with the cached
+    /// transactions, marked stale
+    pub fn summary(&self, descriptor: &str) -> Option<Cached<WalletSummary>> {
+        let inner = self.lock();
+        let scan = inner.scans.get(descriptor)?;
+        Some(Cached {
+            value: scan.response.summary(&inner.txs),
+            refreshed_at: scan.refreshed_at,
+            stale: true,
+        })
+    }
+
+    /// Unconfirm the cached scan entries above `fork_height`, after a reorg, see
+    /// [`WaterfallResponse::rollback`]
+    pub fn rollback(&self, fork_height: u32) {
+        for scan in self.lock().scans.values_mut() {
+            scan.response.rollback(fork_height);
+        }
+    }
+
+    /// Write the cached scans and transactions as JSON
+    #[cfg(feature = "json")]
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, &*self.lock()).map_err(|e| Error::Io(e.into()))
+    }
+
+    /// Read a cache written by [`Self::write_to`], online
+    #[cfg(feature = "json")]
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, Error> {
+        let inner: OfflineInner =
+            serde_json::from_reader(reader).map_err(|e| Error::Io(e.into()))?;
+        Ok(OfflineCache {
+            inner: Arc::new(Mutex::new(inner)),
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Scan results for air-gapped signers.
+//!
+//! A signer without network access needs the unspent outputs of the wallet, the transactions
+//! creating them and the derivation index of their scripts to check and sign a PSBT. A
+//! [`SyncPayload`] holds only those and encodes them with [`SyncPayload::to_bytes`] in a compact
+//! binary format, small enough for an animated QR code or an SD card:
+//!
+//! - the magic bytes [`SYNC_PAYLOAD_MAGIC`] and the version [`SYNC_PAYLOAD_VERSION`]
+//! - the number of transactions and the transactions, consensus encoded
+//! - the number of outputs and for every output the position of its transaction in the list, its
+//!   output index, its keychain (0 external, 1 internal, 2 none) and its derivation index, every
+//!   number but the keychain as a compact size
+//!
+//! [`crate::BlockingClient::sync_payload`] and its async counterpart build it from a scan.
+
+use std::collections::BTreeMap;
+
+use bitcoin::consensus::encode::{Decodable, Encodable, Error as EncodeError, VarInt};
+use bitcoin::{OutPoint, Transaction, TxOut, Txid};
+
+use crate::{Error, Keychain, Utxo};
+
+/// The first bytes of an encoded [`SyncPayload`]
+pub const SYNC_PAYLOAD_MAGIC: [u8; 4] = *b"WFSP";
+
+/// Version of the [`SyncPayload`] encoding written by this crate
+pub const SYNC_PAYLOAD_VERSION: u8 = 1;
+
+/// An unspent output of a [`SyncPayload`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PayloadUtxo {
+    /// The output
+    pub outpoint: OutPoint,
+    /// The keychain of the script, `None` for address scans and non standard paths
+    pub keychain: Option<Keychain>,
+    /// The derivation index of the script
+    pub index: u32,
+}
+
+/// The unspent outputs of a wallet with the transactions creating them, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SyncPayload {
+    /// The unspent outputs
+    pub utxos: Vec<PayloadUtxo>,
+    /// The transactions creating the outputs
+    pub txs: BTreeMap<Txid, Transaction>,
+}
+
+impl SyncPayload {
+    /// The payload of `utxos`, created by the transactions in `txs`. Outputs whose transaction
+    /// is missing are skipped, transactions not creating any of the outputs aren't included.
+    pub fn new(utxos: &[Utxo], txs: &BTreeMap<Txid, Transaction>) -> Self {
+        let mut payload = SyncPayload::default();
+        for utxo in utxos {
+            let tx = match txs.get(&utxo.outpoint.txid) {
+                Some(tx) => tx,
+                None => continue,
+            };
+            payload.utxos.push(PayloadUtxo {
+                outpoint: utxo.outpoint,
+                keychain: utxo.keychain,
+                index: utxo.index,
+            });
+            payload.txs.insert(utxo.outpoint.txid, tx.clone());
+        }
+        payload
+    }
+
+    /// The value and script of `utxo`, `None` if its transaction isn't in the payload
+    pub fn txout(&self, utxo: &PayloadUtxo) -> Option<&TxOut> {
+        let tx = self.txs.get(&utxo.outpoint.txid)?;
+        tx.output.get(utxo.outpoint.vout as usize)
+    }
+
+    /// Encode the payload, see the [module documentation](self)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = SYNC_PAYLOAD_MAGIC.to_vec();
+        bytes.push(SYNC_PAYLOAD_VERSION);
+        let positions: BTreeMap<Txid, usize> = self
+            .txs
+            .keys()
+            .enumerate()
+            .map(|(position, txid)| (*txid, position))
+            .collect();
+        // Writing to a vector doesn't fail
+        let write_var_int = |bytes: &mut Vec<u8>, n: u64| {
+            VarInt(n).consensus_encode(bytes).expect("in memory");
+        };
+        write_var_int(&mut bytes, self.txs.len() as u64);
+        for tx in self.txs.values() {
+            tx.consensus_encode(&mut bytes).expect("in memory");
+        }
+        let utxos: Vec<_> = self
+            .utxos
+            .iter()
+            .filter_map(|utxo| Some((positions.get(&utxo.outpoint.txid)?, utxo)))
+            .collect();
+        write_var_int(&mut bytes, utxos.len() as u64);
+        for (position, utxo) in utxos {
+            write_var_int(&mut bytes, *position as u64);
+            write_var_int(&mut bytes, u64::from(utxo.outpoint.vout));
+            bytes.push(match utxo.keychain {
+                Some(Keychain::External) => 0,
+                Some(Keychain::Internal) => 1,
+                None => 2,
+            });
+            write_var_int(&mut bytes, u64::from(utxo.index));
+        }
+        bytes
+    }
+
+    /// Decode a payload encoded with [`Self::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let rest = bytes
+            .strip_prefix(&SYNC_PAYLOAD_MAGIC[..])
+            .ok_or_else(|| invalid("not a sync payload".to_string()))?;
+        let (version, mut reader) = match rest.split_first() {
+            Some((version, reader)) => (*version, reader),
+            None => return Err(invalid("missing version".to_string())),
+        };
+        if version != SYNC_PAYLOAD_VERSION {
+            return Err(invalid(format!("unsupported version {version}")));
+        }
+
+        let mut txs = vec![];
+        for _ in 0..read_var_int(&mut reader)? {
+            let tx = Transaction::consensus_decode(&mut reader)?;
+            txs.push((tx.compute_txid(), tx));
+        }
+        let mut utxos = vec![];
+        for _ in 0..read_var_int(&mut reader)? {
+            let position = read_var_int(&mut reader)?;
+            let txid = match usize::try_from(position).ok().and_then(|p| txs.get(p)) {
+                Some((txid, _)) => *txid,
+                None => return Err(invalid(format!("unknown transaction {position}"))),
+            };
+            let vout = read_u32(&mut reader)?;
+            let keychain = match u8::consensus_decode(&mut reader)? {
+                0 => Some(Keychain::External),
+                1 => Some(Keychain::Internal),
+                2 => None,
+                n => return Err(invalid(format!("unknown keychain {n}"))),
+            };
+            utxos.push(PayloadUtxo {
+                outpoint: OutPoint::new(txid, vout),
+                keychain,
+                index: read_u32(&mut reader)?,
+            });
+        }
+        if !reader.is_empty() {
+            return Err(invalid(format!("{} trailing bytes", reader.len())));
+        }
+        Ok(SyncPayload {
+            utxos,
+            txs: txs.into_iter().collect(),
+        })
+    }
+}
+
+fn read_var_int(reader: &mut &[u8]) -> Result<u64, Error> {
+    Ok(VarInt::consensus_decode(reader)?.0)
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32, Error> {
+    let n = read_var_int(reader)?;
+    u32::try_from(n).map_err(|_| Error::BitcoinEncoding(EncodeError::ParseFailed("u32 overflow")))
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidSyncPayload(message)
+}
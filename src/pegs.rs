@@ -0,0 +1,114 @@
+//! Detection of Liquid peg-ins and peg-outs.
+//!
+//! [`find_pegs`] reads a raw Elements transaction, as returned by the `/tx/:txid/raw` endpoint
+//! of a Liquid server, and returns its [`Peg`]s: the inputs claiming bitcoin locked on the main
+//! chain and the outputs burning L-BTC to release it there. Only the fields needed to find the
+//! pegs are parsed, confidential amounts and proofs are skipped.
+//!
+//! [`crate::BlockingClient::find_pegs`] and its async counterpart run it on every transaction
+//! of a scan.
+
+use bitcoin::blockdata::opcodes::all::OP_RETURN;
+use bitcoin::blockdata::script::Instruction;
+use bitcoin::consensus::deserialize;
+use bitcoin::{Address, Amount, BlockHash, Network, Script, ScriptBuf, Transaction, Txid};
+
+use crate::elements::{parse_failed, parse_tx};
+use crate::Error;
+
+/// A transfer between Liquid and the Bitcoin main chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Peg {
+    /// An input claiming bitcoin sent to the federation on the main chain
+    In {
+        /// The index of the input
+        input: u32,
+        /// The main chain transaction locking the bitcoin
+        mainchain_txid: Txid,
+        /// The claimed amount
+        value: Amount,
+    },
+    /// An output burning L-BTC to release bitcoin on the main chain
+    Out {
+        /// The index of the output
+        output: u32,
+        /// The genesis block of the main chain
+        genesis_hash: BlockHash,
+        /// The main chain script receiving the bitcoin
+        mainchain_script: ScriptBuf,
+        /// The burned amount, `None` if the output is blinded
+        value: Option<Amount>,
+    },
+}
+
+impl Peg {
+    /// The main chain address receiving a peg-out, `None` for peg-ins and non standard scripts
+    pub fn mainchain_address(&self, network: Network) -> Option<Address> {
+        match self {
+            Peg::Out {
+                mainchain_script, ..
+            } => Address::from_script(mainchain_script, network).ok(),
+            Peg::In { .. } => None,
+        }
+    }
+}
+
+/// The pegs of the raw Elements transaction `raw`, see the [module documentation](self)
+pub fn find_pegs(raw: &[u8]) -> Result<Vec<Peg>, Error> {
+    let tx = parse_tx(raw)?;
+    let mut pegs = vec![];
+    for (input, txin) in tx.inputs.iter().enumerate() {
+        if txin.is_pegin {
+            pegs.push(pegin(input as u32, &txin.pegin_witness)?);
+        }
+    }
+    for (output, txout) in tx.outputs.iter().enumerate() {
+        if let Some((genesis_hash, mainchain_script)) = pegout_destination(txout.script_pubkey) {
+            pegs.push(Peg::Out {
+                output: output as u32,
+                genesis_hash,
+                mainchain_script,
+                value: txout.value.explicit().map(Amount::from_sat),
+            });
+        }
+    }
+    Ok(pegs)
+}
+
+/// The peg-in claimed by `input` with the given peg-in witness: value, asset, genesis hash,
+/// claim script, main chain transaction and its merkle proof
+fn pegin(input: u32, witness: &[&[u8]]) -> Result<Peg, Error> {
+    let (value, mainchain_tx) = match witness {
+        [value, _, _, _, mainchain_tx, _] if value.len() == 8 => (value, mainchain_tx),
+        _ => return Err(parse_failed("invalid peg-in witness")),
+    };
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(value);
+    let mainchain_tx: Transaction = deserialize(mainchain_tx)?;
+    Ok(Peg::In {
+        input,
+        mainchain_txid: mainchain_tx.compute_txid(),
+        value: Amount::from_sat(u64::from_le_bytes(bytes)),
+    })
+}
+
+/// The genesis hash and main chain script of a peg-out script: `OP_RETURN`, the genesis hash
+/// of the main chain and the script receiving the bitcoin, possibly followed by other pushes
+fn pegout_destination(script: &Script) -> Option<(BlockHash, ScriptBuf)> {
+    let mut instructions = script.instructions();
+    match instructions.next()?.ok()? {
+        Instruction::Op(OP_RETURN) => {}
+        _ => return None,
+    }
+    let genesis_hash = match instructions.next()?.ok()? {
+        Instruction::PushBytes(bytes) => deserialize(bytes.as_bytes()).ok()?,
+        _ => return None,
+    };
+    match instructions.next()?.ok()? {
+        Instruction::PushBytes(bytes) if !bytes.is_empty() => Some((
+            genesis_hash,
+            ScriptBuf::from_bytes(bytes.as_bytes().to_vec()),
+        )),
+        _ => None,
+    }
+}
@@ -0,0 +1,94 @@
+//! Stale-while-revalidate polling of a descriptor scan.
+//!
+//! [`crate::BlockingClient::poll_waterfalls`] and its async counterpart return at once the last
+//! response cached in a [`StaleWhileRevalidate`] and refresh it in the background, so a wallet
+//! UI can render immediately and update when the receiver returned by
+//! [`StaleWhileRevalidate::new`] yields a new response.
+//!
+//! The time between two polls can be chosen with a [`crate::PollSchedule`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{Error, WaterfallResponse};
+
+/// A refreshed response, or the error of the refresh
+pub type Update = Result<WaterfallResponse, Error>;
+
+#[derive(Debug)]
+struct PollerInner {
+    cached: Mutex<Option<WaterfallResponse>>,
+    refreshing: AtomicBool,
+    sender: Mutex<Sender<Update>>,
+}
+
+/// The cached response of a polled scan, see the [module documentation](self).
+///
+/// Cloning a [`StaleWhileRevalidate`] returns a handle to the same cache.
+#[derive(Debug, Clone)]
+pub struct StaleWhileRevalidate {
+    inner: Arc<PollerInner>,
+}
+
+impl StaleWhileRevalidate {
+    /// Create an empty cache and the receiver of its updates.
+    ///
+    /// A refreshed response is sent only if its [`WaterfallResponse::content_hash`] differs
+    /// from the cached one, failed refreshes are always sent.
+    pub fn new() -> (Self, Receiver<Update>) {
+        let (sender, receiver) = mpsc::channel();
+        let poller = StaleWhileRevalidate {
+            inner: Arc::new(PollerInner {
+                cached: Mutex::new(None),
+                refreshing: AtomicBool::new(false),
+                sender: Mutex::new(sender),
+            }),
+        };
+        (poller, receiver)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<WaterfallResponse>> {
+        self.inner.cached.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The last response received, if any
+    pub fn cached(&self) -> Option<WaterfallResponse> {
+        self.lock().clone()
+    }
+
+    /// Set the cached response, e.g. with one persisted by a previous session
+    pub fn set_cached(&self, response: WaterfallResponse) {
+        *self.lock() = Some(response);
+    }
+
+    /// Returns true while a refresh is in progress
+    pub fn is_refreshing(&self) -> bool {
+        self.inner.refreshing.load(Ordering::SeqCst)
+    }
+
+    /// Mark a refresh as started, returning false if one is already in progress
+    pub(crate) fn start_refresh(&self) -> bool {
+        !self.inner.refreshing.swap(true, Ordering::SeqCst)
+    }
+
+    /// Store the result of a refresh and send it to the receiver if it's news
+    pub(crate) fn finish_refresh(&self, update: Update) {
+        let changed = match &update {
+            Ok(response) => {
+                let mut cached = self.lock();
+                let changed = cached.as_ref().map(WaterfallResponse::content_hash)
+                    != Some(response.content_hash());
+                *cached = Some(response.clone());
+                changed
+            }
+            Err(_) => true,
+        };
+        self.inner.refreshing.store(false, Ordering::SeqCst);
+        if changed {
+            let sender = self.inner.sender.lock().unwrap_or_else(|e| e.into_inner());
+            // The receiver may have been dropped, the cache is still updated
+            let _ = sender.send(update);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Load balancing of requests between several Waterfalls servers.
+//!
+//! A [`ServerPool`] holds one client per replica and hands them out with
+//! [`ServerPool::acquire`], by weighted round-robin or by lowest observed latency, without
+//! exceeding the number of requests in flight allowed for each server.
+
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How a [`ServerPool`] chooses the next server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Selection {
+    /// Servers are used in turn proportionally to their weight
+    #[default]
+    WeightedRoundRobin,
+    /// The server with the lowest average latency is used, servers without measurements
+    /// first
+    LeastLatency,
+}
+
+#[derive(Debug, Default)]
+struct ServerState {
+    in_flight: usize,
+    /// Smooth weighted round-robin counter
+    current_weight: i64,
+    /// Exponentially weighted moving average of the latency
+    latency: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct PoolServer<T> {
+    client: T,
+    weight: u32,
+    max_in_flight: Option<usize>,
+}
+
+/// A set of clients to replicas of the same server, see the [module documentation](self).
+#[derive(Debug)]
+pub struct ServerPool<T> {
+    servers: Vec<PoolServer<T>>,
+    selection: Selection,
+    states: Mutex<Vec<ServerState>>,
+}
+
+impl<T> ServerPool<T> {
+    /// Create an empty pool choosing servers with `selection`
+    pub fn new(selection: Selection) -> Self {
+        ServerPool {
+            servers: vec![],
+            selection,
+            states: Mutex::new(vec![]),
+        }
+    }
+
+    /// Add a server with its `client`, its `weight` for round-robin and the maximum number of
+    /// requests in flight to it, if any
+    pub fn add(mut self, client: T, weight: u32, max_in_flight: Option<usize>) -> Self {
+        self.servers.push(PoolServer {
+            client,
+            weight: weight.max(1),
+            max_in_flight,
+        });
+        self.lock_states().push(ServerState::default());
+        self
+    }
+
+    /// The number of servers in the pool
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// Returns true if the pool has no servers
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// The average latency observed for the server at `index`, if any
+    pub fn latency(&self, index: usize) -> Option<Duration> {
+        self.lock_states().get(index).and_then(|s| s.latency)
+    }
+
+    /// The number of requests in flight to the server at `index`
+    pub fn in_flight(&self, index: usize) -> usize {
+        self.lock_states().get(index).map_or(0, |s| s.in_flight)
+    }
+
+    fn lock_states(&self) -> std::sync::MutexGuard<'_, Vec<ServerState>> {
+        self.states.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Reserve the next server for a request.
+    ///
+    /// Returns `None` if every server reached its maximum of requests in flight. The
+    /// reservation ends when the returned [`ServerPermit`] is dropped.
+    pub fn acquire(&self) -> Option<ServerPermit<'_, T>> {
+        let mut states = self.lock_states();
+        let available = |(index, state): &(usize, &ServerState)| {
+            self.servers[*index]
+                .max_in_flight
+                .map_or(true, |max| state.in_flight < max)
+        };
+        let index = match self.selection {
+            Selection::WeightedRoundRobin => {
+                // Smooth weighted round-robin as in nginx: every available server gains its
+                // weight, the one with the highest counter is chosen and loses the total
+                let candidates: Vec<usize> = states
+                    .iter()
+                    .enumerate()
+                    .filter(available)
+                    .map(|(index, _)| index)
+                    .collect();
+                let mut total = 0;
+                for &index in &candidates {
+                    let weight = i64::from(self.servers[index].weight);
+                    states[index].current_weight += weight;
+                    total += weight;
+                }
+                let chosen = candidates.iter().copied().max_by_key(|&index| {
+                    (states[index].current_weight, std::cmp::Reverse(index))
+                })?;
+                states[chosen].current_weight -= total;
+                chosen
+            }
+            Selection::LeastLatency => states
+                .iter()
+                .enumerate()
+                .filter(available)
+                .min_by_key(|(index, state)| (state.latency.is_some(), state.latency, *index))
+                .map(|(index, _)| index)?,
+        };
+        states[index].in_flight += 1;
+        Some(ServerPermit {
+            pool: self,
+            index,
+            started: Instant::now(),
+        })
+    }
+
+    fn release(&self, index: usize, latency: Option<Duration>) {
+        let mut states = self.lock_states();
+        let state = &mut states[index];
+        state.in_flight = state.in_flight.saturating_sub(1);
+        if let Some(latency) = latency {
+            state.latency = Some(match state.latency {
+                Some(average) => (average * 4 + latency) / 5,
+                None => latency,
+            });
+        }
+    }
+}
+
+/// A server reserved from a [`ServerPool`], dereferencing to its client.
+#[derive(Debug)]
+pub struct ServerPermit<'a, T> {
+    pool: &'a ServerPool<T>,
+    index: usize,
+    started: Instant,
+}
+
+impl<T> ServerPermit<'_, T> {
+    /// The index of the server in the pool
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// End the reservation, recording the time since [`ServerPool::acquire`] as a latency
+    /// sample of the server.
+    ///
+    /// Dropping the permit instead ends the reservation without a sample, which should be done
+    /// for failed requests.
+    pub fn succeeded(self) {
+        let latency = self.started.elapsed();
+        self.succeeded_in(latency);
+    }
+
+    /// Like [`Self::succeeded`], with a `latency` measured by the caller
+    pub fn succeeded_in(self, latency: Duration) {
+        self.pool.release(self.index, Some(latency));
+        std::mem::forget(self);
+    }
+}
+
+impl<T> Deref for ServerPermit<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.pool.servers[self.index].client
+    }
+}
+
+impl<T> Drop for ServerPermit<'_, T> {
+    fn drop(&mut self) {
+        self.pool.release(self.index, None);
+    }
+}
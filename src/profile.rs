@@ -0,0 +1,103 @@
+//! How hard the clients load a server.
+//!
+//! Public Waterfalls instances ban clients syncing large wallets with many concurrent requests.
+//! A [`SyncProfile`] set with [`crate::Builder::sync_profile`] adjusts the concurrency of the
+//! batched calls, such as [`crate::BlockingClient::get_block_hashes`] or the header sync, and
+//! the spacing between requests:
+//!
+//! - [`SyncProfile::Polite`] sends one request at a time with a pause between them, and after a
+//!   `Retry-After` header every request of the client waits the requested delay, not only the
+//!   retried one
+//! - [`SyncProfile::Aggressive`] raises the header sync concurrency, for self-hosted servers
+//! - [`SyncProfile::Standard`] keeps the concurrency chosen by the caller
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::time::Instant;
+
+/// Delay between two requests of a [`SyncProfile::Polite`] client
+pub const POLITE_REQUEST_SPACING: Duration = Duration::from_millis(500);
+
+/// Number of block hashes requested concurrently by the header sync of a
+/// [`SyncProfile::Aggressive`] client
+pub const AGGRESSIVE_HEADER_SYNC_CONCURRENCY: usize = 16;
+
+/// The load a client puts on the server, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SyncProfile {
+    /// The concurrency requested by the caller, without spacing between requests
+    #[default]
+    Standard,
+    /// One request at a time, spaced by [`POLITE_REQUEST_SPACING`], obeying `Retry-After` for
+    /// every request, for public servers
+    Polite,
+    /// High concurrency for self-hosted servers
+    Aggressive,
+}
+
+impl SyncProfile {
+    /// The number of concurrent requests used when the caller asks for `requested`
+    pub fn concurrency(self, requested: usize) -> usize {
+        match self {
+            SyncProfile::Polite => 1,
+            SyncProfile::Standard | SyncProfile::Aggressive => requested.max(1),
+        }
+    }
+
+    /// The number of block hashes requested concurrently by a header sync
+    pub fn header_sync_concurrency(self) -> usize {
+        match self {
+            SyncProfile::Standard => crate::HEADER_SYNC_CONCURRENCY,
+            SyncProfile::Polite => 1,
+            SyncProfile::Aggressive => AGGRESSIVE_HEADER_SYNC_CONCURRENCY,
+        }
+    }
+
+    /// The minimum delay between the start of two requests
+    pub fn request_spacing(self) -> Duration {
+        match self {
+            SyncProfile::Polite => POLITE_REQUEST_SPACING,
+            SyncProfile::Standard | SyncProfile::Aggressive => Duration::ZERO,
+        }
+    }
+
+    /// Returns true if a `Retry-After` delays every following request, not only the retry
+    pub fn strict_rate_limits(self) -> bool {
+        self == SyncProfile::Polite
+    }
+}
+
+/// The earliest time the next request of a client may be sent.
+///
+/// Cloning a [`Throttle`] returns a handle to the same throttle.
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Throttle {
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl Throttle {
+    fn lock(&self) -> MutexGuard<'_, Option<Instant>> {
+        self.next.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Reserve a slot for a request, the next one is allowed `spacing` after it. Returns the
+    /// time to wait before sending.
+    pub(crate) fn reserve(&self, spacing: Duration) -> Duration {
+        let mut next = self.lock();
+        let now = Instant::now();
+        let start = next.map_or(now, |next| next.max(now));
+        *next = Some(start + spacing);
+        start - now
+    }
+
+    /// Hold every request for at least `delay`
+    pub(crate) fn defer(&self, delay: Duration) {
+        let mut next = self.lock();
+        let until = Instant::now() + delay;
+        *next = Some(next.map_or(until, |next| next.max(until)));
+    }
+}
@@ -0,0 +1,52 @@
+//! Progress of the transfers made by the clients.
+//!
+//! A [`ProgressSink`] set with [`crate::Builder::progress_sink`] receives a [`Progress`] as
+//! request bodies are sent and response bodies are received, so a wallet can render a progress
+//! bar during a large scan or block download.
+//!
+//! The async client reports the download of scans, blocks and transactions chunk by chunk. The
+//! blocking client reports each transfer once it's complete, since `minreq` doesn't expose
+//! partial transfers. Uploads are reported once the server answered.
+
+use std::fmt;
+
+use crate::EndpointClass;
+
+/// The direction of a transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transfer {
+    /// A request body sent to the server
+    Upload,
+    /// A response body received from the server
+    Download,
+}
+
+/// The bytes transferred so far by a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The direction of the transfer
+    pub transfer: Transfer,
+    /// The kind of data transferred
+    pub endpoint: EndpointClass,
+    /// Bytes transferred so far
+    pub bytes: u64,
+    /// Bytes of the whole transfer, `None` if the server didn't announce them
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    /// The completed fraction of the transfer between 0 and 1, `None` if the total is unknown
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.bytes as f64 / total as f64).min(1.0)),
+            None => None,
+        }
+    }
+}
+
+/// Receives the progress of the transfers of a client.
+pub trait ProgressSink: fmt::Debug + Send + Sync {
+    /// Called every time more bytes of a transfer are sent or received
+    fn progress(&self, progress: Progress);
+}
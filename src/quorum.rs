@@ -0,0 +1,70 @@
+//! Agreement on the chain tip between independent servers.
+//!
+//! A single server can hide blocks from a client, e.g. to conceal a payment or a double spend.
+//! Asking several servers run by different parties for their tip and requiring a quorum of them
+//! to agree makes this much harder. [`crate::BlockingClient::tip_quorum`] and its async
+//! counterpart query every server and build a [`TipQuorum`] from the answers.
+//!
+//! Servers a block behind the others are reported as outliers too, a monitor seeing the same
+//! outlier over several polls should treat it as suspicious.
+
+use std::collections::BTreeMap;
+
+use bitcoin::BlockHash;
+
+use crate::Error;
+
+/// The tip agreed on by a quorum of servers, see the [module documentation](self).
+#[derive(Debug)]
+pub struct TipQuorum {
+    /// The tip returned by the most servers
+    pub tip: BlockHash,
+    /// The indexes of the servers returning [`Self::tip`]
+    pub agreeing: Vec<usize>,
+    /// The indexes of the servers returning another tip, with their tips
+    pub outliers: Vec<(usize, BlockHash)>,
+    /// The indexes of the servers which couldn't be queried, with their errors
+    pub errors: Vec<(usize, Error)>,
+}
+
+impl TipQuorum {
+    /// Count the tips returned by the servers, in server order, and check that at least
+    /// `threshold` of them agree. Returns [`Error::NoTipQuorum`] otherwise.
+    pub fn from_tips(
+        tips: Vec<Result<BlockHash, Error>>,
+        threshold: usize,
+    ) -> Result<TipQuorum, Error> {
+        let mut votes: BTreeMap<BlockHash, Vec<usize>> = BTreeMap::new();
+        let mut answers = vec![];
+        let mut errors = vec![];
+        for (index, tip) in tips.into_iter().enumerate() {
+            match tip {
+                Ok(tip) => {
+                    votes.entry(tip).or_default().push(index);
+                    answers.push((index, tip));
+                }
+                Err(e) => errors.push((index, e)),
+            }
+        }
+        // Ties go to the tip returned by the first server
+        let best = votes
+            .into_iter()
+            .max_by_key(|(_, agreeing)| (agreeing.len(), std::cmp::Reverse(agreeing[0])));
+        let (tip, agreeing) = match best {
+            Some((tip, agreeing)) if agreeing.len() >= threshold.max(1) => (tip, agreeing),
+            best => {
+                return Err(Error::NoTipQuorum {
+                    threshold,
+                    votes: best.map_or(0, |(_, agreeing)| agreeing.len()),
+                })
+            }
+        };
+        let outliers = answers.into_iter().filter(|(_, t)| *t != tip).collect();
+        Ok(TipQuorum {
+            tip,
+            agreeing,
+            outliers,
+            errors,
+        })
+    }
+}
@@ -0,0 +1,130 @@
+//! Settings of the wallets of a multi-wallet app.
+//!
+//! A [`DescriptorRegistry`] maps each registered descriptor, by [`descriptor_fingerprint`], to
+//! its [`WalletSettings`]. A client built with [`crate::Builder::descriptor_registry`] uses
+//! them in [`crate::BlockingClient::waterfalls_registered`], and so in
+//! [`crate::BlockingClient::poll_watcher`] and [`crate::BlockingClient::poll_session`], and a
+//! [`crate::Session`] with [`crate::Session::registry`] polls the registered wallets at their
+//! interval.
+//!
+//! The registry can be saved with [`DescriptorRegistry::write_to`] and restored at the next
+//! start with [`DescriptorRegistry::read_from`].
+
+use std::collections::BTreeMap;
+#[cfg(feature = "json")]
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use bitcoin::hashes::sha256;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "json")]
+use crate::Error;
+use crate::{descriptor_fingerprint, Height};
+
+/// The settings of a wallet in a [`DescriptorRegistry`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletSettings {
+    /// The unused scripts scanned after the last used one, the server's if `None`
+    #[serde(default)]
+    pub gap_limit: Option<u32>,
+    /// The height of the first block which may have transactions of the wallet, for the app
+    /// since the server scans the whole chain
+    #[serde(default)]
+    pub birthday: Option<Height>,
+    /// The seconds between two polls of the wallet, the default schedule if `None`
+    #[serde(default)]
+    pub poll_interval: Option<u64>,
+    /// Free form labels of the wallet, e.g. its name
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Registered {
+    descriptor: String,
+    settings: WalletSettings,
+}
+
+/// The settings of registered descriptors, see the [module documentation](self).
+///
+/// Cloning a [`DescriptorRegistry`] returns a handle to the same registry.
+#[derive(Debug, Clone, Default)]
+pub struct DescriptorRegistry {
+    inner: Arc<Mutex<BTreeMap<sha256::Hash, Registered>>>,
+}
+
+impl DescriptorRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        DescriptorRegistry::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<sha256::Hash, Registered>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register `descriptor` with `settings`, returning the settings it replaces
+    pub fn insert(&self, descriptor: &str, settings: WalletSettings) -> Option<WalletSettings> {
+        let registered = Registered {
+            descriptor: descriptor.to_string(),
+            settings,
+        };
+        let previous = self
+            .lock()
+            .insert(descriptor_fingerprint(descriptor), registered);
+        previous.map(|registered| registered.settings)
+    }
+
+    /// The settings of `descriptor`, or of a cosmetic variant of it, `None` if not registered
+    pub fn get(&self, descriptor: &str) -> Option<WalletSettings> {
+        let registered = self.lock();
+        let registered = registered.get(&descriptor_fingerprint(descriptor))?;
+        Some(registered.settings.clone())
+    }
+
+    /// Unregister `descriptor`, returning its settings
+    pub fn remove(&self, descriptor: &str) -> Option<WalletSettings> {
+        let removed = self.lock().remove(&descriptor_fingerprint(descriptor));
+        removed.map(|registered| registered.settings)
+    }
+
+    /// The registered descriptors, as registered
+    pub fn descriptors(&self) -> Vec<String> {
+        let registered = self.lock();
+        registered.values().map(|r| r.descriptor.clone()).collect()
+    }
+
+    /// The number of registered descriptors
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if no descriptor is registered
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Write the registry as JSON
+    #[cfg(feature = "json")]
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let registered: Vec<Registered> = self.lock().values().cloned().collect();
+        serde_json::to_writer(writer, &registered).map_err(|e| Error::Io(e.into()))
+    }
+
+    /// Read a registry written by [`Self::write_to`]
+    #[cfg(feature = "json")]
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, Error> {
+        let registered: Vec<Registered> =
+            serde_json::from_reader(reader).map_err(|e| Error::Io(e.into()))?;
+        let registry = DescriptorRegistry::new();
+        for Registered {
+            descriptor,
+            settings,
+        } in registered
+        {
+            registry.insert(&descriptor, settings);
+        }
+        Ok(registry)
+    }
+}
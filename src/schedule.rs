@@ -0,0 +1,82 @@
+//! Adaptive delay between two polls of a server.
+//!
+//! A [`PollSchedule`] polls fast right after something happened, a new block or a broadcast, when
+//! a wallet is most likely to see changes, and backs off exponentially toward the 10 minutes
+//! block interval otherwise. This keeps a UI fresh while reducing the load on public servers.
+
+use std::time::Duration;
+
+use bitcoin::BlockHash;
+
+use crate::WaterfallResponse;
+
+/// Default delay after a new block or a broadcast
+pub const DEFAULT_MIN_POLL_DELAY: Duration = Duration::from_secs(5);
+
+/// Default delay after a long time without changes, the average block interval
+pub const DEFAULT_MAX_POLL_DELAY: Duration = Duration::from_secs(600);
+
+/// Adaptive delay between polls, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollSchedule {
+    /// The delay after a new block or a broadcast
+    pub min: Duration,
+    /// The upper bound of the delay when nothing changes
+    pub max: Duration,
+    current: Duration,
+    last_tip: Option<BlockHash>,
+}
+
+impl Default for PollSchedule {
+    fn default() -> Self {
+        PollSchedule::new(DEFAULT_MIN_POLL_DELAY, DEFAULT_MAX_POLL_DELAY)
+    }
+}
+
+impl PollSchedule {
+    /// Create a schedule with delays between `min` and `max`, starting from `min`
+    pub fn new(min: Duration, max: Duration) -> Self {
+        PollSchedule {
+            min,
+            max: max.max(min),
+            current: min,
+            last_tip: None,
+        }
+    }
+
+    /// The delay to wait before the next poll
+    pub fn next_delay(&self) -> Duration {
+        self.current
+    }
+
+    /// Poll fast again, e.g. after a broadcast
+    pub fn reset(&mut self) {
+        self.current = self.min;
+    }
+
+    /// Double the delay up to [`Self::max`], returning the new delay
+    pub fn backoff(&mut self) -> Duration {
+        self.current = self.current.saturating_mul(2).min(self.max);
+        self.current
+    }
+
+    /// Record the tip seen by the last poll, returning the delay before the next one.
+    ///
+    /// The schedule is reset if the tip changed since the previous call, it backs off otherwise.
+    pub fn observe_tip(&mut self, tip: BlockHash) -> Duration {
+        if self.last_tip.replace(tip).map_or(false, |last| last != tip) {
+            self.reset();
+            self.current
+        } else {
+            self.backoff()
+        }
+    }
+
+    /// Like [`Self::observe_tip`] with the tip of `response`, backing off if it has none
+    pub fn observe_response(&mut self, response: &WaterfallResponse) -> Duration {
+        match response.tip_hash() {
+            Some(tip) => self.observe_tip(tip),
+            None => self.backoff(),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Choice of the best of several public servers.
+//!
+//! Wallets shipping a list of community instances want to use the one which is up to date and
+//! fast. A [`ServerSelector`] holds one client per server and a primary among them.
+//! [`crate::BlockingClient::score_servers`] and its async counterpart probe every server with
+//! [`crate::BlockingClient::probe`] and give the probes to [`ServerSelector::record`], which
+//! scores them with the [`ScoreWeights`] and promotes the best server as primary. Every change
+//! of primary is sent as a [`SelectorEvent`] on the receiver returned by
+//! [`ServerSelector::new`].
+//!
+//! A server is scored on its freshness, the seconds since its last block and whether its tip is
+//! the one returned by most servers, and on the latency of the probe. Lower scores are better.
+//! Score again at [`ServerSelector::next_scoring`].
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use bitcoin::BlockHash;
+
+use crate::Error;
+
+/// Default time between two scorings of a [`ServerSelector`]
+pub const DEFAULT_SCORING_INTERVAL: Duration = Duration::from_secs(300);
+
+/// How much each measurement of a [`ServerProbe`] weighs in its score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScoreWeights {
+    /// Added for each millisecond of latency
+    pub latency: u64,
+    /// Added for each second since the last block of the server
+    pub staleness: u64,
+    /// Added if the tip of the server isn't the one returned by most servers, or if its time
+    /// since the last block is unknown
+    pub behind: u64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights {
+            latency: 1,
+            staleness: 10,
+            behind: 60_000,
+        }
+    }
+}
+
+/// The state of a server measured by [`crate::BlockingClient::probe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerProbe {
+    /// The tip of the server
+    pub tip: BlockHash,
+    /// The seconds since the last block according to the server, `None` if unknown
+    pub seconds_since_block: Option<u64>,
+    /// The average time taken by the probe requests
+    pub latency: Duration,
+}
+
+/// A change of the primary server of a [`ServerSelector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectorEvent {
+    /// The index of the previous primary
+    pub previous: usize,
+    /// The index of the new primary
+    pub primary: usize,
+    /// The score of the new primary
+    pub score: u64,
+}
+
+#[derive(Debug)]
+struct SelectorState {
+    primary: usize,
+    scores: Vec<Option<u64>>,
+    next_scoring: Option<Instant>,
+}
+
+/// Clients to several servers with the best one as primary, see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct ServerSelector<T> {
+    servers: Vec<T>,
+    weights: ScoreWeights,
+    interval: Duration,
+    state: Mutex<SelectorState>,
+    sender: Mutex<Sender<SelectorEvent>>,
+}
+
+impl<T> ServerSelector<T> {
+    /// Create a selector over the clients in `servers`, the first one primary until scored,
+    /// and the receiver of its events
+    pub fn new(servers: Vec<T>, weights: ScoreWeights) -> (Self, Receiver<SelectorEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let selector = ServerSelector {
+            state: Mutex::new(SelectorState {
+                primary: 0,
+                scores: vec![None; servers.len()],
+                next_scoring: None,
+            }),
+            servers,
+            weights,
+            interval: DEFAULT_SCORING_INTERVAL,
+            sender: Mutex::new(sender),
+        };
+        (selector, receiver)
+    }
+
+    /// Set the time between two scorings
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SelectorState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The clients of the servers, in the order given to [`Self::new`]
+    pub fn servers(&self) -> &[T] {
+        &self.servers
+    }
+
+    /// The index of the primary server
+    pub fn primary_index(&self) -> usize {
+        self.lock().primary
+    }
+
+    /// The client of the primary server, `None` if the selector has no servers
+    pub fn primary(&self) -> Option<&T> {
+        self.servers.get(self.primary_index())
+    }
+
+    /// The score of each server at the last scoring, `None` for servers which failed or
+    /// weren't scored yet
+    pub fn scores(&self) -> Vec<Option<u64>> {
+        self.lock().scores.clone()
+    }
+
+    /// When the servers should be scored again, `None` before the first scoring
+    pub fn next_scoring(&self) -> Option<Instant> {
+        self.lock().next_scoring
+    }
+
+    /// Returns true if the servers weren't scored since the scoring interval
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.next_scoring().map_or(true, |next| next <= now)
+    }
+
+    /// Score the `probes` of the servers, in server order, and promote the best server as
+    /// primary, returning its index.
+    ///
+    /// The primary is kept on ties and when every probe failed.
+    pub fn record(&self, probes: Vec<Result<ServerProbe, Error>>) -> usize {
+        let scores = score(&probes, &self.weights);
+        let mut state = self.lock();
+        state.next_scoring = Some(Instant::now() + self.interval);
+        let current = scores.get(state.primary).copied().flatten();
+        let best = scores
+            .iter()
+            .enumerate()
+            .filter_map(|(index, score)| score.map(|score| (score, index)))
+            .min();
+        state.scores = scores;
+        if let Some((score, index)) = best {
+            if current.map_or(true, |current| score < current) {
+                let event = SelectorEvent {
+                    previous: state.primary,
+                    primary: index,
+                    score,
+                };
+                state.primary = index;
+                // The receiver may have been dropped, the selector still works
+                let _ = self
+                    .sender
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .send(event);
+            }
+        }
+        state.primary
+    }
+}
+
+/// The score of each probe, `None` for the failed ones
+fn score(probes: &[Result<ServerProbe, Error>], weights: &ScoreWeights) -> Vec<Option<u64>> {
+    let mut votes: BTreeMap<BlockHash, usize> = BTreeMap::new();
+    for probe in probes.iter().flatten() {
+        *votes.entry(probe.tip).or_default() += 1;
+    }
+    let most = votes.values().copied().max().unwrap_or(0);
+    probes
+        .iter()
+        .map(|probe| {
+            let probe = probe.as_ref().ok()?;
+            let latency = u64::try_from(probe.latency.as_millis()).unwrap_or(u64::MAX);
+            let behind =
+                votes.get(&probe.tip) != Some(&most) || probe.seconds_since_block.is_none();
+            let staleness = probe.seconds_since_block.unwrap_or(0);
+            Some(
+                latency
+                    .saturating_mul(weights.latency)
+                    .saturating_add(staleness.saturating_mul(weights.staleness))
+                    .saturating_add(if behind { weights.behind } else { 0 }),
+            )
+        })
+        .collect()
+}
+
+/// The seconds in a `/v1/time_since_last_block` answer such as
+/// `42 seconds since last block, less than 100 minutes`, `None` for `unknown`
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn parse_seconds_since_block(text: &str) -> Option<u64> {
+    text.split_whitespace().next()?.parse().ok()
+}
@@ -0,0 +1,275 @@
+//! Several wallets tracked with one client.
+//!
+//! A [`Session`] holds the descriptors of many wallets, e.g. the xpubs followed by a portfolio
+//! app, with a [`HeaderCache`] and a [`TxCache`] shared by all of them. Every wallet has its own
+//! [`PollSchedule`] and the first polls of the wallets are staggered, so the wallets are polled
+//! interleaved instead of in bursts.
+//!
+//! [`crate::BlockingClient::poll_session`] and its async counterpart poll the wallets which are
+//! due, and each wallet receives its [`WalletEvent`]s on the receiver returned by
+//! [`Session::add_wallet`].
+//!
+//! With a [`DescriptorRegistry`] the wallets registered with a poll interval are polled at that
+//! interval.
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use bitcoin::Txid;
+
+use crate::{
+    Builder, DescriptorRegistry, Error, HeaderCache, PollSchedule, TxCache, WaterfallResponse,
+};
+
+/// Default delay between the first polls of two wallets added to a [`Session`]
+pub const DEFAULT_SESSION_STAGGER: Duration = Duration::from_secs(1);
+
+/// The identifier of a wallet in a [`Session`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WalletId(pub usize);
+
+/// An event of a wallet of a [`Session`].
+#[derive(Debug)]
+pub enum WalletEvent {
+    /// The history of the wallet changed, the new transactions are in the [`TxCache`] of the
+    /// session
+    Updated {
+        /// The scan of the wallet
+        response: WaterfallResponse,
+        /// The transactions not seen by the previous scan, in order of appearance
+        new_txids: Vec<Txid>,
+    },
+    /// The poll of the wallet failed, it's retried with the backoff of its [`PollSchedule`]
+    Failed(Error),
+}
+
+#[derive(Debug)]
+struct SessionWallet {
+    descriptor: String,
+    schedule: PollSchedule,
+    next_poll: Instant,
+    last: Option<WaterfallResponse>,
+    sender: Sender<WalletEvent>,
+}
+
+#[derive(Debug, Default)]
+struct SessionInner {
+    wallets: BTreeMap<WalletId, SessionWallet>,
+    next_id: usize,
+}
+
+/// Wallets polled with one client, see the [module documentation](self).
+///
+/// Cloning a [`Session`] returns a handle to the same session.
+#[derive(Debug, Clone)]
+pub struct Session {
+    inner: Arc<Mutex<SessionInner>>,
+    stagger: Duration,
+    header_cache: HeaderCache,
+    tx_cache: TxCache,
+    registry: Option<DescriptorRegistry>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new(HeaderCache::default(), TxCache::default())
+    }
+}
+
+impl Session {
+    /// Create a session without wallets sharing the given caches
+    pub fn new(header_cache: HeaderCache, tx_cache: TxCache) -> Self {
+        Session {
+            inner: Arc::default(),
+            stagger: DEFAULT_SESSION_STAGGER,
+            header_cache,
+            tx_cache,
+            registry: None,
+        }
+    }
+
+    /// Set the delay between the first polls of two wallets
+    pub fn stagger(mut self, stagger: Duration) -> Self {
+        self.stagger = stagger;
+        self
+    }
+
+    /// Poll the wallets with the settings registered in `registry`
+    pub fn registry(mut self, registry: DescriptorRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SessionInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The header cache shared by the wallets
+    pub fn header_cache(&self) -> &HeaderCache {
+        &self.header_cache
+    }
+
+    /// The transactions of the wallets fetched so far
+    pub fn tx_cache(&self) -> &TxCache {
+        &self.tx_cache
+    }
+
+    /// Set the header and transaction caches and the descriptor registry of `builder` to the
+    /// ones of the session
+    pub fn configure(&self, builder: Builder) -> Builder {
+        let builder = builder
+            .header_cache(self.header_cache.clone())
+            .tx_cache(self.tx_cache.clone());
+        match &self.registry {
+            Some(registry) => builder.descriptor_registry(registry.clone()),
+            None => builder,
+        }
+    }
+
+    /// Add the wallet of `descriptor` polled every `poll_interval` seconds if registered with
+    /// one, with the default [`PollSchedule`] otherwise
+    pub fn add_wallet(&self, descriptor: &str) -> (WalletId, Receiver<WalletEvent>) {
+        let interval = self
+            .registry
+            .as_ref()
+            .and_then(|registry| registry.get(descriptor)?.poll_interval);
+        let schedule = match interval {
+            Some(seconds) => {
+                let interval = Duration::from_secs(seconds);
+                PollSchedule::new(interval, interval)
+            }
+            None => PollSchedule::default(),
+        };
+        self.add_wallet_with_schedule(descriptor, schedule)
+    }
+
+    /// Add the wallet of `descriptor` polled with `schedule`, returning its id and the receiver
+    /// of its events.
+    ///
+    /// The first poll of the wallet is due [`Self::stagger`] after the one of the previously
+    /// added wallet.
+    pub fn add_wallet_with_schedule(
+        &self,
+        descriptor: &str,
+        schedule: PollSchedule,
+    ) -> (WalletId, Receiver<WalletEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let mut inner = self.lock();
+        let id = WalletId(inner.next_id);
+        inner.next_id += 1;
+        let now = Instant::now();
+        let next_poll = inner
+            .wallets
+            .values()
+            .map(|wallet| wallet.next_poll + self.stagger)
+            .max()
+            .map_or(now, |after_last| after_last.max(now));
+        let wallet = SessionWallet {
+            descriptor: descriptor.to_string(),
+            schedule,
+            next_poll,
+            last: None,
+            sender,
+        };
+        inner.wallets.insert(id, wallet);
+        (id, receiver)
+    }
+
+    /// Remove the wallet `id`, returning false if it isn't in the session
+    pub fn remove_wallet(&self, id: WalletId) -> bool {
+        self.lock().wallets.remove(&id).is_some()
+    }
+
+    /// The wallets of the session with their descriptors
+    pub fn wallets(&self) -> Vec<(WalletId, String)> {
+        let inner = self.lock();
+        inner
+            .wallets
+            .iter()
+            .map(|(id, wallet)| (*id, wallet.descriptor.clone()))
+            .collect()
+    }
+
+    /// The last successful scan of the wallet `id`
+    pub fn last_response(&self, id: WalletId) -> Option<WaterfallResponse> {
+        self.lock().wallets.get(&id)?.last.clone()
+    }
+
+    /// Poll the wallet `id` at once and fast again, e.g. after a broadcast
+    pub fn reset(&self, id: WalletId) {
+        if let Some(wallet) = self.lock().wallets.get_mut(&id) {
+            wallet.schedule.reset();
+            wallet.next_poll = Instant::now();
+        }
+    }
+
+    /// When the next wallet is due for a poll, `None` if the session has no wallets
+    pub fn next_poll(&self) -> Option<Instant> {
+        let inner = self.lock();
+        inner.wallets.values().map(|wallet| wallet.next_poll).min()
+    }
+
+    /// The wallets due for a poll at `now` with their descriptors, most overdue first
+    pub(crate) fn due(&self, now: Instant) -> Vec<(WalletId, String)> {
+        let inner = self.lock();
+        let mut due: Vec<_> = inner
+            .wallets
+            .iter()
+            .filter(|(_, wallet)| wallet.next_poll <= now)
+            .collect();
+        due.sort_by_key(|(_, wallet)| wallet.next_poll);
+        due.into_iter()
+            .map(|(id, wallet)| (*id, wallet.descriptor.clone()))
+            .collect()
+    }
+
+    /// The transactions of `response` missing from the transaction cache
+    pub(crate) fn missing_txids(&self, response: &WaterfallResponse) -> Vec<Txid> {
+        let txids = response.txids().into_iter();
+        txids.filter(|txid| !self.tx_cache.contains(txid)).collect()
+    }
+
+    /// Store the result of the poll of the wallet `id` finished at `now`, schedule its next
+    /// poll and send its event if the history changed or the poll failed
+    pub(crate) fn record(
+        &self,
+        id: WalletId,
+        result: Result<WaterfallResponse, Error>,
+        now: Instant,
+    ) {
+        let mut inner = self.lock();
+        let wallet = match inner.wallets.get_mut(&id) {
+            Some(wallet) => wallet,
+            None => return,
+        };
+        let event = match result {
+            Ok(response) => {
+                let delay = wallet.schedule.observe_response(&response);
+                wallet.next_poll = now + delay;
+                let last = wallet.last.replace(response.clone());
+                let changed = last.as_ref().map(WaterfallResponse::content_hash)
+                    != Some(response.content_hash());
+                let seen = last.map(|last| last.txids()).unwrap_or_default();
+                let new_txids = response
+                    .txids()
+                    .into_iter()
+                    .filter(|txid| !seen.contains(txid))
+                    .collect();
+                changed.then(|| WalletEvent::Updated {
+                    response,
+                    new_txids,
+                })
+            }
+            Err(e) => {
+                wallet.next_poll = now + wallet.schedule.backoff();
+                Some(WalletEvent::Failed(e))
+            }
+        };
+        if let Some(event) = event {
+            // The receiver may have been dropped, the wallet is still polled
+            let _ = wallet.sender.send(event);
+        }
+    }
+}
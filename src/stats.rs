@@ -0,0 +1,113 @@
+//! Counters of requests and connections, to tell whether connections are reused.
+//!
+//! Every new connection costs a TCP handshake, a TLS handshake for `https` servers and a
+//! circuit setup through Tor, which dominates the latency of small requests. Comparing
+//! [`ConnectionStats::connections`] with [`ConnectionStats::requests`] shows how many
+//! requests reused an open connection instead.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+struct StatsInner {
+    requests: AtomicU64,
+    connections: AtomicU64,
+}
+
+/// Shared counters of the requests sent and connections opened by the clients using it.
+///
+/// Cloning a [`ConnectionStats`] returns a handle to the same counters.
+///
+/// The blocking client opens a connection for every request. The async client keeps idle
+/// connections open, see [`crate::Builder::pool_idle_timeout`], and counts them only with the
+/// `async-connection-stats` feature.
+///
+/// Only connection reuse is measured: TLS session resumption on a new connection is up to the
+/// TLS backend and isn't counted.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionStats {
+    inner: Arc<StatsInner>,
+}
+
+impl ConnectionStats {
+    /// Create counters starting at zero
+    pub fn new() -> Self {
+        ConnectionStats::default()
+    }
+
+    /// Number of requests sent, including retries
+    pub fn requests(&self) -> u64 {
+        self.inner.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of connections opened
+    pub fn connections(&self) -> u64 {
+        self.inner.connections.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests sent over an already open connection
+    pub fn reused(&self) -> u64 {
+        self.requests().saturating_sub(self.connections())
+    }
+
+    /// The fraction of requests that reused a connection, `None` before the first request
+    pub fn reuse_ratio(&self) -> Option<f64> {
+        match self.requests() {
+            0 => None,
+            requests => Some(self.reused() as f64 / requests as f64),
+        }
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    pub(crate) fn record_request(&self) {
+        self.inner.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(any(feature = "blocking", feature = "async-connection-stats"))]
+    pub(crate) fn record_connection(&self) {
+        self.inner.connections.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A connector layer counting the connections opened by a [`reqwest::Client`]
+#[cfg(all(feature = "async-connection-stats", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub(crate) struct CountConnections(pub(crate) ConnectionStats);
+
+#[cfg(all(feature = "async-connection-stats", not(target_arch = "wasm32")))]
+impl<S> tower_layer::Layer<S> for CountConnections {
+    type Service = CountingConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountingConnector {
+            inner,
+            stats: self.0.clone(),
+        }
+    }
+}
+
+#[cfg(all(feature = "async-connection-stats", not(target_arch = "wasm32")))]
+#[derive(Debug, Clone)]
+pub(crate) struct CountingConnector<S> {
+    inner: S,
+    stats: ConnectionStats,
+}
+
+#[cfg(all(feature = "async-connection-stats", not(target_arch = "wasm32")))]
+impl<S: tower_service::Service<R>, R> tower_service::Service<R> for CountingConnector<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.stats.record_connection();
+        self.inner.call(request)
+    }
+}
@@ -0,0 +1,98 @@
+//! Subscription to the new blocks of the server.
+//!
+//! The Waterfalls server has no long-poll nor SSE endpoint announcing blocks, so a
+//! [`BlockSubscription`] polls the next block height following a [`PollSchedule`]: a new block is
+//! requested soon after the previous one and less often while none comes.
+
+use bitcoin::BlockHash;
+#[cfg(feature = "async-stream")]
+use futures_util::stream::{self, Stream};
+
+use crate::r#async::{AsyncClient, Sleeper};
+use crate::{BlockMeta, Error, Height, PollSchedule, Timestamp};
+
+/// The blocks connected to the tip of a server, see
+/// [`AsyncClient::subscribe_blocks`].
+#[derive(Debug, Clone)]
+pub struct BlockSubscription<S> {
+    client: AsyncClient<S>,
+    schedule: PollSchedule,
+    next_height: u32,
+    last_hash: Option<BlockHash>,
+}
+
+impl<S: Sleeper> BlockSubscription<S> {
+    pub(crate) fn new(client: AsyncClient<S>, from_height: u32) -> Self {
+        BlockSubscription {
+            client,
+            schedule: PollSchedule::default(),
+            next_height: from_height,
+            last_hash: None,
+        }
+    }
+
+    /// Set the schedule of the polls of the next block
+    pub fn schedule(mut self, schedule: PollSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// The height of the next block to be returned
+    pub fn next_height(&self) -> u32 {
+        self.next_height
+    }
+
+    /// Wait for the next block.
+    ///
+    /// If the last returned block has been reorged out, the caches of the client are
+    /// invalidated above its parent, see [`AsyncClient::invalidate_above`], and its replacement
+    /// is returned at the same height. After an error the next call resumes from the last
+    /// returned block.
+    ///
+    /// With the [`crate::SingleShot`] sleeper the call doesn't wait for a block not
+    /// found yet, it fails with its `404` [`Error::HttpResponse`] instead.
+    pub async fn next_block(&mut self) -> Result<BlockMeta, Error> {
+        loop {
+            let block_hash = match self.client.get_block_hash(self.next_height).await {
+                Ok(block_hash) => block_hash,
+                Err(Error::HttpResponse { status: 404, .. }) if S::RETRIES => {
+                    let delay = self.schedule.next_delay();
+                    self.client.cancellable(S::sleep(delay)).await?;
+                    self.schedule.backoff();
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let header = self.client.get_header_by_hash(&block_hash).await?;
+            if self
+                .last_hash
+                .map_or(false, |last| last != header.prev_blockhash)
+            {
+                // The last returned block was reorged out, the fork is below it
+                self.client
+                    .invalidate_above(self.next_height.saturating_sub(2));
+                self.next_height -= 1;
+                self.last_hash = None;
+                continue;
+            }
+            let block = BlockMeta {
+                b: block_hash,
+                t: Timestamp(u64::from(header.time)),
+                h: Height(self.next_height),
+            };
+            self.schedule.reset();
+            self.next_height += 1;
+            self.last_hash = Some(block_hash);
+            return Ok(block);
+        }
+    }
+
+    /// Turn the subscription into an endless [`Stream`] of [`Self::next_block`] results
+    #[cfg(feature = "async-stream")]
+    pub fn into_stream(self) -> impl Stream<Item = Result<BlockMeta, Error>> {
+        stream::unfold(self, |mut subscription| async move {
+            let block = subscription.next_block().await;
+            Some((block, subscription))
+        })
+    }
+}
@@ -0,0 +1,80 @@
+//! End-to-end test helpers, with the `test-env` feature.
+//!
+//! [`launch`] starts a `bitcoind` node, from the executable in the `BITCOIND_EXEC` environment
+//! variable, and a waterfalls server indexing it. [`blocking_client`] and [`async_client`]
+//! return clients pointed at the server, and the `convert_*` functions turn the types returned
+//! by [`TestEnv`] into their `rust-bitcoin` counterparts:
+//!
+//! ```no_run
+//! # async fn run() {
+//! use waterfalls_client::test_env;
+//!
+//! let env = test_env::launch().await;
+//! let client = test_env::async_client(&env);
+//! let address = env.get_new_address(None);
+//! let txid = test_env::convert_txid(env.send_to(&address, 10_000));
+//! env.node_generate(1).await;
+//! assert!(client.get_tx(&txid).await.unwrap().is_some());
+//! env.shutdown().await;
+//! # }
+//! ```
+
+pub use waterfalls::test_env::TestEnv;
+
+#[cfg(all(feature = "async", feature = "tokio"))]
+use crate::AsyncClient;
+#[cfg(feature = "blocking")]
+use crate::BlockingClient;
+use crate::Builder;
+
+/// Environment variable holding the path of the `bitcoind` executable
+pub const BITCOIND_EXEC: &str = "BITCOIND_EXEC";
+
+/// Launch a regtest `bitcoind` and a waterfalls server indexing it
+///
+/// Panics if [`BITCOIND_EXEC`] isn't set.
+pub async fn launch() -> TestEnv {
+    let exe = std::env::var(BITCOIND_EXEC).expect("BITCOIND_EXEC must be set");
+    waterfalls::test_env::launch(exe, waterfalls::be::Family::Bitcoin).await
+}
+
+/// A builder pointed at the server of `env`
+pub fn builder(env: &TestEnv) -> Builder {
+    Builder::new(env.base_url())
+}
+
+/// A blocking client of the server of `env`
+#[cfg(feature = "blocking")]
+pub fn blocking_client(env: &TestEnv) -> BlockingClient {
+    builder(env).build_blocking()
+}
+
+/// An async client of the server of `env`
+#[cfg(all(feature = "async", feature = "tokio"))]
+pub fn async_client(env: &TestEnv) -> AsyncClient {
+    builder(env)
+        .build_async()
+        .expect("default builder is valid")
+}
+
+/// The `rust-bitcoin` txid of a txid returned by [`TestEnv`]
+pub fn convert_txid(txid: waterfalls::be::Txid) -> bitcoin::Txid {
+    txid.bitcoin()
+}
+
+/// The `rust-bitcoin` transaction of a transaction returned by [`TestEnv`], `None` for Elements
+/// ones
+pub fn convert_transaction(tx: &waterfalls::be::Transaction) -> Option<&bitcoin::Transaction> {
+    match tx {
+        waterfalls::be::Transaction::Bitcoin(tx) => Some(tx),
+        waterfalls::be::Transaction::Elements(_) => None,
+    }
+}
+
+/// The `rust-bitcoin` address of an address returned by [`TestEnv`], `None` for Elements ones
+pub fn convert_address(address: &waterfalls::be::Address) -> Option<&bitcoin::Address> {
+    match address {
+        waterfalls::be::Address::Bitcoin(address) => Some(address),
+        waterfalls::be::Address::Elements(_) => None,
+    }
+}
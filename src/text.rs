@@ -0,0 +1,40 @@
+//! Decoding of the bodies of text endpoints.
+//!
+//! Endpoints such as `/blocks/tip/hash` or `/block-height/:height` answer with a short text
+//! body. Some reverse proxies prepend a byte order mark or pad the body with bytes which aren't
+//! UTF-8, and the strict decoding then fails. With [`TextDecoding::Tolerant`], set with
+//! [`crate::Builder::text_decoding`], the clients strip the byte order mark, decode invalid
+//! UTF-8 lossily with a warning and trim the padding around the text.
+
+use std::borrow::Cow;
+
+use log::warn;
+
+/// How the clients decode the bodies of text endpoints, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextDecoding {
+    /// Decode the body as returned, failing if it isn't UTF-8
+    #[default]
+    Strict,
+    /// Strip a byte order mark, replace invalid UTF-8 and trim the padding
+    Tolerant,
+}
+
+/// Decode `body` as [`TextDecoding::Tolerant`] does
+pub fn decode_tolerant(body: &[u8]) -> String {
+    let body = body.strip_prefix(b"\xef\xbb\xbf").unwrap_or(body);
+    let text = match std::str::from_utf8(body) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(e) => {
+            warn!(
+                "text response invalid at byte {}, decoding it lossily",
+                e.valid_up_to()
+            );
+            String::from_utf8_lossy(body)
+        }
+    };
+    let padding = |c: char| {
+        c.is_whitespace() || c == '\0' || c == '\u{feff}' || c == char::REPLACEMENT_CHARACTER
+    };
+    text.trim_matches(padding).to_string()
+}
@@ -0,0 +1,177 @@
+//! Soft-fail scans collecting non-fatal anomalies as warnings.
+//!
+//! Batch jobs scanning many wallets would rather finish and report the issues afterwards than
+//! stop at the first one. [`crate::BlockingClient::waterfalls_soft`] and
+//! [`crate::BlockingClient::waterfalls_all_pages_soft`], and their async counterparts, return a
+//! [`Warned`] result listing as [`Warning`]s the anomalies other calls fail on or ignore:
+//!
+//! - history entries which can't be parsed are skipped instead of failing the whole response
+//! - a tip differing from the tip endpoint, or older than [`STALE_TIP_AGE`]
+//! - a scan stopping short of the requested gap limit
+//! - pages whose number or tip isn't the one of the first page
+//!
+//! Network and HTTP errors still fail the call, the result would be empty anyway.
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bitcoin::BlockHash;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use serde::Deserialize;
+
+use crate::Height;
+#[cfg(any(feature = "blocking", feature = "async"))]
+use crate::{BlockMeta, TxSeen, WaterfallResponse};
+
+/// Seconds since its timestamp after which the tip of a response is reported as stale
+pub const STALE_TIP_AGE: u64 = 2 * 60 * 60;
+
+/// A non-fatal anomaly met by a soft-fail call, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The entry at `entry` in the history of the script at `script` of `key` couldn't be
+    /// parsed and was skipped
+    SkippedEntry {
+        key: String,
+        script: usize,
+        entry: usize,
+    },
+    /// The tip of the response differs from the one of the tip endpoint
+    TipInconsistent {
+        waterfalls: BlockHash,
+        tip: BlockHash,
+    },
+    /// The last block of the server at `height` is `age` seconds old
+    StaleTip { height: Height, age: u64 },
+    /// The server didn't scan up to the `required` index of the gap limit, it stopped at
+    /// `scanned`
+    ScanTruncated { required: u32, scanned: u32 },
+    /// The server returned page `returned` when asked for page `requested`
+    PageMismatch { requested: u32, returned: u16 },
+    /// The tip of `page` differs from the one of the first page, the chain moved during the
+    /// scan
+    PageTipChanged {
+        page: u32,
+        first: BlockHash,
+        tip: BlockHash,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// The result of a soft-fail call with the [`Warning`]s met on the way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warned<T> {
+    /// The result
+    pub value: T,
+    /// The anomalies met, empty if none
+    pub warnings: Vec<Warning>,
+}
+
+impl<T> Warned<T> {
+    /// Returns true if no anomaly was met
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// A history entry which may not parse as a [`TxSeen`]
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaybeTxSeen {
+    Parsed(TxSeen),
+    Malformed(serde::de::IgnoredAny),
+}
+
+/// A [`WaterfallResponse`] whose malformed history entries don't fail the parse
+#[cfg(any(feature = "blocking", feature = "async"))]
+#[derive(Deserialize)]
+pub(crate) struct LenientResponse {
+    txs_seen: BTreeMap<String, Vec<Vec<MaybeTxSeen>>>,
+    page: u16,
+    tip: Option<BlockHash>,
+    tip_meta: Option<BlockMeta>,
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl LenientResponse {
+    /// The response without the malformed entries, reported as [`Warning::SkippedEntry`]
+    pub(crate) fn into_warned(self) -> Warned<WaterfallResponse> {
+        let mut warnings = vec![];
+        let mut txs_seen = BTreeMap::new();
+        for (key, scripts) in self.txs_seen {
+            let mut histories = Vec::with_capacity(scripts.len());
+            for (script, entries) in scripts.into_iter().enumerate() {
+                let mut history = Vec::with_capacity(entries.len());
+                for (entry, maybe) in entries.into_iter().enumerate() {
+                    match maybe {
+                        MaybeTxSeen::Parsed(seen) => history.push(seen),
+                        MaybeTxSeen::Malformed(_) => warnings.push(Warning::SkippedEntry {
+                            key: key.clone(),
+                            script,
+                            entry,
+                        }),
+                    }
+                }
+                histories.push(history);
+            }
+            txs_seen.insert(key, histories);
+        }
+        let value = WaterfallResponse {
+            txs_seen,
+            page: self.page,
+            tip: self.tip,
+            tip_meta: self.tip_meta,
+        };
+        Warned { value, warnings }
+    }
+}
+
+/// The [`Warning::StaleTip`] of `response` if its tip is older than [`STALE_TIP_AGE`] plus
+/// `tolerance` seconds, `elapsed_since` giving the seconds since a timestamp
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn stale_tip(
+    response: &WaterfallResponse,
+    tolerance: u64,
+    elapsed_since: impl Fn(u64) -> u64,
+) -> Option<Warning> {
+    let meta = response.tip_meta.as_ref()?;
+    let age = elapsed_since(meta.t.0);
+    (age > STALE_TIP_AGE.saturating_add(tolerance)).then_some(Warning::StaleTip {
+        height: meta.h,
+        age,
+    })
+}
+
+/// The warnings of the response to page `requested` of a scan whose first page had tip
+/// `first`
+#[cfg(any(feature = "blocking", feature = "async"))]
+pub(crate) fn page_warnings(
+    requested: u32,
+    first: Option<BlockHash>,
+    page: &WaterfallResponse,
+) -> Vec<Warning> {
+    let mut warnings = vec![];
+    if u32::from(page.page) != requested {
+        warnings.push(Warning::PageMismatch {
+            requested,
+            returned: page.page,
+        });
+    }
+    if let (Some(first), Some(tip)) = (first, page.tip_hash()) {
+        if first != tip {
+            warnings.push(Warning::PageTipChanged {
+                page: requested,
+                first,
+                tip,
+            });
+        }
+    }
+    warnings
+}
@@ -0,0 +1,305 @@
+//! A minimal wallet watcher, scanning a descriptor and logging what changes, with the
+//! `watcher` feature.
+//!
+//! A [`Watcher`] holds the state of one wallet between polls: its transactions, so that each
+//! poll fetches only the new ones, its last balance and the log of its [`WatchEvent`]s.
+//! [`crate::BlockingClient::poll_watcher`] and its async counterpart scan the descriptor, fetch
+//! the missing transactions and return the events of the poll. The state can be saved with
+//! [`Watcher::write_to`] and restored at the next start with [`Watcher::read_from`].
+//!
+//! A transaction with [`FINAL_CONFIRMATIONS`] is considered final: the watcher keeps the height
+//! of the last final block as its [`Watcher::watermark`] and, the server having no height
+//! filter, ignores the history confirmed at or below it when looking for new transactions. With
+//! [`Watcher::retention`] the events and transactions older than the retention window below the
+//! watermark are pruned, keeping the state of a long running watcher bounded. Transactions still
+//! needed to compute the balance are kept. History found below the watermark after the fact,
+//! e.g. after a reorg deeper than [`FINAL_CONFIRMATIONS`], isn't reported.
+//!
+//! [`run`] is a command line runner polling a server and printing the events, used by the
+//! `watcher` example:
+//!
+//! ```text
+//! cargo run --example watcher --features watcher -- \
+//!     https://waterfalls.example.com/api "wpkh(xpub.../<0;1>/*)" --state watcher.json
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{Read, Write};
+
+use bitcoin::{Amount, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+use crate::{Height, WaterfallResponse};
+
+/// Default seconds between two polls of [`run`]
+pub const DEFAULT_WATCH_INTERVAL: u64 = 30;
+
+/// Confirmations after which a [`Watcher`] considers a transaction final
+pub const FINAL_CONFIRMATIONS: u32 = 6;
+
+/// A change of a wallet seen by a [`Watcher`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchEvent {
+    /// A transaction of the wallet seen for the first time
+    Tx(Txid),
+    /// The balance changed
+    Balance {
+        /// The balance at the previous poll, zero at the first one
+        previous: Amount,
+        /// The balance now
+        current: Amount,
+    },
+}
+
+impl fmt::Display for WatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchEvent::Tx(txid) => write!(f, "tx {txid}"),
+            WatchEvent::Balance { previous, current } => write!(
+                f,
+                "balance {} -> {} sat",
+                previous.to_sat(),
+                current.to_sat()
+            ),
+        }
+    }
+}
+
+/// The state of a watched wallet, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watcher {
+    descriptor: String,
+    txs: BTreeMap<Txid, Transaction>,
+    balance: Option<Amount>,
+    events: Vec<WatchEvent>,
+    /// The tip height when each event was logged, zero if unknown
+    #[serde(default)]
+    logged_at: Vec<Height>,
+    #[serde(default)]
+    watermark: Option<Height>,
+    #[serde(default)]
+    retention: Option<u32>,
+}
+
+impl Watcher {
+    /// Watch `descriptor`, not polled yet
+    pub fn new(descriptor: &str) -> Self {
+        Watcher {
+            descriptor: descriptor.to_string(),
+            txs: BTreeMap::new(),
+            balance: None,
+            events: vec![],
+            logged_at: vec![],
+            watermark: None,
+            retention: None,
+        }
+    }
+
+    /// Prune the events and transactions older than `blocks` below the watermark
+    pub fn retention(mut self, blocks: u32) -> Self {
+        self.retention = Some(blocks);
+        self
+    }
+
+    /// The watched descriptor
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// The balance at the last poll, `None` before the first one
+    pub fn balance(&self) -> Option<Amount> {
+        self.balance
+    }
+
+    /// Every event seen since the watcher was created, oldest first
+    pub fn events(&self) -> &[WatchEvent] {
+        &self.events
+    }
+
+    /// The height of the last block with [`FINAL_CONFIRMATIONS`] at the last poll, `None`
+    /// before a response with the tip height
+    pub fn watermark(&self) -> Option<Height> {
+        self.watermark
+    }
+
+    /// The height at and below which the history is pruned, `None` without retention
+    fn cutoff(&self) -> Option<Height> {
+        self.watermark?.checked_sub(self.retention?)
+    }
+
+    /// The transactions of the wallet
+    pub fn txs(&self) -> &BTreeMap<Txid, Transaction> {
+        &self.txs
+    }
+
+    /// The transactions of `response` not fetched yet, except the pruned ones
+    pub fn missing_txids(&self, response: &WaterfallResponse) -> Vec<Txid> {
+        let txids = match self.cutoff() {
+            Some(cutoff) => response.txids_above(cutoff),
+            None => response.txids(),
+        };
+        let txids = txids.into_iter();
+        txids.filter(|txid| !self.txs.contains_key(txid)).collect()
+    }
+
+    /// Add fetched transactions of the wallet
+    pub fn insert_txs(&mut self, txs: impl IntoIterator<Item = Transaction>) {
+        self.txs
+            .extend(txs.into_iter().map(|tx| (tx.compute_txid(), tx)));
+    }
+
+    /// Record a scan of the descriptor, whose transactions were inserted, returning the
+    /// events it causes
+    pub fn observe(&mut self, response: &WaterfallResponse) -> Vec<WatchEvent> {
+        let seen: Vec<_> = self.events.iter().filter_map(event_txid).collect();
+        let mut events: Vec<_> = response
+            .txids_above(self.watermark.unwrap_or_default())
+            .into_iter()
+            .filter(|txid| !seen.contains(txid))
+            .map(WatchEvent::Tx)
+            .collect();
+        let summary = response.summary(&self.txs);
+        let previous = self
+            .balance
+            .replace(summary.balance)
+            .unwrap_or(Amount::ZERO);
+        if previous != summary.balance {
+            events.push(WatchEvent::Balance {
+                previous,
+                current: summary.balance,
+            });
+        }
+
+        let tip = response.tip_meta.as_ref().map(|meta| meta.h);
+        self.logged_at.resize(self.events.len(), Height::ZERO);
+        self.logged_at
+            .extend(events.iter().map(|_| tip.unwrap_or_default()));
+        self.events.extend(events.iter().cloned());
+        if let Some(watermark) = tip.and_then(|tip| tip.checked_sub(FINAL_CONFIRMATIONS - 1)) {
+            self.watermark = self.watermark.max(Some(watermark));
+        }
+
+        if let Some(cutoff) = self.cutoff() {
+            let recent: BTreeSet<_> = response.txids_above(cutoff).into_iter().collect();
+            let logged = self.events.drain(..).zip(self.logged_at.drain(..));
+            let (events, logged_at) = logged
+                .filter(|(event, logged_at)| {
+                    *logged_at > cutoff || event_txid(event).map_or(false, |t| recent.contains(&t))
+                })
+                .unzip();
+            self.events = events;
+            self.logged_at = logged_at;
+
+            // The balance needs the transactions funding the unspent outputs and the ones
+            // spending their other outputs
+            let funding: BTreeSet<_> = summary.utxos.iter().map(|u| u.outpoint.txid).collect();
+            self.txs.retain(|txid, tx| {
+                recent.contains(txid)
+                    || funding.contains(txid)
+                    || tx
+                        .input
+                        .iter()
+                        .any(|input| funding.contains(&input.previous_output.txid))
+            });
+        }
+        events
+    }
+
+    /// Write the state as JSON
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer, self).map_err(|e| Error::Io(e.into()))
+    }
+
+    /// Read a state written by [`Self::write_to`]
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, Error> {
+        serde_json::from_reader(reader).map_err(|e| Error::Io(e.into()))
+    }
+}
+
+fn event_txid(event: &WatchEvent) -> Option<Txid> {
+    match event {
+        WatchEvent::Tx(txid) => Some(*txid),
+        WatchEvent::Balance { .. } => None,
+    }
+}
+
+/// Watch a wallet from command line arguments, printing the events to `out`:
+///
+/// `<base url> <descriptor> [--state <path>] [--interval <seconds>] [--retention <blocks>]
+/// [--once]`
+///
+/// The state is restored from and saved to `--state` if given, pruned with `--retention`. Polls are
+/// [`DEFAULT_WATCH_INTERVAL`] seconds apart unless `--interval` is set, failures are printed
+/// and retried at the next poll. With `--once` the runner polls once and returns the error of
+/// the poll if any.
+pub fn run<I, W>(args: I, mut out: W) -> Result<(), Error>
+where
+    I: IntoIterator<Item = String>,
+    W: Write,
+{
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    let usage = || {
+        Error::InvalidConfiguration(
+            "usage: <base url> <descriptor> [--state <path>] [--interval <seconds>] \
+             [--retention <blocks>] [--once]"
+                .to_string(),
+        )
+    };
+    let mut args = args.into_iter();
+    let url = args.next().ok_or_else(usage)?;
+    let descriptor = args.next().ok_or_else(usage)?;
+    let (mut state, mut interval, mut once) = (None, DEFAULT_WATCH_INTERVAL, false);
+    let mut retention = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--state" => state = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--interval" => {
+                let value = args.next().ok_or_else(usage)?;
+                interval = value.parse().map_err(|_| usage())?;
+            }
+            "--retention" => {
+                let value = args.next().ok_or_else(usage)?;
+                retention = Some(value.parse().map_err(|_| usage())?);
+            }
+            "--once" => once = true,
+            _ => return Err(usage()),
+        }
+    }
+
+    let mut watcher = match &state {
+        Some(path) if path.exists() => Watcher::read_from(File::open(path)?)?,
+        _ => Watcher::new(&descriptor),
+    };
+    if let Some(blocks) = retention {
+        watcher = watcher.retention(blocks);
+    }
+    if watcher.descriptor() != descriptor {
+        return Err(Error::InvalidConfiguration(
+            "the state belongs to another descriptor".to_string(),
+        ));
+    }
+    let client = crate::Builder::new(&url).try_build_blocking()?;
+    loop {
+        match client.poll_watcher(&mut watcher) {
+            Ok(events) => {
+                for event in events {
+                    writeln!(out, "{event}")?;
+                }
+                if let Some(path) = &state {
+                    watcher.write_to(File::create(path)?)?;
+                }
+            }
+            Err(e) if !once => writeln!(out, "error {e}")?,
+            Err(e) => return Err(e),
+        }
+        if once {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
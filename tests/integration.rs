@@ -4,11 +4,14 @@
 //! an actual waterfalls server instance.
 
 #[cfg(any(feature = "blocking", feature = "async"))]
-use waterfalls_client::{Builder, WaterfallResponse};
+use waterfalls_client::{Builder, HeaderCache, WaterfallResponse, WaterfallsQuery};
 
 #[cfg(any(feature = "blocking", feature = "async"))]
 use bitcoin::Network;
 
+#[cfg(any(feature = "blocking", feature = "async"))]
+use std::str::FromStr;
+
 #[cfg(any(feature = "blocking", feature = "async"))]
 async fn launch_test_env() -> waterfalls::test_env::TestEnv {
     let exe = std::env::var("BITCOIND_EXEC").expect("BITCOIND_EXEC must be set");
@@ -396,6 +399,137 @@ async fn test_waterfalls_addresses_async() {
     test_env.shutdown().await;
 }
 
+#[cfg(feature = "blocking")]
+#[test]
+fn test_waterfalls_query_blocking() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    let test_env = rt.block_on(launch_test_env());
+    let url = test_env.base_url();
+
+    let builder = Builder::new(url);
+    let blocking_client = builder.build_blocking();
+
+    let descriptor = "wpkh(tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/<0;1>/*)";
+
+    // Create a standalone address and send some funds to it
+    let waterfalls_address = test_env.get_new_address(None);
+    let bitcoin_address = convert_address(&waterfalls_address)
+        .expect("Expected Bitcoin address from test environment");
+    let _txid = test_env.send_to(&waterfalls_address, 10000);
+    rt.block_on(test_env.node_generate(1));
+
+    // Test combined descriptor + addresses query
+    let query = WaterfallsQuery::descriptor(descriptor).with_addresses(&[bitcoin_address.clone()]);
+    let result_blocking = blocking_client.waterfalls_query(&query).unwrap();
+
+    assert!(!result_blocking.is_empty());
+    assert!(result_blocking.tip_meta.is_some());
+
+    rt.block_on(test_env.shutdown());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_waterfalls_query_async() {
+    let test_env = launch_test_env().await;
+    let url = test_env.base_url();
+
+    let builder = Builder::new(url);
+    let async_client = builder.build_async().unwrap();
+
+    let descriptor = "wpkh(tpubDC8msFGeGuwnKG9Upg7DM2b4DaRqg3CUZa5g8v2SRQ6K4NSkxUgd7HsL2XVWbVm39yBA4LAxysQAm397zwQSQoQgewGiYZqrA9DsP4zbQ1M/<0;1>/*)";
+
+    // Create a standalone address and send some funds to it
+    let waterfalls_address = test_env.get_new_address(None);
+    let bitcoin_address = convert_address(&waterfalls_address)
+        .expect("Expected Bitcoin address from test environment");
+    let _txid = test_env.send_to(&waterfalls_address, 10000);
+    test_env.node_generate(1).await;
+
+    // Test combined descriptor + addresses query
+    let query = WaterfallsQuery::descriptor(descriptor).with_addresses(&[bitcoin_address.clone()]);
+    let result_async = async_client.waterfalls_query(&query).await.unwrap();
+
+    assert!(!result_async.is_empty());
+    assert!(result_async.tip_meta.is_some());
+
+    test_env.shutdown().await;
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn test_hydrate_blocking() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    let test_env = rt.block_on(launch_test_env());
+    let url = test_env.base_url();
+
+    let builder = Builder::new(url);
+    let blocking_client = builder.build_blocking();
+
+    let waterfalls_address = test_env.get_new_address(None);
+    let bitcoin_address = convert_address(&waterfalls_address)
+        .expect("Expected Bitcoin address from test environment");
+    let txid = convert_txid(test_env.send_to(&waterfalls_address, 10000));
+    rt.block_on(test_env.node_generate(1));
+
+    let response = blocking_client
+        .waterfalls_addresses(&[bitcoin_address.clone()])
+        .unwrap();
+    let hydrated = blocking_client.hydrate(&response);
+    assert!(hydrated.is_complete());
+    assert!(hydrated
+        .items
+        .iter()
+        .any(|(id, tx)| *id == txid && tx.compute_txid() == txid));
+
+    // A missing transaction doesn't fail the others
+    let missing = bitcoin::Txid::from_str(&"00".repeat(32)).unwrap();
+    let result = blocking_client.get_txs(&[missing, txid]);
+    assert_eq!(result.items.len(), 1);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].0, missing);
+
+    rt.block_on(test_env.shutdown());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_hydrate_async() {
+    let test_env = launch_test_env().await;
+    let url = test_env.base_url();
+
+    let builder = Builder::new(url);
+    let async_client = builder.build_async().unwrap();
+
+    let waterfalls_address = test_env.get_new_address(None);
+    let bitcoin_address = convert_address(&waterfalls_address)
+        .expect("Expected Bitcoin address from test environment");
+    let txid = convert_txid(test_env.send_to(&waterfalls_address, 10000));
+    test_env.node_generate(1).await;
+
+    let response = async_client
+        .waterfalls_addresses(&[bitcoin_address.clone()])
+        .await
+        .unwrap();
+    let hydrated = async_client.hydrate(&response).await;
+    assert!(hydrated.is_complete());
+    assert!(hydrated
+        .items
+        .iter()
+        .any(|(id, tx)| *id == txid && tx.compute_txid() == txid));
+
+    // A missing transaction doesn't fail the others
+    let missing = bitcoin::Txid::from_str(&"00".repeat(32)).unwrap();
+    let result = async_client.get_txs(&[missing, txid]).await;
+    assert_eq!(result.items.len(), 1);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].0, missing);
+
+    test_env.shutdown().await;
+}
+
 #[cfg(feature = "blocking")]
 #[test]
 fn test_waterfalls_version_blocking() {
@@ -622,6 +756,57 @@ async fn test_client_with_headers_async() {
     test_env.shutdown().await;
 }
 
+#[cfg(feature = "blocking")]
+#[test]
+fn test_sync_headers_blocking() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    let test_env = rt.block_on(launch_test_env());
+    let url = test_env.base_url();
+
+    let cache = HeaderCache::default();
+    let builder = Builder::new(url).header_cache(cache.clone());
+    let blocking_client = builder.build_blocking();
+
+    rt.block_on(test_env.node_generate(3));
+
+    // Validate the regtest chain from genesis
+    let mut chain = blocking_client.header_chain(Network::Regtest, 0);
+    blocking_client.sync_headers(&mut chain, 3).unwrap();
+
+    assert_eq!(chain.tip_height(), 3);
+    assert_eq!(chain.tip_hash(), blocking_client.get_block_hash(3).unwrap());
+    assert_eq!(cache.hash_at_height(3), Some(chain.tip_hash()));
+
+    rt.block_on(test_env.shutdown());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_sync_headers_async() {
+    let test_env = launch_test_env().await;
+    let url = test_env.base_url();
+
+    let cache = HeaderCache::default();
+    let builder = Builder::new(url).header_cache(cache.clone());
+    let async_client = builder.build_async().unwrap();
+
+    test_env.node_generate(3).await;
+
+    // Validate the regtest chain from genesis
+    let mut chain = async_client.header_chain(Network::Regtest, 0);
+    async_client.sync_headers(&mut chain, 3).await.unwrap();
+
+    assert_eq!(chain.tip_height(), 3);
+    assert_eq!(
+        chain.tip_hash(),
+        async_client.get_block_hash(3).await.unwrap()
+    );
+    assert_eq!(cache.hash_at_height(3), Some(chain.tip_hash()));
+
+    test_env.shutdown().await;
+}
+
 //
 // Production Tests using real URLs and descriptors
 //